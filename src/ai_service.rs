@@ -1,8 +1,11 @@
 use async_trait::async_trait;
 use dialoguer::{Confirm, Select};
 use log::{debug, info, warn};
-use crate::config::{AIService, Config, AIServiceConfig};
-use crate::terminal_format::print_progress;
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+use std::time::Duration;
+use crate::config::{AIService, AuthScheme, Config, AIServiceConfig, RetryConfig};
+use crate::terminal_format::{print_progress, StreamPrinter};
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Message {
@@ -20,32 +23,358 @@ pub trait AiService: Send + Sync {
     }
 
     async fn chat(&self, system_prompt: &str, user_content: &str) -> anyhow::Result<String>;
+
+    /// 流式对话，每收到一段内容就调用一次 `on_token`，最终返回完整内容
+    ///
+    /// 不支持流式输出的服务使用默认实现：等待完整响应后一次性回调。
+    async fn chat_stream(
+        &self,
+        system_prompt: &str,
+        user_content: &str,
+        on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+    ) -> anyhow::Result<String> {
+        let result = self.chat(system_prompt, user_content).await?;
+        on_token(&result);
+        Ok(result)
+    }
+
+    /// 以尽量小的开销检查服务是否可达、认证是否有效，返回简要说明
+    ///
+    /// 默认发送一条极短的对话请求，提供模型列表接口的服务改为只读查询。
+    async fn probe(&self) -> anyhow::Result<String> {
+        self.chat("Reply with OK.", "ping").await?;
+        Ok("对话请求成功".to_string())
+    }
+
+    /// 之后的请求只发送一次，失败时直接返回错误，不重试也不询问，用于检查服务状态
+    fn disable_retry(&mut self) {}
 }
 
 pub use AiService as Translator; // 为了兼容性，保留原有的 Translator 类型
 
-pub struct DeepSeekTranslator {
-    api_key: String,
-    endpoint: String,
-    model: String,
+/// 基于 HTTP 的 AI 服务共用的请求客户端
+///
+/// 统一处理超时重试、HTTP 状态检查和错误信息提取，
+/// 各个服务只负责构造请求体和解析响应内容。
+/// 同一个服务的所有请求共用一个 reqwest 客户端，重试和分块总结时可以复用连接和 TLS 会话。
+pub struct HttpChatClient {
+    name: String,
+    client: reqwest::Client,
     timeout_seconds: u64,
     max_tokens: u64,
+    retry: RetryConfig,
+    /// 重试次数用完后是否在终端中询问用户
+    interactive: bool,
 }
 
-pub struct OpenAITranslator {
+impl HttpChatClient {
+    pub fn new(name: impl Into<String>) -> Self {
+        let config = crate::config::Config::load_effective().ok();
+        let timeout_seconds = config.as_ref().map(|c| c.timeout_seconds).unwrap_or(20);
+        // 流式响应的总耗时可能远超超时时间，客户端只限制连接时间，完整请求的超时在发送时单独设置
+        let client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(timeout_seconds))
+            .build()
+            .unwrap_or_default();
+        Self {
+            name: name.into(),
+            client,
+            timeout_seconds,
+            max_tokens: config.as_ref().map(|c| c.max_tokens).unwrap_or(2048),
+            retry: config.map(|c| c.retry).unwrap_or_default(),
+            interactive: true,
+        }
+    }
+
+    /// 请求只发送一次，失败时直接返回错误
+    pub fn disable_retry(&mut self) {
+        self.retry.max_attempts = 1;
+        self.interactive = false;
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn max_tokens(&self) -> u64 {
+        self.max_tokens
+    }
+
+    /// 发送 JSON 请求并返回解析后的 JSON 响应，非 2xx 状态码会转换为错误
+    pub async fn post_json(
+        &self,
+        url: &str,
+        headers: &[(&str, String)],
+        body: &serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        let timeout = Duration::from_secs(self.timeout_seconds);
+        let response = self.send(url, headers, body, Some(timeout)).await?;
+        let result: serde_json::Value = serde_json::from_str(&response.text().await?)?;
+        debug!("响应内容: {}", serde_json::to_string_pretty(&result)?);
+        Ok(result)
+    }
+
+    /// 发送 GET 请求并返回 JSON 响应，不重试，用于模型列表等只读查询
+    ///
+    /// 接口不存在（HTTP 404/405）时返回 None，由调用方决定是否改用其他方式。
+    pub async fn get_json(
+        &self,
+        url: &str,
+        headers: &[(&str, String)],
+    ) -> anyhow::Result<Option<serde_json::Value>> {
+        let mut request = self.client.get(url).timeout(Duration::from_secs(self.timeout_seconds));
+        for (key, value) in headers {
+            request = request.header(*key, value);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        let text = response.text().await?;
+        debug!("响应内容: {}", text);
+        if status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::METHOD_NOT_ALLOWED {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            return Err(anyhow::anyhow!("API 调用失败 (HTTP {}): {}",
+                status.as_u16(), extract_error_message(&text)));
+        }
+        Ok(Some(serde_json::from_str(&text)?))
+    }
+
+    /// 发送流式请求，按行回调响应内容（SSE 的每一行或 NDJSON 的每条记录）
+    ///
+    /// 流式响应的总耗时可能远超超时时间，因此超时只作用于连接和相邻两段数据之间的间隔。
+    pub async fn post_stream(
+        &self,
+        url: &str,
+        headers: &[(&str, String)],
+        body: &serde_json::Value,
+        on_line: &mut (dyn FnMut(&str) -> anyhow::Result<()> + Send),
+    ) -> anyhow::Result<()> {
+        let timeout = Duration::from_secs(self.timeout_seconds);
+        let mut response = self.send(url, headers, body, None).await?;
+        let mut buffer = LineBuffer::default();
+        loop {
+            let chunk = tokio::time::timeout(timeout, response.chunk()).await
+                .map_err(|_| anyhow::anyhow!("等待响应数据超时"))??;
+            let Some(bytes) = chunk else { break };
+            for line in buffer.push(&bytes) {
+                debug!("收到流式数据: {}", line);
+                on_line(&line)?;
+            }
+        }
+        if let Some(line) = buffer.finish() {
+            on_line(&line)?;
+        }
+        Ok(())
+    }
+
+    /// 发送请求并检查状态码
+    ///
+    /// 超时、连接失败和 HTTP 429/5xx 按重试策略自动重试；重试次数用完或 `Retry-After` 超过自动等待的上限后，
+    /// 只有在终端中运行时才会询问用户是否继续，避免在 CI 或 IDE 钩子中卡住。
+    /// `timeout` 为整个请求的超时时间，流式请求传入 None。
+    async fn send(
+        &self,
+        url: &str,
+        headers: &[(&str, String)],
+        body: &serde_json::Value,
+        timeout: Option<Duration>,
+    ) -> anyhow::Result<reqwest::Response> {
+        let ai_host = url.split('/').nth(2).unwrap_or(&self.name).to_string();
+        print_progress(&format!("正在请求 {} 进行AI对话", ai_host), None);
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let mut request = self.client.post(url).json(body);
+            if let Some(timeout) = timeout {
+                request = request.timeout(timeout);
+            }
+            for (key, value) in headers {
+                request = request.header(*key, value);
+            }
+
+            let (error, retry_after) = match request.send().await {
+                Ok(response) => {
+                    print_progress(&format!("正在请求 {} 进行AI对话", ai_host), Some(100));
+                    debug!("收到响应: {:#?}", response);
+
+                    let status = response.status();
+                    if status.is_success() {
+                        return Ok(response);
+                    }
+
+                    let retry_after = parse_retry_after(response.headers());
+                    let text = response.text().await?;
+                    debug!("响应内容: {}", text);
+                    let error = anyhow::anyhow!("API 调用失败 (HTTP {}): {}",
+                        status.as_u16(), extract_error_message(&text));
+                    if !is_retryable_status(status) {
+                        return Err(error);
+                    }
+                    (error, retry_after)
+                }
+                Err(e) if e.is_timeout() => {
+                    warn!("请求超时: {}", e);
+                    (anyhow::anyhow!("请求超时"), None)
+                }
+                Err(e) if e.is_connect() => {
+                    warn!("连接失败: {}", e);
+                    (e.into(), None)
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            let delay = self.retry.delay(attempt, retry_after);
+            match delay {
+                Ok(delay) if attempt < self.retry.max_attempts => {
+                    warn!("{}，{:.1} 秒后进行第 {}/{} 次重试",
+                        error, delay.as_secs_f64(), attempt + 1, self.retry.max_attempts);
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+                _ => {}
+            }
+
+            if !self.interactive {
+                return Err(error);
+            }
+            // Retry-After 超过自动等待的上限时，同样交给用户决定是否等待后重试
+            let long_wait = delay.err();
+            if !std::io::stdin().is_terminal() {
+                return Err(match long_wait {
+                    Some(wait) => error.context(format!("服务要求 {} 秒后再重试，超过了自动等待的上限 {} 秒",
+                        wait.as_secs(), self.retry.max_retry_after_secs)),
+                    None => error.context(format!("已重试 {} 次仍然失败", attempt)),
+                });
+            }
+            let prompt = match long_wait {
+                Some(wait) => format!("{}，服务要求 {} 秒后再重试，是否等待后重试？", error, wait.as_secs()),
+                None => format!("{}，是否重试？", error),
+            };
+            if !Confirm::with_theme(&dialoguer::theme::ColorfulTheme::default())
+                .with_prompt(prompt)
+                .default(true)
+                .interact()? {
+                return Err(error);
+            }
+            if let Some(wait) = long_wait {
+                tokio::time::sleep(wait).await;
+            }
+            attempt = 0;
+        }
+    }
+}
+
+/// 限流和服务端错误通常是暂时的，值得重试
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// 解析 `Retry-After` 响应头，只支持秒数格式
+fn parse_retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?;
+    value.trim().parse::<u64>().ok().map(Duration::from_secs)
+}
+
+/// 将分块到达的字节流切分为完整的行，避免多字节字符被截断
+#[derive(Default)]
+struct LineBuffer {
+    pending: Vec<u8>,
+}
+
+impl LineBuffer {
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(bytes);
+        let mut lines = Vec::new();
+        while let Some(pos) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\r', '\n']);
+            if !line.is_empty() {
+                lines.push(line.to_string());
+            }
+        }
+        lines
+    }
+
+    fn finish(&mut self) -> Option<String> {
+        let line = String::from_utf8_lossy(&self.pending).trim().to_string();
+        self.pending.clear();
+        if line.is_empty() { None } else { Some(line) }
+    }
+}
+
+/// 提取 SSE `data:` 行的内容，其他行（event、注释等）返回 None
+fn sse_data(line: &str) -> Option<&str> {
+    line.strip_prefix("data:").map(str::trim_start)
+}
+
+/// 从错误响应中提取可读的错误信息，兼容各家服务不同的错误格式
+fn extract_error_message(body: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(json) => json["error"]["message"].as_str()
+            .or_else(|| json["error"].as_str())
+            .or_else(|| json["message"].as_str())
+            .unwrap_or("未知错误")
+            .to_string(),
+        Err(_) if !body.trim().is_empty() => body.trim().to_string(),
+        Err(_) => "未知错误".to_string(),
+    }
+}
+
+/// 兼容 OpenAI `/chat/completions` 接口的服务商
+///
+/// 新增兼容服务商时只需要在 [`OpenAICompatibleProvider::for_config`] 中
+/// 添加一项配置，默认地址和模型在 [`AIService`] 中维护。
+#[derive(Debug, Clone)]
+pub struct OpenAICompatibleProvider {
+    pub name: String,
+    pub default_endpoint: &'static str,
+    pub default_model: &'static str,
+    pub temperature: Option<f64>,
+    pub auth_scheme: AuthScheme,
+    pub extra_headers: BTreeMap<String, String>,
+}
+
+impl OpenAICompatibleProvider {
+    pub fn for_config(config: &AIServiceConfig) -> Option<Self> {
+        let temperature = match config.service {
+            AIService::DeepSeek | AIService::OpenAI | AIService::Grok | AIService::Custom
+            | AIService::LlamaCpp => None,
+            AIService::Qwen => Some(0.1),
+            AIService::Claude | AIService::Copilot | AIService::Gemini | AIService::Ollama => return None,
+        };
+
+        Some(Self {
+            name: config.display_name(),
+            default_endpoint: config.service.default_endpoint(),
+            default_model: config.service.default_model(),
+            temperature,
+            auth_scheme: config.auth_scheme.clone(),
+            extra_headers: config.extra_headers.clone(),
+        })
+    }
+}
+
+pub struct OpenAICompatibleTranslator {
+    client: HttpChatClient,
     api_key: String,
     endpoint: String,
     model: String,
-    timeout_seconds: u64,
-    max_tokens: u64,
+    temperature: Option<f64>,
+    auth_scheme: AuthScheme,
+    extra_headers: BTreeMap<String, String>,
 }
 
+/// Anthropic Messages API 版本号
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
 pub struct ClaudeTranslator {
+    client: HttpChatClient,
     api_key: String,
     endpoint: String,
     model: String,
-    timeout_seconds: u64,
-    max_tokens: u64,
 }
 
 pub struct CopilotTranslator {
@@ -53,62 +382,31 @@ pub struct CopilotTranslator {
     model: String,
 }
 
-pub struct GeminiTranslator {
-    api_key: String,
+pub struct OllamaTranslator {
+    client: HttpChatClient,
     endpoint: String,
     model: String,
-    timeout_seconds: u64,
-    max_tokens: u64,
-}
-
-pub struct GrokTranslator {
-    api_key: String,
-    endpoint: String,
-    model: String,
-    timeout_seconds: u64,
-    max_tokens: u64,
 }
 
-pub struct QwenTranslator {
+pub struct GeminiTranslator {
+    client: HttpChatClient,
     api_key: String,
     endpoint: String,
     model: String,
-    timeout_seconds: u64,
-    max_tokens: u64,
-}
-
-impl DeepSeekTranslator {
-    pub fn new(config: &AIServiceConfig) -> Self {
-        Self {
-            api_key: config.api_key.clone(),
-            endpoint: config.api_endpoint.clone()
-                .unwrap_or_else(|| "https://api.deepseek.com/v1".into()),
-            model: config.model.clone()
-                .unwrap_or_else(|| "deepseek-chat".into()),
-            timeout_seconds: crate::config::Config::load()
-                .map(|c| c.timeout_seconds)
-                .unwrap_or(20),
-            max_tokens: crate::config::Config::load()
-                .map(|c| c.max_tokens)
-                .unwrap_or(2048),
-        }
-    }
 }
 
-impl OpenAITranslator {
-    pub fn new(config: &AIServiceConfig) -> Self {
+impl OpenAICompatibleTranslator {
+    pub fn new(provider: OpenAICompatibleProvider, config: &AIServiceConfig) -> Self {
         Self {
+            client: HttpChatClient::new(provider.name),
             api_key: config.api_key.clone(),
             endpoint: config.api_endpoint.clone()
-                .unwrap_or_else(|| "https://api.openai.com/v1".into()),
+                .unwrap_or_else(|| provider.default_endpoint.into()),
             model: config.model.clone()
-                .unwrap_or_else(|| "gpt-3.5-turbo".into()),
-            timeout_seconds: crate::config::Config::load()
-                .map(|c| c.timeout_seconds)
-                .unwrap_or(20),
-            max_tokens: crate::config::Config::load()
-                .map(|c| c.max_tokens)
-                .unwrap_or(2048),
+                .unwrap_or_else(|| provider.default_model.into()),
+            temperature: provider.temperature,
+            auth_scheme: provider.auth_scheme,
+            extra_headers: provider.extra_headers,
         }
     }
 }
@@ -116,17 +414,12 @@ impl OpenAITranslator {
 impl ClaudeTranslator {
     pub fn new(config: &AIServiceConfig) -> Self {
         Self {
+            client: HttpChatClient::new("Claude"),
             api_key: config.api_key.clone(),
             endpoint: config.api_endpoint.clone()
-                .unwrap_or_else(|| "https://api.anthropic.com/v1".into()),
+                .unwrap_or_else(|| AIService::Claude.default_endpoint().into()),
             model: config.model.clone()
-                .unwrap_or_else(|| "claude-3-sonnet-20240229".into()),
-            timeout_seconds: crate::config::Config::load()
-                .map(|c| c.timeout_seconds)
-                .unwrap_or(20),
-            max_tokens: crate::config::Config::load()
-                .map(|c| c.max_tokens)
-                .unwrap_or(2048),
+                .unwrap_or_else(|| AIService::Claude.default_model().into()),
         }
     }
 }
@@ -137,56 +430,27 @@ impl CopilotTranslator {
     }
 }
 
-impl GeminiTranslator {
+impl OllamaTranslator {
     pub fn new(config: &AIServiceConfig) -> Self {
         Self {
-            api_key: config.api_key.clone(),
+            client: HttpChatClient::new("Ollama"),
             endpoint: config.api_endpoint.clone()
-                .unwrap_or_else(|| "https://generativelanguage.googleapis.com/v1beta".into()),
+                .unwrap_or_else(|| AIService::Ollama.default_endpoint().into()),
             model: config.model.clone()
-                .unwrap_or_else(|| "gemini-2.0-flash".into()),
-            timeout_seconds: crate::config::Config::load()
-                .map(|c| c.timeout_seconds)
-                .unwrap_or(20),
-            max_tokens: crate::config::Config::load()
-                .map(|c| c.max_tokens)
-                .unwrap_or(2048),
+                .unwrap_or_else(|| AIService::Ollama.default_model().into()),
         }
     }
 }
 
-impl GrokTranslator {
-    pub fn new(config: &AIServiceConfig) -> Self {
-        Self {
-            api_key: config.api_key.clone(),
-            endpoint: config.api_endpoint.clone()
-                .unwrap_or_else(|| "https://api.x.ai/v1".into()),
-            model: config.model.clone()
-                .unwrap_or_else(|| "grok-3-latest".into()),
-            timeout_seconds: crate::config::Config::load()
-                .map(|c| c.timeout_seconds)
-                .unwrap_or(20),
-            max_tokens: crate::config::Config::load()
-                .map(|c| c.max_tokens)
-                .unwrap_or(2048),
-        }
-    }
-}
-
-impl QwenTranslator {
+impl GeminiTranslator {
     pub fn new(config: &AIServiceConfig) -> Self {
         Self {
+            client: HttpChatClient::new("Gemini"),
             api_key: config.api_key.clone(),
             endpoint: config.api_endpoint.clone()
-                .unwrap_or_else(|| "https://dashscope.aliyuncs.com/compatible-mode/v1".into()),
+                .unwrap_or_else(|| AIService::Gemini.default_endpoint().into()),
             model: config.model.clone()
-                .unwrap_or_else(|| "qwen-plus".into()),
-            timeout_seconds: crate::config::Config::load()
-                .map(|c| c.timeout_seconds)
-                .unwrap_or(20),
-            max_tokens: crate::config::Config::load()
-                .map(|c| c.max_tokens)
-                .unwrap_or(2048),
+                .unwrap_or_else(|| AIService::Gemini.default_model().into()),
         }
     }
 }
@@ -243,15 +507,8 @@ fn get_translation_prompt(text: &str) -> String {
     prompt
 }
 
-#[async_trait]
-impl AiService for DeepSeekTranslator {
-    async fn chat(&self, system_prompt: &str, user_content: &str) -> anyhow::Result<String> {
-        debug!("使用 DeepSeek，API Endpoint: {}", self.endpoint);
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(self.timeout_seconds))
-            .build()?;
-
-        let url = format!("{}/chat/completions", self.endpoint);
+impl OpenAICompatibleTranslator {
+    fn request_body(&self, system_prompt: &str, user_content: &str, stream: bool) -> anyhow::Result<serde_json::Value> {
         let messages = vec![
             serde_json::json!({
                 "role": "system",
@@ -262,209 +519,199 @@ impl AiService for DeepSeekTranslator {
                 "content": user_content
             })
         ];
-        debug!("发送给 DeepSeek 的消息:\n{}", serde_json::to_string_pretty(&messages)?);
-        let body = serde_json::json!({
+        debug!("发送给 {} 的消息:\n{}", self.client.name(), serde_json::to_string_pretty(&messages)?);
+        let mut body = serde_json::json!({
             "model": self.model,
             "messages": messages,
-            "max_tokens": self.max_tokens
+            "max_tokens": self.client.max_tokens()
         });
+        if let Some(temperature) = self.temperature {
+            body["temperature"] = serde_json::json!(temperature);
+        }
+        if stream {
+            body["stream"] = serde_json::json!(true);
+        }
+        Ok(body)
+    }
 
-        let ai_host = match url.split('/').nth(2) {
-            Some(host) => host,
-            None => "api.deepseek.com",
-        };
-        print_progress(&format!("正在请求 {} 进行AI对话", ai_host), None);
+    fn headers(&self) -> Vec<(&str, String)> {
+        let mut headers: Vec<(&str, String)> = self.extra_headers.iter()
+            .map(|(key, value)| (key.as_str(), value.clone()))
+            .collect();
+        match self.auth_scheme {
+            _ if self.api_key.is_empty() => {}
+            AuthScheme::Bearer => headers.push(("Authorization", format!("Bearer {}", self.api_key))),
+            AuthScheme::ApiKey => headers.push(("api-key", self.api_key.clone())),
+            AuthScheme::None => {}
+        }
+        headers
+    }
+}
 
-        loop {
-            match client
-                .post(&url)
-                .header("Authorization", format!("Bearer {}", self.api_key))
-                .json(&body)
-                .send()
-                .await
-            {
-                Ok(response) => {
-                    print_progress(&format!("正在请求 {} 进行AI对话", ai_host), Some(100));
-                    debug!("收到响应: {:#?}", response);
+#[async_trait]
+impl AiService for OpenAICompatibleTranslator {
+    fn disable_retry(&mut self) {
+        self.client.disable_retry();
+    }
 
-                    if !response.status().is_success() {
-                        let error_json = response.json::<serde_json::Value>().await?;
-                        debug!("响应内容: {}", serde_json::to_string_pretty(&error_json)?);
-                        return Err(anyhow::anyhow!("API 调用失败: {}",
-                            error_json["error"]["message"].as_str().unwrap_or("未知错误")));
-                    }
+    async fn chat(&self, system_prompt: &str, user_content: &str) -> anyhow::Result<String> {
+        debug!("使用 {}，API Endpoint: {}", self.client.name(), self.endpoint);
 
-                    let result = response.json::<serde_json::Value>().await?;
-                    debug!("响应内容: {}", serde_json::to_string_pretty(&result)?);
+        let url = format!("{}/chat/completions", self.endpoint);
+        let body = self.request_body(system_prompt, user_content, false)?;
+        let result = self.client.post_json(&url, &self.headers(), &body).await?;
 
-                    let response = result["choices"][0]["message"]["content"]
-                        .as_str()
-                        .unwrap_or_default();
-                    return Ok(response.to_string());
-                }
-                Err(e) if e.is_timeout() => {
-                    warn!("请求超时: {}", e);
-                    if !Confirm::with_theme(&dialoguer::theme::ColorfulTheme::default())
-                        .with_prompt("请求超时，是否重试？")
-                        .default(true)
-                        .interact()? {
-                        return Err(anyhow::anyhow!("请求超时"));
-                    }
-                    continue;
-                }
-                Err(e) => return Err(e.into()),
+        let response = result["choices"][0]["message"]["content"]
+            .as_str()
+            .unwrap_or_default();
+        Ok(response.to_string())
+    }
+
+    async fn chat_stream(
+        &self,
+        system_prompt: &str,
+        user_content: &str,
+        on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+    ) -> anyhow::Result<String> {
+        debug!("使用 {} 流式输出，API Endpoint: {}", self.client.name(), self.endpoint);
+
+        let url = format!("{}/chat/completions", self.endpoint);
+        let body = self.request_body(system_prompt, user_content, true)?;
+        let mut content = String::new();
+        self.client.post_stream(&url, &self.headers(), &body, &mut |line| {
+            let Some(data) = sse_data(line) else { return Ok(()) };
+            if data == "[DONE]" {
+                return Ok(());
+            }
+            let event: serde_json::Value = serde_json::from_str(data)?;
+            if let Some(token) = event["choices"][0]["delta"]["content"].as_str() {
+                content.push_str(token);
+                on_token(token);
+            }
+            Ok(())
+        }).await?;
+        Ok(content)
+    }
+
+    async fn probe(&self) -> anyhow::Result<String> {
+        // 部分网关没有实现模型列表接口，此时退回到一次极短的对话
+        let url = format!("{}/models", self.endpoint);
+        match self.client.get_json(&url, &self.headers()).await? {
+            Some(result) => Ok(describe_models(&result["data"], "id", &self.model)),
+            None => {
+                self.chat("Reply with OK.", "ping").await?;
+                Ok("对话请求成功".to_string())
             }
         }
     }
 }
 
-#[async_trait]
-impl AiService for OpenAITranslator {
-    async fn chat(&self, system_prompt: &str, user_content: &str) -> anyhow::Result<String> {
-        debug!("使用 OpenAI，API Endpoint: {}", self.endpoint);
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(self.timeout_seconds))
-            .build()?;
+/// 根据模型列表生成检查结果说明，并指出配置的模型是否在列表中
+fn describe_models(models: &serde_json::Value, name_key: &str, model: &str) -> String {
+    let names: Vec<&str> = models.as_array()
+        .map(|models| models.iter().filter_map(|m| m[name_key].as_str()).collect())
+        .unwrap_or_default();
+    if names.is_empty() || names.iter().any(|name| name.trim_start_matches("models/") == model) {
+        format!("认证通过，可用模型 {} 个", names.len())
+    } else {
+        format!("认证通过，但模型列表中没有 {}", model)
+    }
+}
 
-        let url = format!("{}/chat/completions", self.endpoint);
+impl ClaudeTranslator {
+    /// 构造 Messages API 请求体，系统提示词放在顶层 `system` 字段中
+    fn request_body(&self, system_prompt: &str, user_content: &str, stream: bool) -> anyhow::Result<serde_json::Value> {
         let messages = vec![
-            serde_json::json!({
-                "role": "system",
-                "content": system_prompt
-            }),
             serde_json::json!({
                 "role": "user",
                 "content": user_content
             })
         ];
-        debug!("发送给 OpenAI 的消息:\n{}", serde_json::to_string_pretty(&messages)?);
-        let body = serde_json::json!({
+        debug!("发送给 Claude 的消息:\n{}", serde_json::to_string_pretty(&messages)?);
+        let mut body = serde_json::json!({
             "model": self.model,
+            "system": system_prompt,
             "messages": messages,
-            "max_tokens": self.max_tokens
+            "max_tokens": self.client.max_tokens()
         });
+        if stream {
+            body["stream"] = serde_json::json!(true);
+        }
+        Ok(body)
+    }
 
-        let ai_host = match url.split('/').nth(2) {
-            Some(host) => host,
-            None => "api.openai.com",
-        };
-        print_progress(&format!("正在请求 {} 进行AI对话", ai_host), None);
-
-        loop {
-            match client
-                .post(&url)
-                .header("Authorization", format!("Bearer {}", self.api_key))
-                .json(&body)
-                .send()
-                .await
-            {
-                Ok(response) => {
-                    print_progress(&format!("正在请求 {} 进行AI对话", ai_host), Some(100));
-                    debug!("收到响应: {:#?}", response);
-
-                    if !response.status().is_success() {
-                        let error_json = response.json::<serde_json::Value>().await?;
-                        debug!("响应内容: {}", serde_json::to_string_pretty(&error_json)?);
-                        return Err(anyhow::anyhow!("API 调用失败: {}",
-                            error_json["error"]["message"].as_str().unwrap_or("未知错误")));
-                    }
-
-                    let result = response.json::<serde_json::Value>().await?;
-                    debug!("响应内容: {}", serde_json::to_string_pretty(&result)?);
+    fn headers(&self) -> Vec<(&str, String)> {
+        vec![
+            ("x-api-key", self.api_key.clone()),
+            ("anthropic-version", ANTHROPIC_VERSION.to_string()),
+        ]
+    }
 
-                    let response = result["choices"][0]["message"]["content"]
-                        .as_str()
-                        .unwrap_or_default();
-                    return Ok(response.to_string());
-                }
-                Err(e) if e.is_timeout() => {
-                    warn!("请求超时: {}", e);
-                    if !Confirm::with_theme(&dialoguer::theme::ColorfulTheme::default())
-                        .with_prompt("请求超时，是否重试？")
-                        .default(true)
-                        .interact()? {
-                        return Err(anyhow::anyhow!("请求超时"));
-                    }
-                    continue;
-                }
-                Err(e) => return Err(e.into()),
-            }
-        }
+    /// 拼接响应中所有 `text` 类型的内容块
+    fn response_text(result: &serde_json::Value) -> String {
+        result["content"]
+            .as_array()
+            .map(|blocks| blocks.iter()
+                .filter(|block| block["type"] == "text")
+                .filter_map(|block| block["text"].as_str())
+                .collect())
+            .unwrap_or_default()
     }
 }
 
 #[async_trait]
 impl AiService for ClaudeTranslator {
+    fn disable_retry(&mut self) {
+        self.client.disable_retry();
+    }
+
     async fn chat(&self, system_prompt: &str, user_content: &str) -> anyhow::Result<String> {
         debug!("使用 Claude，API Endpoint: {}", self.endpoint);
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(self.timeout_seconds))
-            .build()?;
 
         let url = format!("{}/messages", self.endpoint);
-        let messages = vec![
-            serde_json::json!({
-                "role": "system",
-                "content": system_prompt
-            }),
-            serde_json::json!({
-                "role": "user",
-                "content": user_content
-            })
-        ];
-        debug!("发送给 Claude 的消息:\n{}", serde_json::to_string_pretty(&messages)?);
-        let body = serde_json::json!({
-            "model": self.model,
-            "messages": messages,
-            "max_tokens": self.max_tokens
-        });
+        let body = self.request_body(system_prompt, user_content, false)?;
+        let result = self.client.post_json(&url, &self.headers(), &body).await?;
 
-        let ai_host = match url.split('/').nth(2) {
-            Some(host) => host,
-            None => "api.anthropic.com",
-        };
-        print_progress(&format!("正在请求 {} 进行AI对话", ai_host), None);
+        Ok(Self::response_text(&result))
+    }
 
-        loop {
-            match client
-                .post(&url)
-                .header("Authorization", format!("Bearer {}", self.api_key))
-                .header("anthropic-version", "2023-06-01")
-                .json(&body)
-                .send()
-                .await
-            {
-                Ok(response) => {
-                    print_progress(&format!("正在请求 {} 进行AI对话", ai_host), Some(100));
-                    debug!("收到响应: {:#?}", response);
+    async fn chat_stream(
+        &self,
+        system_prompt: &str,
+        user_content: &str,
+        on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+    ) -> anyhow::Result<String> {
+        debug!("使用 Claude 流式输出，API Endpoint: {}", self.endpoint);
 
-                    if !response.status().is_success() {
-                        let error_json = response.json::<serde_json::Value>().await?;
-                        debug!("响应内容: {}", serde_json::to_string_pretty(&error_json)?);
-                        return Err(anyhow::anyhow!("API 调用失败: {}",
-                            error_json["error"]["message"].as_str().unwrap_or("未知错误")));
+        let url = format!("{}/messages", self.endpoint);
+        let body = self.request_body(system_prompt, user_content, true)?;
+        let mut content = String::new();
+        self.client.post_stream(&url, &self.headers(), &body, &mut |line| {
+            let Some(data) = sse_data(line) else { return Ok(()) };
+            let event: serde_json::Value = serde_json::from_str(data)?;
+            match event["type"].as_str() {
+                Some("content_block_delta") => {
+                    if let Some(token) = event["delta"]["text"].as_str() {
+                        content.push_str(token);
+                        on_token(token);
                     }
-
-                    let result = response.json::<serde_json::Value>().await?;
-                    debug!("响应内容: {}", serde_json::to_string_pretty(&result)?);
-
-                    let response = result["content"][0]["text"]
-                        .as_str()
-                        .unwrap_or_default();
-                    return Ok(response.to_string());
                 }
-                Err(e) if e.is_timeout() => {
-                    warn!("请求超时: {}", e);
-                    if !Confirm::with_theme(&dialoguer::theme::ColorfulTheme::default())
-                        .with_prompt("请求超时，是否重试？")
-                        .default(true)
-                        .interact()? {
-                        return Err(anyhow::anyhow!("请求超时"));
-                    }
-                    continue;
+                Some("error") => {
+                    return Err(anyhow::anyhow!("API 调用失败: {}",
+                        event["error"]["message"].as_str().unwrap_or("未知错误")));
                 }
-                Err(e) => return Err(e.into()),
+                _ => {}
             }
+            Ok(())
+        }).await?;
+        Ok(content)
+    }
+
+    async fn probe(&self) -> anyhow::Result<String> {
+        let url = format!("{}/models", self.endpoint);
+        match self.client.get_json(&url, &self.headers()).await? {
+            Some(result) => Ok(describe_models(&result["data"], "id", &self.model)),
+            None => Err(anyhow::anyhow!("{} 不存在，请检查 API 地址", url)),
         }
     }
 }
@@ -494,87 +741,15 @@ impl AiService for CopilotTranslator {
             .unwrap_or_default();
         Ok(result)
     }
-}
-
-#[async_trait]
-impl AiService for GeminiTranslator {
-    async fn chat(&self, system_prompt: &str, user_content: &str) -> anyhow::Result<String> {
-        debug!("使用 Gemini，API Endpoint: {}", self.endpoint);
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(self.timeout_seconds))
-            .build()?;
-
-        let url = format!("{}/models/{}:generateContent?key={}", self.endpoint, self.model, self.api_key);
-        let prompt = format!("{}\n\n{}", system_prompt, user_content);
-        debug!("发送给 Gemini 的内容:\n{}", prompt);
-        let body = serde_json::json!({
-            "contents": [{
-                "parts": [{
-                    "text": prompt
-                }]
-            }],
-            "generationConfig": {
-                "maxOutputTokens": self.max_tokens
-            }
-        });
-
-        let ai_host = match url.split('/').nth(2) {
-            Some(host) => host,
-            None => "generativelanguage.googleapis.com",
-        };
-        print_progress(&format!("正在请求 {} 进行AI对话", ai_host), None);
-
-        loop {
-            match client
-                .post(&url)
-                .json(&body)
-                .send()
-                .await
-            {
-                Ok(response) => {
-                    print_progress(&format!("正在请求 {} 进行AI对话", ai_host), Some(100));
-                    debug!("收到响应: {:#?}", response);
-
-                    if !response.status().is_success() {
-                        let error_json = response.json::<serde_json::Value>().await?;
-                        debug!("响应内容: {}", serde_json::to_string_pretty(&error_json)?);
-                        return Err(anyhow::anyhow!("API 调用失败: {}",
-                            error_json["error"]["message"].as_str().unwrap_or("未知错误")));
-                    }
-
-                    let result = response.json::<serde_json::Value>().await?;
-                    debug!("响应内容: {}", serde_json::to_string_pretty(&result)?);
 
-                    let response = result["candidates"][0]["content"]["parts"][0]["text"]
-                        .as_str()
-                        .unwrap_or_default();
-                    return Ok(response.to_string());
-                }
-                Err(e) if e.is_timeout() => {
-                    warn!("请求超时: {}", e);
-                    if !Confirm::with_theme(&dialoguer::theme::ColorfulTheme::default())
-                        .with_prompt("请求超时，是否重试？")
-                        .default(true)
-                        .interact()? {
-                        return Err(anyhow::anyhow!("请求超时"));
-                    }
-                    continue;
-                }
-                Err(e) => return Err(e.into()),
-            }
-        }
+    async fn probe(&self) -> anyhow::Result<String> {
+        let models = self.client.get_models().await?;
+        Ok(format!("令牌有效，可用模型 {} 个", models.len()))
     }
 }
 
-#[async_trait]
-impl AiService for GrokTranslator {
-    async fn chat(&self, system_prompt: &str, user_content: &str) -> anyhow::Result<String> {
-        debug!("使用 Grok，API Endpoint: {}", self.endpoint);
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(self.timeout_seconds))
-            .build()?;
-
-        let url = format!("{}/chat/completions", self.endpoint);
+impl OllamaTranslator {
+    fn request_body(&self, system_prompt: &str, user_content: &str, stream: bool) -> anyhow::Result<serde_json::Value> {
         let messages = vec![
             serde_json::json!({
                 "role": "system",
@@ -585,146 +760,200 @@ impl AiService for GrokTranslator {
                 "content": user_content
             })
         ];
-        debug!("发送给 Grok 的消息:\n{}", serde_json::to_string_pretty(&messages)?);
-        let body = serde_json::json!({
+        debug!("发送给 Ollama 的消息:\n{}", serde_json::to_string_pretty(&messages)?);
+        Ok(serde_json::json!({
             "model": self.model,
             "messages": messages,
-            "max_tokens": self.max_tokens
-        });
+            "stream": stream,
+            "options": {
+                "num_predict": self.client.max_tokens()
+            }
+        }))
+    }
+}
 
-        let ai_host = match url.split('/').nth(2) {
-            Some(host) => host,
-            None => "api.x.ai",
-        };
-        print_progress(&format!("正在请求 {} 进行AI对话", ai_host), None);
+#[async_trait]
+impl AiService for OllamaTranslator {
+    fn disable_retry(&mut self) {
+        self.client.disable_retry();
+    }
 
-        loop {
-            match client
-                .post(&url)
-                .header("Authorization", format!("Bearer {}", self.api_key))
-                .json(&body)
-                .send()
-                .await
-            {
-                Ok(response) => {
-                    print_progress(&format!("正在请求 {} 进行AI对话", ai_host), Some(100));
-                    debug!("收到响应: {:#?}", response);
+    async fn chat(&self, system_prompt: &str, user_content: &str) -> anyhow::Result<String> {
+        debug!("使用 Ollama，API Endpoint: {}", self.endpoint);
 
-                    if !response.status().is_success() {
-                        let error_json = response.json::<serde_json::Value>().await?;
-                        debug!("响应内容: {}", serde_json::to_string_pretty(&error_json)?);
-                        return Err(anyhow::anyhow!("API 调用失败: {}",
-                            error_json["error"]["message"].as_str().unwrap_or("未知错误")));
-                    }
+        let url = format!("{}/api/chat", self.endpoint);
+        let body = self.request_body(system_prompt, user_content, false)?;
+        let result = self.client.post_json(&url, &[], &body).await?;
 
-                    let result = response.json::<serde_json::Value>().await?;
-                    debug!("响应内容: {}", serde_json::to_string_pretty(&result)?);
+        let response = result["message"]["content"]
+            .as_str()
+            .unwrap_or_default();
+        Ok(response.to_string())
+    }
 
-                    let response = result["choices"][0]["message"]["content"]
-                        .as_str()
-                        .unwrap_or_default();
-                    return Ok(response.to_string());
-                }
-                Err(e) if e.is_timeout() => {
-                    warn!("请求超时: {}", e);
-                    if !Confirm::with_theme(&dialoguer::theme::ColorfulTheme::default())
-                        .with_prompt("请求超时，是否重试？")
-                        .default(true)
-                        .interact()? {
-                        return Err(anyhow::anyhow!("请求超时"));
-                    }
-                    continue;
-                }
-                Err(e) => return Err(e.into()),
+    async fn chat_stream(
+        &self,
+        system_prompt: &str,
+        user_content: &str,
+        on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+    ) -> anyhow::Result<String> {
+        debug!("使用 Ollama 流式输出，API Endpoint: {}", self.endpoint);
+
+        // Ollama 的流式响应为 NDJSON，每行一个 JSON 对象
+        let url = format!("{}/api/chat", self.endpoint);
+        let body = self.request_body(system_prompt, user_content, true)?;
+        let mut content = String::new();
+        self.client.post_stream(&url, &[], &body, &mut |line| {
+            let event: serde_json::Value = serde_json::from_str(line)?;
+            if let Some(error) = event["error"].as_str() {
+                return Err(anyhow::anyhow!("API 调用失败: {}", error));
             }
+            if let Some(token) = event["message"]["content"].as_str() {
+                content.push_str(token);
+                on_token(token);
+            }
+            Ok(())
+        }).await?;
+        Ok(content)
+    }
+
+    async fn probe(&self) -> anyhow::Result<String> {
+        let models = list_local_models(&AIService::Ollama, &self.endpoint).await?;
+        // Ollama 的模型名可以省略 :latest 标签
+        let installed = models.iter()
+            .any(|name| name == &self.model || name.strip_suffix(":latest") == Some(&self.model));
+        if !installed {
+            return Err(anyhow::anyhow!("模型 {} 未安装，请先运行 ollama pull {}", self.model, self.model));
         }
+        Ok(format!("服务可达，已安装模型 {} 个", models.len()))
     }
 }
 
-#[async_trait]
-impl AiService for QwenTranslator {
-    async fn chat(&self, system_prompt: &str, user_content: &str) -> anyhow::Result<String> {
-        debug!("使用 Qwen，API Endpoint: {}", self.endpoint);
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(self.timeout_seconds))
-            .build()?;
+/// 获取本地服务（Ollama / llama.cpp）中已安装的模型列表
+pub async fn list_local_models(service: &AIService, endpoint: &str) -> anyhow::Result<Vec<String>> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(5))
+        .build()?;
+
+    let (url, list_key, name_key) = match service {
+        AIService::Ollama => (format!("{}/api/tags", endpoint), "models", "name"),
+        AIService::LlamaCpp => (format!("{}/models", endpoint), "data", "id"),
+        _ => return Err(anyhow::anyhow!("{:?} 不是本地服务", service)),
+    };
+    debug!("获取本地模型列表: {}", url);
+
+    let response = client.get(&url).send().await?;
+    let status = response.status();
+    let text = response.text().await?;
+    if !status.is_success() {
+        return Err(anyhow::anyhow!("获取模型列表失败 (HTTP {}): {}",
+            status.as_u16(), extract_error_message(&text)));
+    }
 
-        let url = format!("{}/chat/completions", self.endpoint);
-        let messages = vec![
-            serde_json::json!({
-                "role": "system",
-                "content": system_prompt
-            }),
-            serde_json::json!({
-                "role": "user",
-                "content": user_content
-            })
-        ];
-        debug!("发送给 Qwen 的消息:\n{}", serde_json::to_string_pretty(&messages)?);
-        let body = serde_json::json!({
-            "model": self.model,
-            "messages": messages,
-            "temperature": 0.1,
-            "max_tokens": self.max_tokens
-        });
+    let result: serde_json::Value = serde_json::from_str(&text)?;
+    Ok(result[list_key].as_array()
+        .map(|models| models.iter()
+            .filter_map(|m| m[name_key].as_str().map(str::to_string))
+            .collect())
+        .unwrap_or_default())
+}
 
-        let ai_host = match url.split('/').nth(2) {
-            Some(host) => host,
-            None => "dashscope.aliyuncs.com",
-        };
-        print_progress(&format!("正在请求 {} 进行AI对话", ai_host), None);
+impl GeminiTranslator {
+    fn request_body(&self, system_prompt: &str, user_content: &str) -> serde_json::Value {
+        let prompt = format!("{}\n\n{}", system_prompt, user_content);
+        debug!("发送给 Gemini 的内容:\n{}", prompt);
+        serde_json::json!({
+            "contents": [{
+                "parts": [{
+                    "text": prompt
+                }]
+            }],
+            "generationConfig": {
+                "maxOutputTokens": self.client.max_tokens()
+            }
+        })
+    }
+}
 
-        loop {
-            match client
-                .post(&url)
-                .header("Authorization", format!("Bearer {}", self.api_key))
-                .json(&body)
-                .send()
-                .await
-            {
-                Ok(response) => {
-                    print_progress(&format!("正在请求 {} 进行AI对话", ai_host), Some(100));
-                    debug!("收到响应: {:#?}", response);
+#[async_trait]
+impl AiService for GeminiTranslator {
+    fn disable_retry(&mut self) {
+        self.client.disable_retry();
+    }
 
-                    if !response.status().is_success() {
-                        let error_json = response.json::<serde_json::Value>().await?;
-                        debug!("响应内容: {}", serde_json::to_string_pretty(&error_json)?);
-                        return Err(anyhow::anyhow!("API 调用失败: {}",
-                            error_json["error"]["message"].as_str().unwrap_or("未知错误")));
-                    }
+    async fn chat(&self, system_prompt: &str, user_content: &str) -> anyhow::Result<String> {
+        debug!("使用 Gemini，API Endpoint: {}", self.endpoint);
 
-                    let result = response.json::<serde_json::Value>().await?;
-                    debug!("响应内容: {}", serde_json::to_string_pretty(&result)?);
+        let url = format!("{}/models/{}:generateContent?key={}", self.endpoint, self.model, self.api_key);
+        let body = self.request_body(system_prompt, user_content);
+        let result = self.client.post_json(&url, &[], &body).await?;
 
-                    let response = result["choices"][0]["message"]["content"]
-                        .as_str()
-                        .unwrap_or_default();
-                    return Ok(response.to_string());
-                }
-                Err(e) if e.is_timeout() => {
-                    warn!("请求超时: {}", e);
-                    if !Confirm::with_theme(&dialoguer::theme::ColorfulTheme::default())
-                        .with_prompt("请求超时，是否重试？")
-                        .default(true)
-                        .interact()? {
-                        return Err(anyhow::anyhow!("请求超时"));
-                    }
-                    continue;
-                }
-                Err(e) => return Err(e.into()),
+        let response = result["candidates"][0]["content"]["parts"][0]["text"]
+            .as_str()
+            .unwrap_or_default();
+        Ok(response.to_string())
+    }
+
+    async fn chat_stream(
+        &self,
+        system_prompt: &str,
+        user_content: &str,
+        on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+    ) -> anyhow::Result<String> {
+        debug!("使用 Gemini 流式输出，API Endpoint: {}", self.endpoint);
+
+        let url = format!("{}/models/{}:streamGenerateContent?alt=sse&key={}", self.endpoint, self.model, self.api_key);
+        let body = self.request_body(system_prompt, user_content);
+        let mut content = String::new();
+        self.client.post_stream(&url, &[], &body, &mut |line| {
+            let Some(data) = sse_data(line) else { return Ok(()) };
+            let event: serde_json::Value = serde_json::from_str(data)?;
+            if let Some(token) = event["candidates"][0]["content"]["parts"][0]["text"].as_str() {
+                content.push_str(token);
+                on_token(token);
             }
+            Ok(())
+        }).await?;
+        Ok(content)
+    }
+
+    async fn probe(&self) -> anyhow::Result<String> {
+        let url = format!("{}/models?key={}", self.endpoint, self.api_key);
+        match self.client.get_json(&url, &[]).await? {
+            Some(result) => Ok(describe_models(&result["models"], "name", &self.model)),
+            None => Err(anyhow::anyhow!("{}/models 不存在，请检查 API 地址", self.endpoint)),
         }
     }
 }
 
+/// 对话并在终端实时显示生成的内容，结束后擦除实时输出，由调用方统一展示最终结果
+///
+/// 非终端环境（如 IDE 触发的 hook、CI）下直接使用普通对话。
+pub async fn chat_with_live_output(
+    translator: &dyn Translator,
+    system_prompt: &str,
+    user_content: &str,
+) -> anyhow::Result<String> {
+    let mut printer = StreamPrinter::new();
+    if !printer.is_enabled() {
+        return translator.chat(system_prompt, user_content).await;
+    }
+
+    let result = translator
+        .chat_stream(system_prompt, user_content, &mut |token| printer.push(token))
+        .await;
+    printer.clear();
+    result
+}
+
 pub async fn create_translator(config: &Config) -> anyhow::Result<Box<dyn Translator>> {
-    info!("创建 {:?} AI服务", config.default_service);
+    info!("创建 {} AI服务", config.default_service_name());
     let service_config = config.get_default_service()?;
     create_translator_for_service(service_config).await
 }
 
 pub async fn translate_with_fallback(config: &Config, text: &str) -> anyhow::Result<String> {
-    let mut tried_services = Vec::new();
+    let mut tried_services: Vec<String> = Vec::new();
 
     // 如果已设置环境变量，直接返回原文
     if std::env::var("GIT_COMMIT_HELPER_NO_TRANSLATE").is_ok() {
@@ -733,53 +962,50 @@ pub async fn translate_with_fallback(config: &Config, text: &str) -> anyhow::Res
 
     // 获取默认服务配置并尝试翻译
     if let Ok(default_service_config) = config.get_default_service() {
-        debug!("尝试使用默认服务 {:?}", default_service_config.service);
-        if let Some(result) = try_translate(&default_service_config.service, config, text).await {
+        debug!("尝试使用默认服务 {}", default_service_config.display_name());
+        if let Some(result) = try_translate(default_service_config, text).await {
             return result;
         }
-        tried_services.push(default_service_config.service.clone());
+        tried_services.push(default_service_config.id.clone());
     }
 
     for service_config in &config.services {
-        if tried_services.contains(&service_config.service) {
+        if tried_services.contains(&service_config.id) {
             continue;
         }
 
-        debug!("尝试使用备选服务 {:?}", service_config.service);
-        if let Some(result) = try_translate(&service_config.service, config, text).await {
+        debug!("尝试使用备选服务 {}", service_config.display_name());
+        if let Some(result) = try_translate(service_config, text).await {
             return result;
         }
-        tried_services.push(service_config.service.clone());
+        tried_services.push(service_config.id.clone());
     }
 
-    while let Some(service) = select_retry_service(config, &tried_services)? {
-        debug!("用户选择使用 {:?} 重试", service);
-        if let Some(result) = try_translate(&service, config, text).await {
+    while let Some(service_config) = select_retry_service(config, &tried_services)? {
+        debug!("用户选择使用 {} 重试", service_config.display_name());
+        if let Some(result) = try_translate(service_config, text).await {
             return result;
         }
-        tried_services.push(service);
+        tried_services.push(service_config.id.clone());
     }
 
     Err(anyhow::anyhow!("所有AI服务均失败"))
 }
 
-async fn try_translate(service: &AIService, config: &Config, text: &str) -> Option<anyhow::Result<String>> {
-    let service_config = config.services.iter()
-        .find(|s| s.service == *service)?;
-
+async fn try_translate(service_config: &AIServiceConfig, text: &str) -> Option<anyhow::Result<String>> {
     let translator = create_translator_for_service(service_config).await.ok()?;
     match translator.translate(text).await {
         Ok(result) => Some(Ok(result)),
         Err(e) => {
-            warn!("{:?} 服务翻译失败: {}", service, e);
+            warn!("{} 服务翻译失败: {}", service_config.display_name(), e);
             None
         }
     }
 }
 
-fn select_retry_service(config: &Config, tried_services: &[AIService]) -> anyhow::Result<Option<AIService>> {
+fn select_retry_service<'a>(config: &'a Config, tried_services: &[String]) -> anyhow::Result<Option<&'a AIServiceConfig>> {
     let available_services: Vec<_> = config.services.iter()
-        .filter(|s| !tried_services.contains(&s.service))
+        .filter(|s| !tried_services.contains(&s.id))
         .collect();
 
     if available_services.is_empty() {
@@ -787,7 +1013,7 @@ fn select_retry_service(config: &Config, tried_services: &[AIService]) -> anyhow
     }
 
     let options: Vec<String> = available_services.iter()
-        .map(|s| format!("{:?}", s.service))
+        .map(|s| s.display_name())
         .collect();
 
     println!("\n之前的翻译尝试都失败了，是否要使用其他服务重试？");
@@ -803,50 +1029,300 @@ fn select_retry_service(config: &Config, tried_services: &[AIService]) -> anyhow
         .default(0)
         .interact()?;
 
-    Ok(Some(available_services[selection].service.clone()))
+    Ok(Some(available_services[selection]))
 }
 
 pub async fn create_translator_for_service(service_config: &AIServiceConfig) -> anyhow::Result<Box<dyn Translator>> {
-    // 获取全局配置中的超时时间
-    let config = crate::config::Config::load().ok();
-    let timeout = config.as_ref().map(|c| c.timeout_seconds).unwrap_or(20);
-
     Ok(match service_config.service {
-        AIService::DeepSeek => {
-            let mut translator = DeepSeekTranslator::new(service_config);
-            translator.timeout_seconds = timeout;
-            Box::new(translator)
-        },
-        AIService::OpenAI => {
-            let mut translator = OpenAITranslator::new(service_config);
-            translator.timeout_seconds = timeout;
-            Box::new(translator)
-        },
-        AIService::Claude => {
-            let mut translator = ClaudeTranslator::new(service_config);
-            translator.timeout_seconds = timeout;
-            Box::new(translator)
-        },
+        AIService::Claude => Box::new(ClaudeTranslator::new(service_config)),
         AIService::Copilot => {
             let editor_version = "1.0.0".to_string();
             let client = CopilotClient::new_with_models(service_config.api_key.clone(), editor_version).await?;
             let model_id = service_config.model.clone().unwrap_or_else(|| "copilot-chat".to_string());
             Box::new(CopilotTranslator::new(client, model_id))
         },
-        AIService::Gemini => {
-            let mut translator = GeminiTranslator::new(service_config);
-            translator.timeout_seconds = timeout;
-            Box::new(translator)
-        },
-        AIService::Grok => {
-            let mut translator = GrokTranslator::new(service_config);
-            translator.timeout_seconds = timeout;
-            Box::new(translator)
-        },
-        AIService::Qwen => {
-            let mut translator = QwenTranslator::new(service_config);
-            translator.timeout_seconds = timeout;
-            Box::new(translator)
-        },
+        AIService::Gemini => Box::new(GeminiTranslator::new(service_config)),
+        AIService::Ollama => Box::new(OllamaTranslator::new(service_config)),
+        ref service => {
+            let provider = OpenAICompatibleProvider::for_config(service_config)
+                .ok_or_else(|| anyhow::anyhow!("不支持的 AI 服务: {:?}", service))?;
+            Box::new(OpenAICompatibleTranslator::new(provider, service_config))
+        }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_line_buffer_keeps_split_utf8_chars() {
+        let data = "data: {\"text\":\"中文\"}\r\n\r\ndata: [DONE]\n".as_bytes();
+        let mut buffer = LineBuffer::default();
+        // 在多字节字符中间切分
+        let mut lines = buffer.push(&data[..18]);
+        lines.extend(buffer.push(&data[18..]));
+        assert_eq!(lines, vec!["data: {\"text\":\"中文\"}", "data: [DONE]"]);
+        assert_eq!(buffer.finish(), None);
+    }
+
+    #[test]
+    fn test_sse_data() {
+        assert_eq!(sse_data("data: {\"a\":1}"), Some("{\"a\":1}"));
+        assert_eq!(sse_data("data:[DONE]"), Some("[DONE]"));
+        assert_eq!(sse_data("event: message_start"), None);
+    }
+
+    /// 收到的请求：小写的请求头和 JSON 请求体
+    struct CapturedRequest {
+        path: String,
+        headers: BTreeMap<String, String>,
+        body: serde_json::Value,
+    }
+
+    /// 拼接一个完整的 HTTP 响应报文
+    fn http_reply(status: &str, extra_headers: &str, content_type: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\ncontent-type: {}\r\ncontent-length: {}\r\n{}connection: close\r\n\r\n{}",
+            status, content_type, body.len(), extra_headers, body
+        )
+    }
+
+    /// 读取一个完整的请求
+    async fn read_request(socket: &mut tokio::net::TcpStream) -> CapturedRequest {
+        let mut data = Vec::new();
+        let mut buf = [0u8; 4096];
+        let header_end = loop {
+            let n = socket.read(&mut buf).await.unwrap();
+            data.extend_from_slice(&buf[..n]);
+            if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+        };
+
+        let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+        let mut lines = head.lines();
+        let path = lines.next().unwrap().split(' ').nth(1).unwrap().to_string();
+        let headers: BTreeMap<String, String> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+            .collect();
+
+        let length: usize = headers["content-length"].parse().unwrap();
+        while data.len() < header_end + length {
+            let n = socket.read(&mut buf).await.unwrap();
+            data.extend_from_slice(&buf[..n]);
+        }
+        let body = serde_json::from_slice(&data[header_end..header_end + length]).unwrap();
+
+        CapturedRequest { path, headers, body }
+    }
+
+    /// 启动本地 mock 服务，按顺序为每个连接返回一个响应，返回服务地址和收到的所有请求
+    async fn mock_server_sequence(
+        replies: Vec<String>,
+    ) -> (String, tokio::task::JoinHandle<Vec<CapturedRequest>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/v1", listener.local_addr().unwrap());
+
+        let handle = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for reply in replies {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                requests.push(read_request(&mut socket).await);
+                socket.write_all(reply.as_bytes()).await.unwrap();
+                socket.shutdown().await.ok();
+            }
+            requests
+        });
+
+        (endpoint, handle)
+    }
+
+    /// 启动只处理一次请求的本地 mock 服务，返回服务地址和收到的请求
+    async fn mock_server(
+        content_type: &'static str,
+        response: String,
+    ) -> (String, tokio::task::JoinHandle<CapturedRequest>) {
+        let reply = http_reply("200 OK", "", content_type, &response);
+        let (endpoint, handle) = mock_server_sequence(vec![reply]).await;
+        let handle = tokio::spawn(async move {
+            handle.await.unwrap().remove(0)
+        });
+        (endpoint, handle)
+    }
+
+    fn claude_translator(endpoint: String) -> ClaudeTranslator {
+        ClaudeTranslator::new(&AIServiceConfig {
+            api_key: "test-key".to_string(),
+            api_endpoint: Some(endpoint),
+            model: Some("claude-test".to_string()),
+            ..AIServiceConfig::new(AIService::Claude)
+        })
+    }
+
+    fn assert_request_shape(request: &CapturedRequest) {
+        assert_eq!(request.path, "/v1/messages");
+        assert_eq!(request.headers["x-api-key"], "test-key");
+        assert_eq!(request.headers["anthropic-version"], ANTHROPIC_VERSION);
+        assert!(!request.headers.contains_key("authorization"));
+
+        assert_eq!(request.body["model"], "claude-test");
+        assert_eq!(request.body["system"], "system prompt");
+        assert!(request.body["max_tokens"].as_u64().unwrap() > 0);
+        assert_eq!(request.body["messages"], serde_json::json!([
+            { "role": "user", "content": "user content" }
+        ]));
+    }
+
+    #[tokio::test]
+    async fn test_claude_chat_request_shape() {
+        let response = serde_json::json!({
+            "id": "msg_01",
+            "type": "message",
+            "role": "assistant",
+            "content": [
+                { "type": "text", "text": "feat: add " },
+                { "type": "tool_use", "id": "toolu_01", "name": "noop", "input": {} },
+                { "type": "text", "text": "parser" }
+            ],
+            "stop_reason": "end_turn"
+        });
+        let (endpoint, server) = mock_server("application/json", response.to_string()).await;
+
+        let result = claude_translator(endpoint)
+            .chat("system prompt", "user content")
+            .await
+            .unwrap();
+        assert_eq!(result, "feat: add parser");
+
+        let request = server.await.unwrap();
+        assert_request_shape(&request);
+        assert!(request.body.get("stream").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_claude_chat_stream_request_shape() {
+        let events = [
+            r#"{"type":"message_start","message":{"id":"msg_01","content":[]}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"fix: "}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"修复解析"}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"message_stop"}"#,
+        ];
+        let response: String = events.iter()
+            .map(|data| format!("event: message\ndata: {}\n\n", data))
+            .collect();
+        let (endpoint, server) = mock_server("text/event-stream", response).await;
+
+        let mut tokens = Vec::new();
+        let result = claude_translator(endpoint)
+            .chat_stream("system prompt", "user content", &mut |token| tokens.push(token.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(result, "fix: 修复解析");
+        assert_eq!(tokens, vec!["fix: ", "修复解析"]);
+
+        let request = server.await.unwrap();
+        assert_request_shape(&request);
+        assert_eq!(request.body["stream"], true);
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        let retry = RetryConfig {
+            max_attempts: 5,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 3000,
+            max_retry_after_secs: 120,
+        };
+        assert_eq!(retry.backoff(1), Duration::from_millis(500));
+        assert_eq!(retry.backoff(2), Duration::from_millis(1000));
+        assert_eq!(retry.backoff(3), Duration::from_millis(2000));
+        assert_eq!(retry.backoff(4), Duration::from_millis(3000));
+        assert_eq!(retry.backoff(100), Duration::from_millis(3000));
+
+        // Retry-After 不受退避上限限制，超过自动等待的上限时交给用户决定
+        assert_eq!(retry.delay(2, None), Ok(Duration::from_millis(1000)));
+        assert_eq!(retry.delay(1, Some(Duration::from_secs(60))), Ok(Duration::from_secs(60)));
+        assert_eq!(retry.delay(1, Some(Duration::from_secs(3600))), Err(Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(parse_retry_after(&headers), None);
+        headers.insert(reqwest::header::RETRY_AFTER, "2".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(2)));
+        headers.insert(reqwest::header::RETRY_AFTER, "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), None);
+    }
+
+    #[tokio::test]
+    async fn test_retry_on_rate_limit() {
+        let error = r#"{"type":"error","error":{"type":"rate_limit_error","message":"rate limited"}}"#;
+        let success = r#"{"content":[{"type":"text","text":"ok"}]}"#;
+        let (endpoint, server) = mock_server_sequence(vec![
+            http_reply("429 Too Many Requests", "retry-after: 0\r\n", "application/json", error),
+            http_reply("503 Service Unavailable", "retry-after: 0\r\n", "application/json", error),
+            http_reply("200 OK", "", "application/json", success),
+        ]).await;
+
+        let mut translator = claude_translator(endpoint);
+        translator.client.retry = RetryConfig::default();
+        let result = translator.chat("system prompt", "user content").await.unwrap();
+        assert_eq!(result, "ok");
+        assert_eq!(server.await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_longer_than_backoff() {
+        let error = r#"{"type":"error","error":{"type":"rate_limit_error","message":"rate limited"}}"#;
+        let success = r#"{"content":[{"type":"text","text":"ok"}]}"#;
+        let (endpoint, server) = mock_server_sequence(vec![
+            http_reply("429 Too Many Requests", "retry-after: 1\r\n", "application/json", error),
+            http_reply("200 OK", "", "application/json", success),
+        ]).await;
+
+        // Retry-After 超过单次退避的上限时仍按其要求等待后重试
+        let mut translator = claude_translator(endpoint);
+        translator.client.retry = RetryConfig { max_backoff_ms: 0, ..RetryConfig::default() };
+        let started = std::time::Instant::now();
+        let result = translator.chat("system prompt", "user content").await.unwrap();
+        assert_eq!(result, "ok");
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert_eq!(server.await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_disable_retry() {
+        let error = r#"{"type":"error","error":{"type":"overloaded_error","message":"overloaded"}}"#;
+        let (endpoint, server) = mock_server_sequence(vec![
+            http_reply("503 Service Unavailable", "retry-after: 0\r\n", "application/json", error),
+        ]).await;
+
+        // 检查服务状态时只请求一次，直接报告 HTTP 错误
+        let mut translator = claude_translator(endpoint);
+        translator.client.retry = RetryConfig::default();
+        translator.disable_retry();
+        let error = translator.chat("system prompt", "user content").await.unwrap_err();
+        assert_eq!(error.to_string(), "API 调用失败 (HTTP 503): overloaded");
+        assert_eq!(server.await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_on_client_error() {
+        let error = r#"{"type":"error","error":{"type":"authentication_error","message":"invalid x-api-key"}}"#;
+        let (endpoint, _server) = mock_server_sequence(vec![
+            http_reply("401 Unauthorized", "", "application/json", error),
+        ]).await;
+
+        let mut translator = claude_translator(endpoint);
+        translator.client.retry = RetryConfig::default();
+        let error = translator.chat("system prompt", "user content").await.unwrap_err();
+        assert_eq!(error.to_string(), "API 调用失败 (HTTP 401): invalid x-api-key");
+    }
+}
@@ -1,16 +1,12 @@
 use regex::Regex;
 use crate::ai_service;
+use crate::commit_template::{self, LanguageMode, PromptHints, Sections};
 use crate::config;
+use crate::conventional;
+use crate::diff_budget;
+use crate::diff_filter;
 use crate::git;
 
-// 语言模式枚举
-#[derive(Debug, Clone, Copy)]
-enum LanguageMode {
-    ChineseOnly,
-    EnglishOnly,
-    Bilingual,
-}
-
 /// 解析 issues 参数并生成相应的引用字段
 fn parse_issue_reference(issues: &str) -> anyhow::Result<String> {
     let mut fixes_refs = Vec::new();
@@ -138,514 +134,136 @@ fn is_current_project_repo(repo: &str) -> anyhow::Result<bool> {
     }
 }
 
-// 提示词模板常量
-const ENGLISH_PROMPT_TEMPLATE: &str = r#"Please analyze the git diff content and generate a commit message in English only:
-1. First line: type: message (under 50 characters)
-2. Empty line after the title
-3. Detailed explanation in English (what was changed and why)
-4. Empty line after explanation
-5. Log field (ONLY if this change involves user-facing features/UI changes that product managers would communicate to users)
-6. Empty line after Log field (if present)
-7. Influence section with black-box testing recommendations
-8. Type must be one of: feat/fix/docs/style/refactor/test/chore
-9. Focus on both WHAT changed and WHY it was necessary
-10. Include any important technical details or context
-11. DO NOT include any Chinese content
-12. DO NOT wrap the response in any markdown or code block markers
-
-Example response format:
-feat: add user authentication module
-
-1. Implement JWT-based authentication system
-2. Add user login and registration endpoints
-3. Include password hashing with bcrypt
-4. Set up token refresh mechanism
-
-Log: Added user authentication feature with login and registration
-
-Influence:
-1. Test user registration with valid and invalid inputs
-2. Verify login functionality with correct and incorrect credentials
-3. Test JWT token generation and validation
-4. Verify password security and hashing
-5. Test token refresh mechanism and expiration handling
-6. Verify access control for protected endpoints
-
-Please respond with ONLY the commit message following this format,
-DO NOT end commit titles with any punctuation."#;
-
-const CHINESE_PROMPT_TEMPLATE: &str = r#"请分析以下 git diff 内容，并按照以下格式生成提交信息：
-1. 第一行为标题：type: message（不超过50个字符）
-
-3. 详细的中文说明（解释做了什么改动以及为什么需要这些改动）
-4. 说明下方空一行
-5. Log 字段（仅当此次变更涉及用户可感知的功能/UI层面变化，产品经理会向用户说明的内容时才添加）
-6. Log 字段下方空一行（如果存在 Log 字段）
-7. Influence 部分，提供黑盒测试的重点和范围
-8. type 必须是以下之一：feat/fix/docs/style/refactor/test/chore
-9. 关注点：变更内容（做了什么）和变更原因（为什么）
-10. 包含重要的技术细节或上下文
-11. 不要使用任何 markdown 或代码块标记
-12. 标题结尾不要使用标点符号
-
-示例格式：
-feat: 添加用户认证模块
-
-1. 实现基于 JWT 的认证系统
-2. 添加用户登录和注册端点
-3. 包含使用 bcrypt 的密码哈希处理
-4. 设置令牌刷新机制
-
-Log: 新增用户登录注册功能
-
-Influence:
-1. 测试用户注册功能，包括有效和无效输入
-2. 验证登录功能，测试正确和错误的凭据
-3. 测试 JWT 令牌生成和验证流程
-4. 验证密码安全性和哈希处理
-5. 测试令牌刷新机制和过期处理
-6. 验证受保护端点的访问控制"#;
-
-const BILINGUAL_PROMPT_TEMPLATE: &str = r#"Please analyze the git diff content and generate a detailed bilingual commit message with:
-1. First line in English: type: message (under 50 characters)
-2. Empty line after the title
-3. Detailed explanation in English (what was changed and why)
-4. Empty line after English explanation
-5. Log field in English (ONLY if this change involves user-facing features/UI changes)
-6. Empty line after English Log field (if present)
-7. Influence section in English with black-box testing recommendations
-
-9. Chinese title and explanation (translate the English content)
-10. Empty line after Chinese explanation
-11. Chinese Log field (translate the English Log field, only if present)
-12. Empty line after Chinese Log field (if present)
-13. Chinese Influence section (translate the English testing suggestions)
-14. Type must be one of: feat/fix/docs/style/refactor/test/chore
-15. Focus on both WHAT changed and WHY it was necessary
-16. Include any important technical details or context
-17. DO NOT wrap the response in any markdown or code block markers
-
-Example response format:
-feat: add user authentication module
-
-1. Implement JWT-based authentication system
-2. Add user login and registration endpoints
-3. Include password hashing with bcrypt
-4. Set up token refresh mechanism
-
-Log: Added user authentication feature with login and registration
-
-Influence:
-1. Test user registration with valid and invalid inputs
-2. Verify login functionality with correct and incorrect credentials
-3. Test JWT token generation and validation
-4. Verify password security and hashing
-5. Test token refresh mechanism and expiration handling
-6. Verify access control for protected endpoints
-
-feat: 添加用户认证模块
-
-1. 实现基于 JWT 的认证系统
-2. 添加用户登录和注册端点
-3. 包含使用 bcrypt 的密码哈希处理
-4. 设置令牌刷新机制
-
-Log: 新增用户登录注册功能
-
-Influence:
-1. 测试用户注册功能，包括有效和无效输入
-2. 验证登录功能，测试正确和错误的凭据
-3. 测试 JWT 令牌生成和验证流程
-4. 验证密码安全性和哈希处理
-5. 测试令牌刷新机制和过期处理
-6. 验证受保护端点的访问控制
-
-Please respond with ONLY the commit message following this format,
-DO NOT end commit titles with any punctuation."#;
-
-// 无测试建议版本的提示词模板
-const ENGLISH_PROMPT_TEMPLATE_NO_TEST: &str = r#"Please analyze the git diff content and generate a commit message in English only:
-1. First line: type: message (under 50 characters)
-2. Empty line after the title
-3. Detailed explanation in English (what was changed and why)
-4. Empty line after explanation
-5. Log field (ONLY if this change involves user-facing features/UI changes that product managers would communicate to users)
-6. Type must be one of: feat/fix/docs/style/refactor/test/chore
-7. Focus on both WHAT changed and WHY it was necessary
-8. Include any important technical details or context
-9. DO NOT include any Chinese content
-10. DO NOT wrap the response in any markdown or code block markers
-
-Example response format:
-feat: add user authentication module
-
-1. Implement JWT-based authentication system
-2. Add user login and registration endpoints
-3. Include password hashing with bcrypt
-4. Set up token refresh mechanism
-
-Log: Added user authentication feature with login and registration
-
-Please respond with ONLY the commit message following this format,
-DO NOT end commit titles with any punctuation."#;
-
-const CHINESE_PROMPT_TEMPLATE_NO_TEST: &str = r#"请分析以下 git diff 内容，并按照以下格式生成提交信息：
-1. 第一行为标题：type: message（不超过50个字符）
-2. 标题下方空一行
-3. 详细的中文说明（解释做了什么改动以及为什么需要这些改动）
-4. 说明下方空一行
-5. Log 字段（仅当此次变更涉及用户可感知的功能/UI层面变化时才添加）
-6. type 必须是以下之一：feat/fix/docs/style/refactor/test/chore
-7. 关注点：变更内容（做了什么）和变更原因（为什么）
-8. 包含重要的技术细节或上下文
-9. 不要使用任何 markdown 或代码块标记
-10. 标题结尾不要使用标点符号
-
-示例格式：
-feat: 添加用户认证模块
-
-1. 实现基于 JWT 的认证系统
-2. 添加用户登录和注册端点
-3. 包含使用 bcrypt 的密码哈希处理
-4. 设置令牌刷新机制
-
-Log: 新增用户登录注册功能"#;
-
-const BILINGUAL_PROMPT_TEMPLATE_NO_TEST: &str = r#"Please analyze the git diff content and generate a detailed bilingual commit message with:
-1. First line in English: type: message (under 50 characters)
-2. Empty line after the title
-3. Detailed explanation in English (what was changed and why)
-4. Empty line after English explanation
-5. Log field in English (ONLY if this change involves user-facing features/UI changes)
-6. Empty line after English Log field (if present)
-7. Chinese title and explanation (translate the English content)
-8. Empty line after Chinese explanation
-9. Chinese Log field (translate the English Log field, only if present)
-10. Type must be one of: feat/fix/docs/style/refactor/test/chore
-11. Focus on both WHAT changed and WHY it was necessary
-12. Include any important technical details or context
-13. DO NOT wrap the response in any markdown or code block markers
-
-Example response format:
-feat: add user authentication module
-
-1. Implement JWT-based authentication system
-2. Add user login and registration endpoints
-3. Include password hashing with bcrypt
-4. Set up token refresh mechanism
-
-Log: Added user authentication feature with login and registration
-
-feat: 添加用户认证模块
-
-1. 实现基于 JWT 的认证系统
-2. 添加用户登录和注册端点
-3. 包含使用 bcrypt 的密码哈希处理
-4. 设置令牌刷新机制
-
-Log: 新增用户登录注册功能
-
-Please respond with ONLY the commit message following this format,
-DO NOT end commit titles with any punctuation."#;
-
-// 无Log字段版本的提示词模板
-const ENGLISH_PROMPT_TEMPLATE_NO_LOG: &str = r#"Please analyze the git diff content and generate a commit message in English only:
-1. First line: type: message (under 50 characters)
-2. Empty line after the title
-3. Detailed explanation in English (what was changed and why)
-4. Empty line after explanation
-5. Influence section with black-box testing recommendations
-6. Type must be one of: feat/fix/docs/style/refactor/test/chore
-7. Focus on both WHAT changed and WHY it was necessary
-8. Include any important technical details or context
-9. DO NOT include any Chinese content
-10. DO NOT wrap the response in any markdown or code block markers
-
-Example response format:
-feat: add user authentication module
-
-1. Implement JWT-based authentication system
-2. Add user login and registration endpoints
-3. Include password hashing with bcrypt
-4. Set up token refresh mechanism
-
-Influence:
-1. Test user registration with valid and invalid inputs
-2. Verify login functionality with correct and incorrect credentials
-3. Test JWT token generation and validation
-4. Verify password security and hashing
-5. Test token refresh mechanism and expiration handling
-6. Verify access control for protected endpoints
-
-Please respond with ONLY the commit message following this format,
-DO NOT end commit titles with any punctuation."#;
-
-const CHINESE_PROMPT_TEMPLATE_NO_LOG: &str = r#"请分析以下 git diff 内容，并按照以下格式生成提交信息：
-1. 第一行为标题：type: message（不超过50个字符）
-
-3. 详细的中文说明（解释做了什么改动以及为什么需要这些改动）
-4. 说明下方空一行
-5. Influence 部分，提供黑盒测试的重点和范围
-6. type 必须是以下之一：feat/fix/docs/style/refactor/test/chore
-7. 关注点：变更内容（做了什么）和变更原因（为什么）
-8. 包含重要的技术细节或上下文
-9. 不要使用任何 markdown 或代码块标记
-10. 标题结尾不要使用标点符号
-
-示例格式：
-feat: 添加用户认证模块
-
-1. 实现基于 JWT 的认证系统
-2. 添加用户登录和注册端点
-3. 包含使用 bcrypt 的密码哈希处理
-4. 设置令牌刷新机制
-
-Influence:
-1. 测试用户注册功能，包括有效和无效输入
-2. 验证登录功能，测试正确和错误的凭据
-3. 测试 JWT 令牌生成和验证流程
-4. 验证密码安全性和哈希处理
-5. 测试令牌刷新机制和过期处理
-6. 验证受保护端点的访问控制"#;
-
-const BILINGUAL_PROMPT_TEMPLATE_NO_LOG: &str = r#"Please analyze the git diff content and generate a detailed bilingual commit message with:
-1. First line in English: type: message (under 50 characters)
-2. Empty line after the title
-3. Detailed explanation in English (what was changed and why)
-4. Empty line after English explanation
-5. Influence section in English with black-box testing recommendations
-
-7. Chinese title and explanation (translate the English content)
-8. Empty line after Chinese explanation
-9. Chinese Influence section (translate the English testing suggestions)
-10. Type must be one of: feat/fix/docs/style/refactor/test/chore
-11. Focus on both WHAT changed and WHY it was necessary
-12. Include any important technical details or context
-13. DO NOT wrap the response in any markdown or code block markers
-
-Example response format:
-feat: add user authentication module
-
-1. Implement JWT-based authentication system
-2. Add user login and registration endpoints
-3. Include password hashing with bcrypt
-4. Set up token refresh mechanism
-
-Influence:
-1. Test user registration with valid and invalid inputs
-2. Verify login functionality with correct and incorrect credentials
-3. Test JWT token generation and validation
-4. Verify password security and hashing
-5. Test token refresh mechanism and expiration handling
-6. Verify access control for protected endpoints
-
-feat: 添加用户认证模块
-
-1. 实现基于 JWT 的认证系统
-2. 添加用户登录和注册端点
-3. 包含使用 bcrypt 的密码哈希处理
-4. 设置令牌刷新机制
-
-Influence:
-1. 测试用户注册功能，包括有效和无效输入
-2. 验证登录功能，测试正确和错误的凭据
-3. 测试 JWT 令牌生成和验证流程
-4. 验证密码安全性和哈希处理
-5. 测试令牌刷新机制和过期处理
-6. 验证受保护端点的访问控制
-
-Please respond with ONLY the commit message following this format,
-DO NOT end commit titles with any punctuation."#;
-
-// 无测试建议无Log字段版本的提示词模板
-const ENGLISH_PROMPT_TEMPLATE_NO_TEST_NO_LOG: &str = r#"Please analyze the git diff content and generate a commit message in English only:
-1. First line: type: message (under 50 characters)
-2. Empty line after the title
-3. Detailed explanation in English (what was changed and why)
-4. Type must be one of: feat/fix/docs/style/refactor/test/chore
-5. Focus on both WHAT changed and WHY it was necessary
-6. Include any important technical details or context
-7. DO NOT include any Chinese content
-8. DO NOT wrap the response in any markdown or code block markers
-
-Example response format:
-feat: add user authentication module
-
-1. Implement JWT-based authentication system
-2. Add user login and registration endpoints
-3. Include password hashing with bcrypt
-4. Set up token refresh mechanism
-
-Please respond with ONLY the commit message following this format,
-DO NOT end commit titles with any punctuation."#;
-
-const CHINESE_PROMPT_TEMPLATE_NO_TEST_NO_LOG: &str = r#"请分析以下 git diff 内容，并按照以下格式生成提交信息：
-1. 第一行为标题：type: message（不超过50个字符）
-2. 标题下方空一行
-3. 详细的中文说明（解释做了什么改动以及为什么需要这些改动）
-4. type 必须是以下之一：feat/fix/docs/style/refactor/test/chore
-5. 关注点：变更内容（做了什么）和变更原因（为什么）
-6. 包含重要的技术细节或上下文
-7. 不要使用任何 markdown 或代码块标记
-8. 标题结尾不要使用标点符号
-
-示例格式：
-feat: 添加用户认证模块
-
-1. 实现基于 JWT 的认证系统
-2. 添加用户登录和注册端点
-3. 包含使用 bcrypt 的密码哈希处理
-4. 设置令牌刷新机制"#;
-
-const BILINGUAL_PROMPT_TEMPLATE_NO_TEST_NO_LOG: &str = r#"Please analyze the git diff content and generate a detailed bilingual commit message with:
-1. First line in English: type: message (under 50 characters)
-2. Empty line after the title
-3. Detailed explanation in English (what was changed and why)
-4. Empty line after English explanation
-5. Chinese title and explanation (translate the English content)
-6. Type must be one of: feat/fix/docs/style/refactor/test/chore
-7. Focus on both WHAT changed and WHY it was necessary
-8. Include any important technical details or context
-9. DO NOT wrap the response in any markdown or code block markers
-
-Example response format:
-feat: add user authentication module
-
-1. Implement JWT-based authentication system
-2. Add user login and registration endpoints
-3. Include password hashing with bcrypt
-4. Set up token refresh mechanism
-
-feat: 添加用户认证模块
-
-1. 实现基于 JWT 的认证系统
-2. 添加用户登录和注册端点
-3. 包含使用 bcrypt 的密码哈希处理
-4. 设置令牌刷新机制
-
-Please respond with ONLY the commit message following this format,
-DO NOT end commit titles with any punctuation."#;
-
-impl LanguageMode {
-    fn determine(only_chinese: bool, only_english: bool) -> Self {
-        if only_english {
-            Self::EnglishOnly
-        } else if only_chinese {
-            Self::ChineseOnly
-        } else {
-            Self::Bilingual
-        }
-    }
+/// 提交信息的标题、正文和尾部字段
+///
+/// 尾部字段的识别规则与 `git interpret-trailers` 一致：只有最后一段（且不是标题所在的段落）可以是尾部字段，
+/// 该段全部是尾部字段，或者包含 `Signed-off-by:` 等 git 生成的字段且尾部字段至少占四分之一时成立。
+/// 以空白开头的续行以及尾部字段段落中的其他行归入前一个字段，因此 `marks` 中的字段可能有多行。
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommitMessage {
+    pub title: String,
+    pub body: Option<String>,
+    pub marks: Vec<String>,
+}
 
-    fn template(&self, include_test_suggestions: bool, include_log: bool) -> &'static str {
-        match (self, include_test_suggestions, include_log) {
-            (Self::EnglishOnly, true, true) => ENGLISH_PROMPT_TEMPLATE,
-            (Self::EnglishOnly, false, true) => ENGLISH_PROMPT_TEMPLATE_NO_TEST,
-            (Self::EnglishOnly, true, false) => ENGLISH_PROMPT_TEMPLATE_NO_LOG,
-            (Self::EnglishOnly, false, false) => ENGLISH_PROMPT_TEMPLATE_NO_TEST_NO_LOG,
-            (Self::ChineseOnly, true, true) => CHINESE_PROMPT_TEMPLATE,
-            (Self::ChineseOnly, false, true) => CHINESE_PROMPT_TEMPLATE_NO_TEST,
-            (Self::ChineseOnly, true, false) => CHINESE_PROMPT_TEMPLATE_NO_LOG,
-            (Self::ChineseOnly, false, false) => CHINESE_PROMPT_TEMPLATE_NO_TEST_NO_LOG,
-            (Self::Bilingual, true, true) => BILINGUAL_PROMPT_TEMPLATE,
-            (Self::Bilingual, false, true) => BILINGUAL_PROMPT_TEMPLATE_NO_TEST,
-            (Self::Bilingual, true, false) => BILINGUAL_PROMPT_TEMPLATE_NO_LOG,
-            (Self::Bilingual, false, false) => BILINGUAL_PROMPT_TEMPLATE_NO_TEST_NO_LOG,
-        }
+/// git 自动添加的尾部字段前缀，段落中出现时放宽尾部字段的判断
+const GIT_GENERATED_PREFIXES: [&str; 2] = ["Signed-off-by: ", "(cherry picked from commit "];
+
+/// `git commit -v` 在提交信息模板中插入的剪切线，之后的内容是 diff，不属于提交信息
+pub(crate) const SCISSORS: &str = "------------------------ >8 ------------------------";
+
+/// 按 git 的规则判断是否为尾部字段行：行首是由字母、数字和 `-` 组成的字段名，之后可以有空白，然后是 `:`
+fn is_trailer_line(line: &str) -> bool {
+    if GIT_GENERATED_PREFIXES.iter().any(|prefix| line.starts_with(prefix)) {
+        return true;
     }
+    let token = line.split(':').next().unwrap_or_default().trim_end();
+    line.contains(':')
+        && !token.is_empty()
+        && token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
 }
 
-// 统一的提示词构建函数
-fn build_prompt(mode: LanguageMode, user_message: Option<&str>, include_test_suggestions: bool, include_log: bool) -> String {
-    let mut prompt = String::from(mode.template(include_test_suggestions, include_log));
-
-    if let Some(msg) = user_message {
-        match mode {
-            LanguageMode::ChineseOnly => {
-                prompt.push_str(&format!("\n\n用户描述：\n{}\n\n变更内容：\n", msg));
-            }
-            _ => {
-                prompt.push_str(&format!("\n\nUser Description:\n{}\n\nChanges:\n", msg));
-            }
-        }
-    } else {
-        match mode {
-            LanguageMode::ChineseOnly => {
-                prompt.push_str("\n\n变更内容：\n");
-            }
-            _ => {
-                prompt.push_str("\n\nHere are the changes:\n");
-            }
+/// 判断段落是否为尾部字段段落，规则与 git 的 find_trailer_block_start 相同
+fn is_trailer_block(lines: &[&str]) -> bool {
+    let mut trailer_lines = 0;
+    let mut non_trailer_lines = 0;
+    let mut possible_continuation_lines = 0;
+    let mut recognized_prefix = false;
+    for line in lines.iter().rev() {
+        if GIT_GENERATED_PREFIXES.iter().any(|prefix| line.starts_with(prefix)) {
+            recognized_prefix = true;
+            trailer_lines += 1;
+            possible_continuation_lines = 0;
+        } else if is_trailer_line(line) {
+            trailer_lines += 1;
+            possible_continuation_lines = 0;
+        } else if line.starts_with([' ', '\t']) {
+            possible_continuation_lines += 1;
+        } else {
+            non_trailer_lines += possible_continuation_lines + 1;
+            possible_continuation_lines = 0;
         }
     }
-
-    prompt
+    non_trailer_lines += possible_continuation_lines;
+    (trailer_lines > 0 && non_trailer_lines == 0) || (recognized_prefix && trailer_lines * 3 >= non_trailer_lines)
 }
 
-pub struct CommitMessage {
-    pub title: String,
-    pub body: Option<String>,
-    pub marks: Vec<String>,
+/// 工具生成的段落的字段名，这些段落跟在正文之后，不属于 git 的尾部字段
+const TOOL_SECTIONS: [&str; 2] = ["Log", "Influence"];
+
+/// 段落是否以工具生成的 Log、Influence 字段开头
+fn is_tool_section(paragraph: &str) -> bool {
+    paragraph.split_once(':')
+        .is_some_and(|(key, _)| TOOL_SECTIONS.iter().any(|section| section.eq_ignore_ascii_case(key.trim())))
 }
 
 impl CommitMessage {
+    /// 解析提交信息，注释符号使用仓库配置的 `core.commentChar`
     pub fn parse(content: &str) -> Self {
-        let mark_regex = Regex::new(r"^[a-zA-Z-]+:\s*.+$").unwrap();
-        let comment_regex = Regex::new(r"^#.*$").unwrap();
-        let mut lines = content.lines().peekable();
-
-        // 获取第一个非注释行作为标题
-        let title = lines
-            .by_ref()
-            .find(|line| !comment_regex.is_match(line.trim()))
-            .unwrap_or("")
-            .to_string();
-
-        let mut body = Vec::new();
-        let mut marks = Vec::new();
-        let mut is_body = false;
-
-        while let Some(line) = lines.next() {
-            // 跳过注释行
-            if comment_regex.is_match(line.trim()) {
+        Self::parse_with_comment_char(content, git::comment_char())
+    }
+
+    pub fn parse_with_comment_char(content: &str, comment_char: char) -> Self {
+        // 与 `git commit` 的清理方式一致：去掉剪切线之后的内容和注释行，去掉行尾空白并合并连续的空行
+        let scissors = format!("{} {}", comment_char, SCISSORS);
+        let mut lines: Vec<&str> = Vec::new();
+        for line in content.lines().take_while(|line| *line != scissors) {
+            let line = line.trim_end();
+            if line.starts_with(comment_char) || (line.is_empty() && lines.last().is_none_or(|last| last.is_empty())) {
                 continue;
             }
+            lines.push(line);
+        }
+        while lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+
+        let Some((title, rest)) = lines.split_first() else {
+            return CommitMessage { title: String::new(), body: None, marks: Vec::new() };
+        };
 
-            if line.trim().is_empty() {
-                if !is_body && body.is_empty() {
-                    continue;
+        // 最后一段是尾部字段段落时拆分出来，标题所在的段落不会被当作尾部字段
+        let (body, block) = match rest.iter().rposition(|line| line.is_empty()) {
+            Some(index) if is_trailer_block(&rest[index + 1..]) => (&rest[..index], &rest[index + 1..]),
+            _ => (rest, &rest[..0]),
+        };
+
+        let mut marks: Vec<String> = Vec::new();
+        for line in block {
+            match marks.last_mut() {
+                Some(mark) if !is_trailer_line(line) => {
+                    mark.push('\n');
+                    mark.push_str(line);
                 }
-                is_body = true;
-                body.push(line.to_string());
-            } else if mark_regex.is_match(line) {
-                marks.push(line.to_string());
-            } else {
-                is_body = true;
-                body.push(line.to_string());
+                _ => marks.push(line.to_string()),
             }
         }
 
-        // 移除body末尾的空行
-        while body.last().map_or(false, |line| line.trim().is_empty()) {
-            body.pop();
+        let body = body.iter().skip_while(|line| line.is_empty()).copied().collect::<Vec<_>>().join("\n");
+        CommitMessage {
+            title: title.to_string(),
+            body: Some(body).filter(|body| !body.is_empty()),
+            marks,
         }
+    }
 
-        // 移除 body 中的注释行
-        let body = if body.is_empty() {
-            None
-        } else {
-            Some(body
-                .into_iter()
-                .filter(|line| !comment_regex.is_match(line.trim()))
-                .collect::<Vec<_>>()
-                .join("\n"))
-        };
+    /// 拆分出工具生成的 Log、Influence 段落并按原有顺序返回，包括正文末尾以这些字段开头的段落，
+    /// 以及尾部字段段落中的这些字段
+    pub fn take_sections(&mut self) -> Vec<String> {
+        let mut sections = Vec::new();
+        if let Some(body) = self.body.take() {
+            let mut paragraphs: Vec<&str> = body.split("\n\n").collect();
+            let start = paragraphs.iter().rposition(|paragraph| !is_tool_section(paragraph)).map_or(0, |i| i + 1);
+            sections.extend(paragraphs.drain(start..).map(str::to_string));
+            self.body = Some(paragraphs.join("\n\n")).filter(|body| !body.is_empty());
+        }
 
-        CommitMessage {
-            title,
-            body,
-            marks,
+        let (marked, marks): (Vec<String>, Vec<String>) = self.marks.drain(..).partition(|mark| is_tool_section(mark));
+        self.marks = marks;
+        if !marked.is_empty() {
+            sections.push(marked.join("\n"));
         }
+        sections
     }
 
     pub fn format(&self) -> String {
@@ -659,35 +277,120 @@ impl CommitMessage {
             }
         }
 
-        // 添加标记
+        // 添加标记，按固定顺序排列
         if !self.marks.is_empty() {
             if !result.last().map_or(false, |s| s.is_empty()) {
                 result.push(String::new());  // 添加空行分隔
             }
-            result.extend(self.marks.clone());
+            let mut marks = self.marks.clone();
+            marks.sort_by_key(|mark| trailer_rank(mark));
+            result.extend(marks);
         }
 
         result.join("\n")
     }
 }
 
+/// 尾部字段的排列顺序：Fixes、BUG 等工单引用、其他字段、Change-Id、Signed-off-by
+///
+/// Log、Influence 是正文之后的段落而不是尾部字段，不参与排序，见 [`CommitMessage::take_sections`]。
+fn trailer_rank(line: &str) -> usize {
+    let key = line.split(':').next().unwrap_or_default().trim().to_ascii_lowercase();
+    match key.as_str() {
+        "fixes" => 0,
+        "bug" | "task" | "story" | "pms" => 1,
+        "change-id" => 3,
+        "signed-off-by" => 4,
+        _ => 2,
+    }
+}
+
+/// 按尾部字段的顺序把 `trailer` 插入提交信息最后的尾部字段段落，最后一段不是尾部字段时另起一段
+pub fn insert_trailer(message: &str, trailer: &str) -> String {
+    let message = message.trim_end();
+    let (head, last) = match message.rfind("\n\n") {
+        Some(index) => (&message[..index], &message[index + 2..]),
+        None => return format!("{}\n\n{}", message, trailer),
+    };
+    let mut lines: Vec<&str> = last.lines().collect();
+    if !is_trailer_block(&lines) {
+        return format!("{}\n\n{}", message, trailer);
+    }
+
+    // 只在字段的起始行之前插入，续行保持跟在所属字段之后
+    let rank = trailer_rank(trailer);
+    let position = lines.iter()
+        .position(|line| is_trailer_line(line) && trailer_rank(line) > rank)
+        .unwrap_or(lines.len());
+    lines.insert(position, trailer);
+    format!("{}\n\n{}", head, lines.join("\n"))
+}
+
+use crate::ai_service::Translator;
+use crate::commit_session::{self, CommitSession, DiffLine};
+use crate::commit_split;
+use crate::commit_style;
+use crate::fixup;
+use crate::gerrit;
 use crate::review;
-use dialoguer::Confirm;
+use crate::terminal_format::Style;
+use dialoguer::{Input, Select};
 use log::{debug, info};
 use std::process::Command;
 
-pub async fn generate_commit_message(
-    commit_type: Option<String>,
-    message: Option<String>,
-    auto_add: bool,
-    no_review: bool,
-    no_translate: bool,
-    mut only_chinese: bool,
-    mut only_english: bool,
-    no_influence: bool,
-    no_log: bool,
-    issues: Option<String>,
-) -> anyhow::Result<()> {
+/// 预览提交信息后可选的操作
+const MENU_ITEMS: [&str; 6] = [
+    "使用此提交信息",
+    "在编辑器中修改",
+    "补充说明后重新生成",
+    "切换语言模式",
+    "仅重新生成标题",
+    "取消提交",
+];
+
+/// `commit` 命令的参数
+pub struct CommitOptions {
+    pub commit_type: Option<String>,
+    pub message: Option<String>,
+    pub auto_add: bool,
+    pub no_review: bool,
+    pub no_translate: bool,
+    pub only_chinese: bool,
+    pub only_english: bool,
+    pub no_influence: bool,
+    pub no_log: bool,
+    pub issues: Option<String>,
+    pub learn_style: bool,
+    pub candidates: Option<usize>,
+    pub split: bool,
+    pub amend: bool,
+    /// fixup 的目标提交，`Some("")` 表示根据暂存的改动建议目标提交
+    pub fixup: Option<String>,
+}
+
+/// 生成提交信息所需的设置，拆分提交时各组共用
+pub struct MessageContext<'a> {
+    pub config: &'a config::Config,
+    pub translator: &'a dyn Translator,
+    pub mode: LanguageMode,
+    pub sections: Sections,
+    pub commit_type: Option<String>,
+    pub reference: Option<String>,
+    pub review: Option<String>,
+    pub learn_style: bool,
+    pub candidates: Option<usize>,
+    /// 修改上一次提交，而不是创建新的提交
+    pub amend: bool,
+    /// 需要保留的尾部字段，例如修改上一次提交时原有的 Change-Id 和 Signed-off-by
+    pub trailers: Vec<String>,
+}
+
+pub async fn generate_commit_message(options: CommitOptions) -> anyhow::Result<()> {
+    let CommitOptions {
+        commit_type, message, auto_add, no_review, no_translate, mut only_chinese, mut only_english,
+        no_influence, no_log, issues, learn_style, candidates, split, amend, fixup,
+    } = options;
+
     // 加载配置，如果指定了参数则使用参数值，否则使用配置中的默认值
     if let Ok(config) = config::Config::load() {
         if !only_chinese && !only_english {
@@ -720,17 +423,35 @@ pub async fn generate_commit_message(
         std::env::set_var("GIT_COMMIT_HELPER_NO_TRANSLATE", "1");
     }
 
-    let diff = get_staged_diff()?;
+    // fixup 提交的标题由 git 根据目标提交生成，不需要生成提交信息
+    if let Some(target) = fixup {
+        if get_staged_diff()?.is_empty() {
+            return Err(anyhow::anyhow!("没有已暂存的改动，请先使用 git add 添加改动"));
+        }
+        return fixup::commit_fixup(Some(target.as_str()).filter(|t| !t.is_empty()));
+    }
+
+    // 修改上一次提交时，根据上一次提交和暂存区合并后的改动重新生成提交信息
+    let diff = if amend { get_amend_diff()? } else { get_staged_diff()? };
     if diff.is_empty() {
         return Err(anyhow::anyhow!("没有已暂存的改动，请先使用 git add 添加改动"));
     }
+    let trailers = if amend { kept_trailers(&git_output(&["log", "-1", "--format=%B"])?) } else { Vec::new() };
 
     let config = config::Config::load()?;
+    if let Some(t) = commit_type.as_deref() {
+        let types = conventional::allowed_types(&config);
+        if !types.iter().any(|allowed| allowed == t) {
+            println!("{}", Style::yellow(&format!("提示：提交类型 {} 不在允许的类型列表中（{}）", t, types.join(", "))));
+        }
+    }
 
-    // 在确认有暂存的改动后执行代码审查
+    // 在确认有暂存的改动后执行代码审查，审查结果同时作为生成提交信息的上下文
+    let mut review = None;
     if !no_review && config.ai_review {
         info!("正在进行代码审查...");
-        if let Some(review) = review::review_changes(&config, no_review).await? {
+        review = review::review_changes(&config, no_review).await?;
+        if let Some(review) = &review {
             println!("\n{}\n", review);
         }
     }
@@ -738,29 +459,278 @@ pub async fn generate_commit_message(
     // 设置环境变量标记跳过后续的代码审查
     std::env::set_var("GIT_COMMIT_HELPER_SKIP_REVIEW", "1");
 
-    // 确定语言模式并构建提示词，考虑是否包含测试建议
+    // 确定语言模式和需要生成的段落，构建提示词
     let language_mode = LanguageMode::determine(only_chinese, only_english);
-    let include_test_suggestions = !no_influence;
-    let include_log = !no_log;
-    let prompt = build_prompt(language_mode, message.as_deref(), include_test_suggestions, include_log);
-
-    debug!("生成的提示信息：\n{}", prompt);
+    let sections = Sections {
+        influence: !no_influence,
+        log: !no_log,
+        ..Default::default()
+    };
 
-    info!("使用 {:?} 服务生成提交信息", config.default_service);
+    info!("使用 {} 服务生成提交信息", config.default_service_name());
     let service = config.get_default_service()?;
     let translator = ai_service::create_translator_for_service(service).await?;
 
-    println!("\n正在生成提交信息建议...");
-    let mut message = translator.chat(&prompt, &diff).await?
-        .trim_start_matches("[NO_TRANSLATE]")
-        .trim_start_matches("、、、plaintext")
-        .trim()
-        .to_string();
+    // 如果指定了 issues 参数，生成引用字段
+    let reference = issues.and_then(|issues_str| match parse_issue_reference(&issues_str) {
+        Ok(reference) => Some(reference),
+        Err(e) => {
+            eprintln!("警告: 解析 issues 参数失败: {}", e);
+            None
+        }
+    });
+
+    let context = MessageContext {
+        config: &config,
+        translator: translator.as_ref(),
+        mode: language_mode,
+        sections,
+        commit_type,
+        reference,
+        review,
+        learn_style: learn_style || config.learn_style,
+        candidates,
+        amend,
+        trailers,
+    };
+    let result = if split {
+        commit_split::split_and_commit(&context, &diff, message).await
+    } else {
+        commit_once(&context, &diff, message).await
+    };
 
-    // 如果提供了具体的type，确保使用该type
-    if let Some(t) = commit_type {
-        message = ensure_commit_type(&message, &[t]);
+    // 清理环境变量（无论命令是否执行成功）
+    std::env::remove_var("GIT_COMMIT_HELPER_SKIP_REVIEW");
+    std::env::remove_var("GIT_COMMIT_HELPER_NO_TRANSLATE");
+    result
+}
+
+/// 为暂存的改动生成一条提交信息并提交
+pub(crate) async fn commit_once(context: &MessageContext<'_>, diff: &str, message: Option<String>) -> anyhow::Result<()> {
+    match compose_message(context, diff, message).await? {
+        Some(content) => {
+            run_git_commit(&content, if context.amend { &["--amend"] } else { &[] })?;
+            println!("提交成功！");
+        }
+        None => println!("已取消提交"),
     }
+    Ok(())
+}
+
+/// 执行 git commit，`args` 为额外的参数，例如 `--amend`
+pub(crate) fn run_git_commit(content: &str, args: &[&str]) -> anyhow::Result<()> {
+    let status = Command::new("git")
+        .current_dir(std::env::current_dir()?)
+        .arg("commit")
+        .args(args)
+        .arg("-m")
+        .arg(content)
+        .status()?;
+
+    if !status.success() {
+        return Err(anyhow::anyhow!("git commit 命令执行失败"));
+    }
+    Ok(())
+}
+
+/// 生成提交信息并预览，用户可以修改或重新生成，确认后返回最终内容，取消时返回 None
+pub(crate) async fn compose_message(
+    context: &MessageContext<'_>,
+    diff: &str,
+    message: Option<String>,
+) -> anyhow::Result<Option<String>> {
+    let PreparedSession { mut session, scope, breaking, condensed } =
+        prepare_session(context, diff, &git::staged_files(), message).await?;
+    let finalize = |message: &str| finalize_message(
+        message, context.commit_type.as_deref(), scope.as_deref(), context.reference.as_deref(), &context.trailers);
+
+    let mut content = match context.candidates {
+        Some(count) => {
+            println!("\n正在生成 {} 条候选提交信息...", count);
+            let candidates = session.generate_candidates(count).await?;
+            let chosen = &candidates[choose_candidate(&candidates)?];
+            session.choose(chosen);
+            finalize(chosen)
+        }
+        None => {
+            println!("\n正在生成提交信息建议...");
+            finalize(&session.generate(None).await?)
+        }
+    };
+
+    loop {
+        // 预览生成的提交信息
+        println!("\n生成的提交信息预览:");
+        println!("----------------------------------------");
+        println!("{}", content);
+        println!("----------------------------------------");
+        if condensed {
+            println!("提示：改动过大，提交信息基于分块生成的改动摘要，请仔细核对");
+        }
+        if !breaking.is_empty() && !conventional::is_marked_breaking(&content) {
+            println!("{}", Style::yellow(&format!(
+                "提示：改动删除了公开接口 {}，如果会破坏兼容性，请在标题中加上 ! 并补充 BREAKING CHANGE 说明",
+                breaking.join(", "))));
+        }
+
+        let selection = Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+            .with_prompt("是否使用此提交信息？")
+            .items(&MENU_ITEMS)
+            .default(0)
+            .interact()?;
+
+        let regenerated = match selection {
+            0 => break,
+            1 => {
+                match commit_session::edit_in_editor(&content)? {
+                    Some(edited) => {
+                        session.record_edit(&edited);
+                        content = edited;
+                    }
+                    None => println!("{}", Style::yellow("提交信息为空，保留修改前的内容")),
+                }
+                continue;
+            }
+            2 => {
+                let guidance: String = Input::new()
+                    .with_prompt("请输入补充说明（可留空）")
+                    .allow_empty(true)
+                    .interact_text()?;
+                let guidance = Some(guidance.trim().to_string()).filter(|g| !g.is_empty());
+                println!("\n正在重新生成提交信息...");
+                session.generate(guidance).await
+            }
+            3 => {
+                let labels: Vec<&str> = LanguageMode::ALL.iter().map(LanguageMode::label).collect();
+                let current = LanguageMode::ALL.iter().position(|m| *m == session.mode()).unwrap_or(0);
+                let index = Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+                    .with_prompt("请选择提交信息语言")
+                    .items(&labels)
+                    .default(current)
+                    .interact()?;
+                let mode = LanguageMode::ALL[index];
+                session.set_mode(mode);
+                println!("\n正在以{}重新生成提交信息...", mode.label());
+                session.generate(Some(format!("改为{}提交信息", mode.label()))).await
+            }
+            4 => {
+                let guidance: String = Input::new()
+                    .with_prompt("对标题的要求（可留空）")
+                    .allow_empty(true)
+                    .interact_text()?;
+                let guidance = Some(guidance.trim().to_string()).filter(|g| !g.is_empty());
+                println!("\n正在重新生成标题...");
+                session.regenerate_title(guidance).await
+            }
+            _ => return Ok(None),
+        };
+
+        // 重新生成失败时保留当前内容，用户可以重试或选择其他操作
+        match regenerated {
+            Ok(message) => content = finalize(&message),
+            Err(e) => println!("{}", Style::red(&format!("重新生成失败: {}", e))),
+        }
+    }
+
+    // Gerrit 仓库在确认后补上 Change-Id，修改上一次提交时沿用原有的 Change-Id
+    Ok(Some(gerrit::ensure_change_id(context.config, &content)?))
+}
+
+/// 准备好的生成会话，以及生成后处理提交信息需要的信息
+pub(crate) struct PreparedSession<'a> {
+    pub session: CommitSession<'a>,
+    pub scope: Option<String>,
+    pub breaking: Vec<String>,
+    /// diff 超出模型上下文，已被替换为分块摘要
+    pub condensed: bool,
+}
+
+/// 为改动准备生成会话：根据改动的文件推断 scope，检测被删除的公开接口，
+/// 需要时从提交历史中选取示例让生成结果沿用仓库的提交风格，改动过大时先压缩为摘要
+pub(crate) async fn prepare_session<'a>(
+    context: &MessageContext<'a>,
+    diff: &str,
+    files: &[String],
+    message: Option<String>,
+) -> anyhow::Result<PreparedSession<'a>> {
+    let config = context.config;
+    let service = config.get_default_service()?;
+
+    let hints = PromptHints {
+        scope: conventional::infer_scope(&config.scopes, files),
+        breaking: conventional::detect_breaking_changes(diff),
+        examples: if context.learn_style {
+            commit_style::examples(service, config.max_tokens)
+        } else {
+            Vec::new()
+        },
+    };
+    if let Some(scope) = &hints.scope {
+        debug!("根据暂存的文件推断 scope: {}", scope);
+    }
+    let prompt = commit_template::build_prompt(config, context.mode, context.sections, &hints, message.as_deref());
+
+    debug!("生成的提示信息：\n{}", prompt);
+
+    // 改动过大时先压缩为摘要，避免超出模型上下文
+    let prepared = diff_budget::prepare_diff(
+        context.translator, service, config.max_tokens, &prompt, diff).await?;
+
+    let scope = hints.scope.clone();
+    let breaking = hints.breaking.clone();
+    let session = CommitSession::new(
+        config, context.translator, context.mode, context.sections, message, prepared.content, context.review.clone())
+        .with_hints(hints);
+    Ok(PreparedSession { session, scope, breaking, condensed: prepared.condensed })
+}
+
+/// 展示各条候选提交信息与第一条的差异，并让用户选择其中一条
+fn choose_candidate(candidates: &[String]) -> anyhow::Result<usize> {
+    if candidates.len() == 1 {
+        println!("{}", Style::yellow("AI 服务只返回了一条提交信息"));
+        return Ok(0);
+    }
+
+    println!("\n{}", Style::title("候选 1:"));
+    println!("{}", candidates[0]);
+    for (i, candidate) in candidates.iter().enumerate().skip(1) {
+        println!("\n{}", Style::title(&format!("候选 {}（与候选 1 的差异）:", i + 1)));
+        for line in commit_session::diff_lines(&candidates[0], candidate) {
+            match line {
+                DiffLine::Same(text) => print!("{}", Style::plain(&format!("  {}", text))),
+                DiffLine::Removed(text) => print!("{}", Style::red(&format!("- {}", text))),
+                DiffLine::Added(text) => print!("{}", Style::green(&format!("+ {}", text))),
+            }
+        }
+    }
+
+    let titles: Vec<String> = candidates.iter()
+        .enumerate()
+        .map(|(i, candidate)| format!("候选 {}: {}", i + 1, candidate.lines().next().unwrap_or_default()))
+        .collect();
+    let selection = Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt("请选择要使用的提交信息")
+        .items(&titles)
+        .default(0)
+        .interact()?;
+    Ok(selection)
+}
+
+/// 处理模型返回的提交信息：替换为指定的 type、按 72 列换行并追加 issue 引用和需要保留的尾部字段
+pub(crate) fn finalize_message(
+    message: &str,
+    commit_type: Option<&str>,
+    scope: Option<&str>,
+    reference: Option<&str>,
+    trailers: &[String],
+) -> String {
+    // 如果提供了具体的type，确保使用该type
+    let message = match commit_type {
+        Some(t) => ensure_commit_type(message, &[t.to_string()]),
+        None => message.to_string(),
+    };
+    // 模型没有写 scope 时补上推断的 scope
+    let message = conventional::apply_to_message(&message, scope);
 
     // 处理换行
     let mut content = message.lines().map(|line| {
@@ -771,65 +741,66 @@ pub async fn generate_commit_message(
         }
     }).collect::<Vec<_>>().join("\n");
 
-    // 如果指定了 issues 参数，添加引用字段
-    if let Some(issues_str) = issues {
-        match parse_issue_reference(&issues_str) {
-            Ok(reference) => {
-                // 在提交信息末尾添加空行和引用字段
-                if !content.ends_with('\n') {
-                    content.push('\n');
-                }
-                content.push('\n');
-                content.push_str(&reference);
-            }
-            Err(e) => {
-                eprintln!("警告: 解析 issues 参数失败: {}", e);
-            }
+    // 在提交信息末尾添加空行和引用字段，手动编辑后已包含引用时不再重复添加
+    if let Some(reference) = reference.filter(|reference| !content.contains(reference)) {
+        if !content.ends_with('\n') {
+            content.push('\n');
         }
+        content.push('\n');
+        content.push_str(reference);
     }
 
-    // 预览生成的提交信息
-    println!("\n生成的提交信息预览:");
-    println!("----------------------------------------");
-    println!("{}", content);
-    println!("----------------------------------------");
-
-    // 移除翻译相关的询问，直接询问用户是否确认提交
-    if !Confirm::with_theme(&dialoguer::theme::ColorfulTheme::default())
-        .with_prompt("是否使用此提交信息？")
-        .default(true)
-        .interact()?
-    {
-        println!("已取消提交");
-        return Ok(());
+    // 按顺序插入保留的尾部字段，已包含的不再重复添加
+    for trailer in trailers {
+        if !content.lines().any(|line| line == trailer.as_str()) {
+            content = insert_trailer(&content, trailer);
+        }
     }
+    content
+}
 
-    // 执行git commit
-    let status = Command::new("git")
-        .current_dir(std::env::current_dir()?)
-        .arg("commit")
-        .arg("-m")
-        .arg(content)
-        .status()?;
-
-    // 清理环境变量（无论命令是否执行成功）
-    std::env::remove_var("GIT_COMMIT_HELPER_SKIP_REVIEW");
-    std::env::remove_var("GIT_COMMIT_HELPER_NO_TRANSLATE");
+/// 由工具生成的尾部字段，修改上一次提交时重新生成，不从原提交信息中保留
+const GENERATED_TRAILERS: [&str; 7] = ["Log", "Influence", "Fixes", "PMS", "BUG", "TASK", "STORY"];
+
+/// 提取提交信息尾部字段中需要保留的字段，例如 Change-Id 和 Signed-off-by
+pub(crate) fn kept_trailers(message: &str) -> Vec<String> {
+    CommitMessage::parse(message).marks.into_iter()
+        .filter(|mark| {
+            let key = mark.split(':').next().unwrap_or_default().trim();
+            mark.contains(':') && !GENERATED_TRAILERS.iter().any(|generated| generated.eq_ignore_ascii_case(key))
+        })
+        .collect()
+}
 
-    if !status.success() {
-        return Err(anyhow::anyhow!("git commit 命令执行失败"));
+/// 执行 git 命令并返回标准输出
+fn git_output(args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new("git").args(args).output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("git {} 执行失败: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()));
     }
+    Ok(String::from_utf8(output.stdout)?)
+}
 
-    println!("提交成功！");
-    Ok(())
+/// 上一次提交与暂存区合并后的改动，即修改上一次提交后该提交的完整 diff
+fn get_amend_diff() -> anyhow::Result<String> {
+    git_output(&["rev-parse", "--verify", "-q", "HEAD"])
+        .map_err(|_| anyhow::anyhow!("当前分支还没有任何提交，无法使用 --amend"))?;
+    if git_output(&["rev-parse", "--verify", "-q", "HEAD^2"]).is_ok() {
+        return Err(anyhow::anyhow!("上一次提交是合并提交，不支持使用 --amend 重新生成提交信息"));
+    }
+    // 根提交没有父提交，与空树比较
+    let base = match git_output(&["rev-parse", "--verify", "-q", "HEAD^"]) {
+        Ok(parent) => parent.trim().to_string(),
+        Err(_) => git_output(&["hash-object", "-t", "tree", "/dev/null"])?.trim().to_string(),
+    };
+    let diff = git_output(&["diff", "--cached", "--no-prefix", &base])?;
+    Ok(diff_filter::filter_diff(&diff))
 }
 
 #[allow(dead_code)]
 pub async fn generate_commit_suggestion(commit_types: &[String], user_description: Option<String>) -> anyhow::Result<String> {
     let config = crate::config::Config::load()?;
-    let service = config.services.iter()
-        .find(|s| s.service == config.default_service)
-        .ok_or_else(|| anyhow::anyhow!("找不到默认服务的配置"))?;
+    let service = config.get_default_service()?;
 
     let translator = ai_service::create_translator_for_service(service).await?;
     let prompt = match user_description {
@@ -854,7 +825,7 @@ pub async fn generate_commit_suggestion(commit_types: &[String], user_descriptio
     Ok(message)
 }
 
-fn get_staged_diff() -> anyhow::Result<String> {
+pub(crate) fn get_staged_diff() -> anyhow::Result<String> {
     let output = Command::new("git")
         .args(["diff", "--cached", "--no-prefix"])
         .output()?;
@@ -863,25 +834,22 @@ fn get_staged_diff() -> anyhow::Result<String> {
         return Err(anyhow::anyhow!("执行 git diff 命令失败"));
     }
 
-    Ok(String::from_utf8(output.stdout)?)
+    Ok(diff_filter::filter_diff(&String::from_utf8(output.stdout)?))
 }
 
 fn ensure_commit_type(message: &str, commit_types: &[String]) -> String {
     let first_line = message.lines().next().unwrap_or_default();
-
-    if let Some(colon_pos) = first_line.find(':') {
-        let current_type = first_line[..colon_pos].trim();
-        if !commit_types.contains(&current_type.to_string()) {
-            return format!("{}: {}",
-                &commit_types[0],
-                first_line[colon_pos + 1..].trim()
-            ) + &message[first_line.len()..];
+    let rest = &message[first_line.len()..];
+
+    // 替换类型时保留原有的 scope 和破坏性变更标记
+    match conventional::Title::parse(first_line) {
+        Some(title) if commit_types.contains(&title.commit_type) => message.to_string(),
+        Some(mut title) => {
+            title.commit_type = commit_types[0].clone();
+            title.format() + rest
         }
-    } else {
-        return format!("{}: {}", &commit_types[0], first_line) + &message[first_line.len()..];
+        None => format!("{}: {}", &commit_types[0], first_line.trim()) + rest,
     }
-
-    message.to_string()
 }
 
 #[cfg(test)]
@@ -985,6 +953,185 @@ mod tests {
         assert_eq!(*pms_line, "PMS: TASK-374223");
     }
 
+    #[test]
+    fn test_kept_trailers() {
+        let message = "fix: crash\n\nbody\n\nLog: 修复崩溃\nFixes: #12\nChange-Id: I0123abcd\nSigned-off-by: A <a@b.c>\n";
+        assert_eq!(kept_trailers(message), vec!["Change-Id: I0123abcd", "Signed-off-by: A <a@b.c>"]);
+        assert!(kept_trailers("fix: crash\n\nChange-Id: I0123 is mentioned in body\n\nbody").is_empty());
+        assert!(kept_trailers("Change-Id: I0123").is_empty());
+        // 最后一段不是尾部字段段落时，其中的 `Key: value` 属于正文
+        assert!(kept_trailers("fix: crash\n\nbody\n\nNote: tricky\nsee the comment above").is_empty());
+        assert_eq!(kept_trailers("fix: crash\n\nReviewed-by: A <a@b.c>\n  on behalf of B\nLog: 修复崩溃\n"),
+            vec!["Reviewed-by: A <a@b.c>\n  on behalf of B"]);
+    }
+
+    #[test]
+    fn test_finalize_message_keeps_trailers() {
+        let trailers = vec!["Change-Id: I0123".to_string(), "Signed-off-by: A <a@b.c>".to_string()];
+        assert_eq!(finalize_message("fix: crash\n\nbody", None, None, None, &trailers),
+            "fix: crash\n\nbody\n\nChange-Id: I0123\nSigned-off-by: A <a@b.c>");
+        assert_eq!(finalize_message("fix: crash", None, None, Some("Fixes: #1"), &trailers),
+            "fix: crash\n\nFixes: #1\nChange-Id: I0123\nSigned-off-by: A <a@b.c>");
+        assert_eq!(finalize_message("fix: crash\n\nChange-Id: I0123", None, None, None, &trailers[..1]),
+            "fix: crash\n\nChange-Id: I0123");
+    }
+
+    #[test]
+    fn test_insert_trailer() {
+        let change_id = "Change-Id: I0123";
+        assert_eq!(insert_trailer("fix: crash", change_id), "fix: crash\n\nChange-Id: I0123");
+        assert_eq!(insert_trailer("fix: crash\n\nInfluence:\n1. test\n", change_id),
+            "fix: crash\n\nInfluence:\n1. test\n\nChange-Id: I0123");
+        assert_eq!(insert_trailer("fix: crash\n\nLog: 修复崩溃\nSigned-off-by: A <a@b.c>", change_id),
+            "fix: crash\n\nLog: 修复崩溃\nChange-Id: I0123\nSigned-off-by: A <a@b.c>");
+        assert_eq!(insert_trailer("fix: crash\n\nFixes: #1\n  #2", "BUG: 123"),
+            "fix: crash\n\nFixes: #1\n  #2\nBUG: 123");
+        assert_eq!(insert_trailer("fix: crash\n\nReviewed-by: A <a@b.c>\n  on behalf of B\nSigned-off-by:A <a@b.c>", change_id),
+            "fix: crash\n\nReviewed-by: A <a@b.c>\n  on behalf of B\nChange-Id: I0123\nSigned-off-by:A <a@b.c>");
+        assert_eq!(insert_trailer("fix: crash\n\nNote: tricky\nsee the comment above", change_id),
+            "fix: crash\n\nNote: tricky\nsee the comment above\n\nChange-Id: I0123");
+    }
+
+    #[test]
+    fn test_format_orders_marks() {
+        let message = CommitMessage {
+            title: "fix: crash".to_string(),
+            body: Some("body".to_string()),
+            marks: vec![
+                "Signed-off-by: A <a@b.c>".to_string(),
+                "Change-Id: I0123".to_string(),
+                "Reviewed-by: B <b@c.d>".to_string(),
+                "BUG: 123".to_string(),
+                "Fixes: #1".to_string(),
+            ],
+        };
+        assert_eq!(message.format(), "fix: crash\n\nbody\n\nFixes: #1\nBUG: 123\nReviewed-by: B <b@c.d>\nChange-Id: I0123\nSigned-off-by: A <a@b.c>");
+    }
+
+    #[test]
+    fn test_generated_message_layout() {
+        // 按默认模板生成的提交信息，使用 --issues 并修改上一次提交，提交到 Gerrit 仓库
+        let generated = "fix(dock): release tray item before unloading\n\nRelease the tray item before unloading the plugin.\n\nLog: 修复任务栏插件卸载时崩溃的问题\n\nInfluence:\n1. 测试插件加载和卸载\n2. 验证托盘图标显示正常";
+        let reference = parse_issue_reference("42").unwrap();
+        let trailers = vec!["Signed-off-by: A <a@b.c>".to_string()];
+        let content = finalize_message(generated, None, None, Some(&reference), &trailers);
+        // change_id 为 true 与远程仓库是 Gerrit 时的行为相同，不依赖当前仓库的远程地址
+        let config = config::Config { change_id: Some(true), ..config::Config::new() };
+        let content = gerrit::ensure_change_id(&config, &content).unwrap();
+
+        let change_id = gerrit::find_change_id(&content).unwrap();
+        assert_eq!(content, format!("fix(dock): release tray item before unloading\n\n\
+            Release the tray item before unloading the plugin.\n\n\
+            Log: 修复任务栏插件卸载时崩溃的问题\n\n\
+            Influence:\n1. 测试插件加载和卸载\n2. 验证托盘图标显示正常\n\n\
+            Fixes: #42\nChange-Id: {}\nSigned-off-by: A <a@b.c>", change_id));
+    }
+
+    #[test]
+    fn test_take_sections() {
+        let mut message = CommitMessage::parse_with_comment_char("fix: crash\n\nbody\n\nLog: 修复崩溃\nInfluence:\n1. test\n", '#');
+        assert_eq!(message.take_sections(), vec!["Log: 修复崩溃\nInfluence:\n1. test"]);
+        assert_eq!(message.body.as_deref(), Some("body"));
+
+        // 只拆分正文末尾的段落，正文中间提到的 Log 保持不变
+        let mut message = CommitMessage::parse_with_comment_char("fix: crash\n\nLog: 修复崩溃\n\nbody\n", '#');
+        assert!(message.take_sections().is_empty());
+        assert_eq!(message.body.as_deref(), Some("Log: 修复崩溃\n\nbody"));
+    }
+
+    /// (提交信息, 标题, 正文, 尾部字段)，尾部字段与 `git interpret-trailers --parse` 的识别结果一致
+    const PARSE_CORPUS: [(&str, &str, Option<&str>, &[&str]); 10] = [
+        // 内核风格的尾部字段
+        (
+            "mm: fix use-after-free in page cache\n\nThe page may be freed before the reference is dropped.\n\nFixes: 1234567890ab (\"mm: add page cache\")\nReported-by: Alice <alice@example.com>\nSigned-off-by: Bob <bob@example.com>\n",
+            "mm: fix use-after-free in page cache",
+            Some("The page may be freed before the reference is dropped."),
+            &["Fixes: 1234567890ab (\"mm: add page cache\")", "Reported-by: Alice <alice@example.com>", "Signed-off-by: Bob <bob@example.com>"],
+        ),
+        // 正文中间段落的 `Note:` 不是尾部字段
+        (
+            "feat(dock): add tray plugin loader\n\nNote: plugins are loaded lazily.\nScope: only affects the tray area.\n\nLoad plugins on first show to speed up startup.\n",
+            "feat(dock): add tray plugin loader",
+            Some("Note: plugins are loaded lazily.\nScope: only affects the tray area.\n\nLoad plugins on first show to speed up startup."),
+            &[],
+        ),
+        // 以空白开头的续行
+        (
+            "fix: handle empty config\n\nCo-authored-by: Alice <alice@example.com>\nReviewed-by: Bob <bob@example.com>\n  on behalf of the release team\n",
+            "fix: handle empty config",
+            None,
+            &["Co-authored-by: Alice <alice@example.com>", "Reviewed-by: Bob <bob@example.com>\n  on behalf of the release team"],
+        ),
+        // cherry-pick 生成的行使段落中的普通行也算在尾部字段段落中
+        (
+            "fix: crash on exit\n\nBackport of upstream fix.\n\nThis line is not a trailer\n(cherry picked from commit 0123456789abcdef0123456789abcdef01234567)\n",
+            "fix: crash on exit",
+            Some("Backport of upstream fix."),
+            &["This line is not a trailer", "(cherry picked from commit 0123456789abcdef0123456789abcdef01234567)"],
+        ),
+        // Influence 的列表不是尾部字段，没有 git 生成的字段时整段都是正文
+        (
+            "fix: crash when dock plugin unloads\n\nRelease the tray item before unloading the plugin.\n\nLog: 修复任务栏插件卸载时崩溃的问题\nInfluence:\n1. 测试插件加载和卸载\n2. 验证托盘图标显示正常\nChange-Id: I3f1c2e4b5a6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f\n",
+            "fix: crash when dock plugin unloads",
+            Some("Release the tray item before unloading the plugin.\n\nLog: 修复任务栏插件卸载时崩溃的问题\nInfluence:\n1. 测试插件加载和卸载\n2. 验证托盘图标显示正常\nChange-Id: I3f1c2e4b5a6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f"),
+            &[],
+        ),
+        // 有 Signed-off-by 时放宽判断，列表归入 Influence
+        (
+            "fix: crash when dock plugin unloads\n\nRelease the tray item before unloading the plugin.\n\nLog: 修复任务栏插件卸载时崩溃的问题\nInfluence:\n1. 测试插件加载和卸载\n2. 验证托盘图标显示正常\nChange-Id: I3f1c2e4b5a6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f\nSigned-off-by: Bob <bob@example.com>\n",
+            "fix: crash when dock plugin unloads",
+            Some("Release the tray item before unloading the plugin."),
+            &["Log: 修复任务栏插件卸载时崩溃的问题", "Influence:\n1. 测试插件加载和卸载\n2. 验证托盘图标显示正常", "Change-Id: I3f1c2e4b5a6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f", "Signed-off-by: Bob <bob@example.com>"],
+        ),
+        // 标题所在的段落不会被当作尾部字段
+        (
+            "fix: x\nSigned-off-by: a <a@b>\n",
+            "fix: x",
+            Some("Signed-off-by: a <a@b>"),
+            &[],
+        ),
+        // 字段名和冒号之间可以有空白，链接的协议名也符合字段名的格式
+        (
+            "fix: x\n\nKey : value\nhttps://example.com/a\n",
+            "fix: x",
+            None,
+            &["Key : value", "https://example.com/a"],
+        ),
+        // `git commit -v` 的模板：去掉注释和剪切线之后的 diff
+        (
+            "feat: add tray loader\n\nLoad plugins lazily.\n\nLog: 延迟加载插件\n\n# Please enter the commit message for your changes.\n#\n# ------------------------ >8 ------------------------\n# Do not modify or remove the line above.\ndiff --git a/a.rs b/a.rs\n+Signed-off-by: x <x@y>\n",
+            "feat: add tray loader",
+            Some("Load plugins lazily."),
+            &["Log: 延迟加载插件"],
+        ),
+        // 中英双语提交信息
+        (
+            "fix: crash on exit\n\nRelease resources before exit.\n\n修复退出时崩溃\n\n释放资源后再退出。\n\nLog: 修复退出时崩溃\nFixes: #42\n",
+            "fix: crash on exit",
+            Some("Release resources before exit.\n\n修复退出时崩溃\n\n释放资源后再退出。"),
+            &["Log: 修复退出时崩溃", "Fixes: #42"],
+        ),
+    ];
+
+    #[test]
+    fn test_parse_trailer_corpus() {
+        for (content, title, body, marks) in PARSE_CORPUS {
+            let message = CommitMessage::parse_with_comment_char(content, '#');
+            assert_eq!(message.title, title, "{}", content);
+            assert_eq!(message.body.as_deref(), body, "{}", content);
+            assert_eq!(message.marks, marks, "{}", content);
+        }
+    }
+
+    #[test]
+    fn test_parse_comment_char() {
+        let content = "; 注释\nfix: include header\n\n#include <stdio.h> is required\n; 另一行注释\n\nSigned-off-by: A <a@b.c>\n\n; ------------------------ >8 ------------------------\n+Fixes: #1\n";
+        let message = CommitMessage::parse_with_comment_char(content, ';');
+        assert_eq!(message.title, "fix: include header");
+        assert_eq!(message.body.as_deref(), Some("#include <stdio.h> is required"));
+        assert_eq!(message.marks, vec!["Signed-off-by: A <a@b.c>"]);
+    }
+
     // 辅助测试函数
     fn parse_pms_link_multiple(issues: &str) -> anyhow::Result<String> {
         parse_issue_reference(issues)
@@ -0,0 +1,391 @@
+// 提交信息生成会话
+// 可以一次生成多条候选提交信息供用户挑选；预览提交信息后可以补充说明重新生成、切换语言或只重新生成标题，
+// 每次请求都带上改动内容、代码审查结果和之前生成的版本及用户反馈，让模型在已有结果的基础上修改。
+
+use crate::ai_service::{self, Translator};
+use crate::commit_template::{self, LanguageMode, PromptHints, Sections};
+use crate::config::Config;
+use crate::git;
+use anyhow::Context;
+use log::debug;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// 候选提交信息之间的分隔行
+const CANDIDATE_SEPARATOR: &str = "=====";
+
+/// 一次生成的结果及用户随后给出的反馈
+struct Attempt {
+    message: String,
+    feedback: Option<String>,
+}
+
+pub struct CommitSession<'a> {
+    config: &'a Config,
+    translator: &'a dyn Translator,
+    mode: LanguageMode,
+    sections: Sections,
+    user_message: Option<String>,
+    diff: String,
+    review: Option<String>,
+    hints: PromptHints,
+    attempts: Vec<Attempt>,
+}
+
+impl<'a> CommitSession<'a> {
+    pub fn new(
+        config: &'a Config,
+        translator: &'a dyn Translator,
+        mode: LanguageMode,
+        sections: Sections,
+        user_message: Option<String>,
+        diff: String,
+        review: Option<String>,
+    ) -> Self {
+        Self { config, translator, mode, sections, user_message, diff, review, hints: PromptHints::default(), attempts: Vec::new() }
+    }
+
+    /// 设置 scope、破坏性变更和仓库提交风格等提示，生成时附在提示词中
+    pub fn with_hints(mut self, hints: PromptHints) -> Self {
+        self.hints = hints;
+        self
+    }
+
+    pub fn mode(&self) -> LanguageMode {
+        self.mode
+    }
+
+    /// 切换语言模式，之后的生成都使用新的模式
+    pub fn set_mode(&mut self, mode: LanguageMode) {
+        self.mode = mode;
+    }
+
+    fn prompt(&self) -> String {
+        commit_template::build_prompt(self.config, self.mode, self.sections, &self.hints, self.user_message.as_deref())
+    }
+
+    /// 生成完整的提交信息，`feedback` 作为对上一次结果的修改意见
+    pub async fn generate(&mut self, feedback: Option<String>) -> anyhow::Result<String> {
+        self.set_feedback(feedback);
+        let prompt = self.prompt();
+        let message = self.chat(&prompt, None).await?;
+        self.attempts.push(Attempt { message: message.clone(), feedback: None });
+        Ok(message)
+    }
+
+    /// 在一次请求中生成多条角度不同的候选提交信息，模型返回的条数可能少于要求的数量
+    pub async fn generate_candidates(&mut self, count: usize) -> anyhow::Result<Vec<String>> {
+        let prompt = self.prompt();
+        let instruction = match self.mode {
+            LanguageMode::ChineseOnly => format!(
+                "请生成 {} 条不同的提交信息，从不同角度描述本次改动（例如不同的 type、影响范围或侧重点），每条都遵循上述格式，相邻两条之间用只包含 {} 的一行分隔。",
+                count, CANDIDATE_SEPARATOR),
+            _ => format!(
+                "Please generate {} distinct commit messages that frame the change differently (e.g. different type, scope or focus). Each must follow the format above, separate them with a line containing only {}.",
+                count, CANDIDATE_SEPARATOR),
+        };
+        let response = self.chat(&prompt, Some(&instruction)).await?;
+        let candidates = split_candidates(&response);
+        if candidates.is_empty() {
+            return Err(anyhow::anyhow!("AI 服务没有返回任何提交信息"));
+        }
+        Ok(candidates)
+    }
+
+    /// 记录用户选中的候选提交信息，后续重新生成以它为基础
+    pub fn choose(&mut self, message: &str) {
+        self.attempts.push(Attempt { message: message.to_string(), feedback: None });
+    }
+
+    /// 只重新生成标题，正文保持不变
+    pub async fn regenerate_title(&mut self, feedback: Option<String>) -> anyhow::Result<String> {
+        let current = self.attempts.last()
+            .map(|attempt| attempt.message.clone())
+            .ok_or_else(|| anyhow::anyhow!("还没有生成过提交信息"))?;
+        self.set_feedback(feedback);
+
+        let sections = Sections { title: true, body: false, log: false, influence: false, trailers: false };
+        let prompt = commit_template::build_prompt(self.config, self.mode, sections, &self.hints, self.user_message.as_deref());
+        let instruction = match self.mode {
+            LanguageMode::ChineseOnly => "请根据以上反馈只重新生成最后一次提交信息的标题，只输出标题。",
+            _ => "Please regenerate ONLY the title of the latest commit message, respond with the title only.",
+        };
+        let titles = self.chat(&prompt, Some(instruction)).await?;
+        let message = replace_titles(&current, &titles);
+        self.attempts.push(Attempt { message: message.clone(), feedback: None });
+        Ok(message)
+    }
+
+    /// 记录用户手动修改后的版本，后续重新生成时以它为准
+    pub fn record_edit(&mut self, message: &str) {
+        self.set_feedback(Some(self.text("用户手动修改为下一个版本", "The user edited it into the next version")));
+        self.attempts.push(Attempt { message: message.to_string(), feedback: None });
+    }
+
+    fn set_feedback(&mut self, feedback: Option<String>) {
+        if let (Some(attempt), Some(feedback)) = (self.attempts.last_mut(), feedback) {
+            attempt.feedback = Some(feedback);
+        }
+    }
+
+    fn text(&self, zh: &str, en: &str) -> String {
+        match self.mode {
+            LanguageMode::ChineseOnly => zh.to_string(),
+            _ => en.to_string(),
+        }
+    }
+
+    async fn chat(&self, prompt: &str, instruction: Option<&str>) -> anyhow::Result<String> {
+        let content = self.conversation(instruction);
+        debug!("生成的提示信息：\n{}", prompt);
+        let message = ai_service::chat_with_live_output(self.translator, prompt, &content).await?;
+        Ok(message
+            .trim_start_matches("[NO_TRANSLATE]")
+            .trim_start_matches("、、、plaintext")
+            .trim()
+            .to_string())
+    }
+
+    /// 拼接改动内容、审查结果、历次生成记录和本次请求的要求
+    ///
+    /// 未指定要求时，有生成记录则要求根据反馈重新生成，否则只发送改动内容。
+    fn conversation(&self, instruction: Option<&str>) -> String {
+        let chinese = self.mode == LanguageMode::ChineseOnly;
+        let mut content = self.diff.clone();
+        if let Some(review) = &self.review {
+            let label = if chinese { "代码审查结果：" } else { "Code review result:" };
+            content.push_str(&format!("\n\n{}\n{}", label, review.trim()));
+        }
+        for (i, attempt) in self.attempts.iter().enumerate() {
+            let label = if chinese {
+                format!("第 {} 次生成的提交信息：", i + 1)
+            } else {
+                format!("Commit message attempt {}:", i + 1)
+            };
+            content.push_str(&format!("\n\n{}\n{}", label, attempt.message));
+            if let Some(feedback) = &attempt.feedback {
+                let label = if chinese { "用户反馈：" } else { "User feedback:" };
+                content.push_str(&format!("\n{}\n{}", label, feedback));
+            }
+        }
+
+        let instruction = match (instruction, chinese) {
+            (Some(instruction), _) => instruction,
+            (None, _) if self.attempts.is_empty() => return content,
+            (None, true) => "请根据以上反馈重新生成完整的提交信息。",
+            (None, false) => "Please generate a new commit message that addresses the feedback above.",
+        };
+        content.push_str("\n\n");
+        content.push_str(instruction);
+        content
+    }
+}
+
+/// 按分隔行拆分模型返回的多条候选提交信息，忽略空白的条目
+fn split_candidates(response: &str) -> Vec<String> {
+    let mut candidates = vec![String::new()];
+    for line in response.lines() {
+        if line.trim() == CANDIDATE_SEPARATOR {
+            candidates.push(String::new());
+        } else if let Some(current) = candidates.last_mut() {
+            current.push_str(line);
+            current.push('\n');
+        }
+    }
+    candidates.into_iter()
+        .map(|candidate| candidate.trim().to_string())
+        .filter(|candidate| !candidate.is_empty())
+        .collect()
+}
+
+/// 逐行比较两条提交信息时的一行结果
+#[derive(Debug, PartialEq)]
+pub enum DiffLine<'t> {
+    Same(&'t str),
+    Removed(&'t str),
+    Added(&'t str),
+}
+
+/// 基于最长公共子序列逐行比较两段文本
+pub fn diff_lines<'t>(old: &'t str, new: &'t str) -> Vec<DiffLine<'t>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // lcs[i][j] 为 old[i..] 与 new[j..] 的最长公共子序列长度
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut result = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            result.push(DiffLine::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            result.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            result.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+    result
+}
+
+/// 用新标题替换提交信息中的标题
+///
+/// 双语提交信息中的中文标题位于中文部分的第一行，即第一行之后首个紧跟在空行后、
+/// 形如 `type: message` 的行；模型返回两行标题时依次替换英文和中文标题。
+fn replace_titles(message: &str, titles: &str) -> String {
+    let titles: Vec<&str> = titles.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+    let mut lines: Vec<String> = message.lines().map(str::to_string).collect();
+    let Some(first) = titles.first() else {
+        return message.to_string();
+    };
+    match lines.first_mut() {
+        Some(line) => *line = first.to_string(),
+        None => lines.push(first.to_string()),
+    }
+
+    if let Some(second) = titles.get(1) {
+        let title_regex = Regex::new(r"^[a-z]+(\([^)]*\))?!?: \S").unwrap();
+        let index = (2..lines.len())
+            .find(|&i| lines[i - 1].trim().is_empty() && title_regex.is_match(&lines[i]));
+        if let Some(index) = index {
+            lines[index] = second.to_string();
+        }
+    }
+    lines.join("\n")
+}
+
+/// 在 `git var GIT_EDITOR` 指定的编辑器（依次取 GIT_EDITOR、core.editor、VISUAL、EDITOR）中修改提交信息
+///
+/// 返回去掉注释行后的内容，用户清空内容时返回 None。
+pub fn edit_in_editor(message: &str) -> anyhow::Result<Option<String>> {
+    let output = Command::new("git").args(["var", "GIT_EDITOR"]).output()
+        .context("无法执行 git var GIT_EDITOR")?;
+    let editor = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || editor.is_empty() {
+        return Err(anyhow::anyhow!("未找到可用的编辑器，请设置 EDITOR 环境变量或 git 的 core.editor"));
+    }
+
+    // 与 git 的 COMMIT_EDITMSG 一样放在仓库的 .git 目录中，而不是公共的临时目录
+    let output = Command::new("git").args(["rev-parse", "--git-path", "COMMIT_HELPER_EDITMSG"]).output()
+        .context("无法执行 git rev-parse")?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("无法确定提交信息文件的位置: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    let path = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    edit_with(&editor, &path, git::comment_char(), message)
+}
+
+/// 使用指定的编辑器命令在 `path` 中修改提交信息，以 `comment_char` 开头的行视为注释
+fn edit_with(editor: &str, path: &Path, comment_char: char, message: &str) -> anyhow::Result<Option<String>> {
+    std::fs::write(path, format!("{}\n\n{} 修改提交信息后保存并退出编辑器，以 {} 开头的行会被忽略\n", message, comment_char, comment_char))
+        .with_context(|| format!("无法写入文件: {}", path.display()))?;
+
+    // 与 git 一样通过 shell 启动编辑器，支持带参数的编辑器命令
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(format!("{} \"{}\"", editor, path.display()));
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(format!("{} \"$@\"", editor)).arg(editor).arg(path);
+        command
+    };
+    let status = command.status().with_context(|| format!("无法启动编辑器: {}", editor));
+    let content = std::fs::read_to_string(path);
+    let _ = std::fs::remove_file(path);
+
+    if !status?.success() {
+        return Err(anyhow::anyhow!("编辑器 {} 异常退出", editor));
+    }
+    let edited = strip_comments(&content?, comment_char);
+    Ok((!edited.is_empty()).then_some(edited))
+}
+
+fn strip_comments(content: &str, comment_char: char) -> String {
+    content.lines()
+        .filter(|line| !line.starts_with(comment_char))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_titles() {
+        let message = "feat: add login\n\n1. Add JWT\n\nfeat: 添加登录\n\n1. 添加 JWT\n\nLog: 新增登录";
+        assert_eq!(replace_titles(message, "feat(auth): support login\n"),
+            "feat(auth): support login\n\n1. Add JWT\n\nfeat: 添加登录\n\n1. 添加 JWT\n\nLog: 新增登录");
+        assert_eq!(replace_titles(message, "feat(auth): support login\n\nfeat(auth): 支持登录"),
+            "feat(auth): support login\n\n1. Add JWT\n\nfeat(auth): 支持登录\n\n1. 添加 JWT\n\nLog: 新增登录");
+        assert_eq!(replace_titles(message, "  \n"), message);
+    }
+
+    #[test]
+    fn test_split_candidates() {
+        let response = "fix: handle empty config\n\n1. Skip parsing\n=====\nrefactor: simplify loader\n\n =====\n\n";
+        assert_eq!(split_candidates(response), vec![
+            "fix: handle empty config\n\n1. Skip parsing".to_string(),
+            "refactor: simplify loader".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let diff = diff_lines("fix: a\n\nbody\nLog: x", "feat: a\n\nbody\nmore");
+        assert_eq!(diff, vec![
+            DiffLine::Removed("fix: a"),
+            DiffLine::Added("feat: a"),
+            DiffLine::Same(""),
+            DiffLine::Same("body"),
+            DiffLine::Removed("Log: x"),
+            DiffLine::Added("more"),
+        ]);
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        assert_eq!(strip_comments("fix: typo\n\nbody\n\n# 注释\n", '#'), "fix: typo\n\nbody");
+        assert_eq!(strip_comments("# 注释\n\n", '#'), "");
+        // 使用其他注释符号时，以 # 开头的正文行保留
+        assert_eq!(strip_comments("fix: typo\n\n#123 was caused by it\n; 注释\n", ';'), "fix: typo\n\n#123 was caused by it");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_edit_with() {
+        let dir = std::env::temp_dir().join(format!("gch-edit-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("COMMIT_HELPER_EDITMSG");
+
+        let editor = r#"sh -c 'sed "s/typo/spelling/" "$1" > "$1.tmp" && mv "$1.tmp" "$1"' editor"#;
+        let edited = edit_with(editor, &path, '#', "fix: typo in README").unwrap();
+        assert_eq!(edited.as_deref(), Some("fix: spelling in README"));
+        assert!(!path.exists());
+
+        let edited = edit_with(r##"sh -c 'printf "#123 typo\n" >> "$1"' editor"##, &path, ';', "fix: typo").unwrap();
+        assert_eq!(edited.as_deref(), Some("fix: typo\n\n#123 typo"));
+
+        let edited = edit_with(r#"sh -c ': > "$1"' editor"#, &path, '#', "fix: typo in README").unwrap();
+        assert_eq!(edited, None);
+        assert!(edit_with("false", &path, '#', "fix: typo in README").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
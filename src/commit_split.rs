@@ -0,0 +1,486 @@
+// 拆分提交
+// 把暂存区中的改动切分为改动块，由模型按逻辑分组，用户确认或调整分组后逐组提交：
+// 每组先把暂存区重置为 HEAD，再通过 `git apply --cached` 暂存该组的改动块，复用提交信息生成流程。
+// 提交前在临时索引中验证各组能依次应用且结果与原暂存内容一致；中途失败或取消时恢复原来的 HEAD 和暂存区。
+
+use crate::ai_service;
+use crate::commit::{self, MessageContext};
+use crate::commit_template::LanguageMode;
+use crate::diff_budget;
+use anyhow::{Context, Result};
+use dialoguer::{Input, Select};
+use log::{debug, warn};
+use regex::Regex;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use crate::terminal_format::Style;
+
+/// 分组时每个改动块最多展示的行数，改动过多时依次减少
+const PREVIEW_LINES: [usize; 3] = [40, 10, 0];
+
+/// 调整分组时可选的操作
+const MENU_ITEMS: [&str; 5] = [
+    "按此分组依次提交",
+    "移动改动块到其他分组",
+    "合并两个分组",
+    "修改分组标题",
+    "取消",
+];
+
+/// 一个文件的补丁，`hunks` 为各改动块的内容
+#[derive(Debug, Clone, PartialEq)]
+struct FilePatch {
+    path: String,
+    header: String,
+    hunks: Vec<String>,
+    /// 新增、删除、重命名、修改权限或二进制文件，只能整体提交
+    whole: bool,
+}
+
+/// 分组的最小单位：普通文件的一个改动块，或只能整体提交的整个文件
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Unit {
+    file: usize,
+    hunk: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Group {
+    title: String,
+    units: Vec<usize>,
+}
+
+/// 将 `git diff` 的输出按文件和改动块切分
+fn parse_patch(patch: &str) -> Vec<FilePatch> {
+    let mut files: Vec<FilePatch> = Vec::new();
+    for line in patch.split_inclusive('\n') {
+        if line.starts_with("diff --git ") {
+            let path = line.trim_end()
+                .strip_prefix("diff --git a/")
+                .and_then(|rest| rest.split_once(" b/"))
+                .map(|(_, path)| path.to_string())
+                .unwrap_or_default();
+            files.push(FilePatch { path, header: line.to_string(), hunks: Vec::new(), whole: false });
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+        if line.starts_with("@@") {
+            file.hunks.push(line.to_string());
+        } else if let Some(hunk) = file.hunks.last_mut() {
+            hunk.push_str(line);
+        } else {
+            if ["new file mode", "deleted file mode", "old mode", "rename from", "copy from", "similarity index", "GIT binary patch", "Binary files"]
+                .iter()
+                .any(|prefix| line.starts_with(prefix))
+            {
+                file.whole = true;
+            }
+            file.header.push_str(line);
+        }
+    }
+    files
+}
+
+fn units(files: &[FilePatch]) -> Vec<Unit> {
+    files.iter().enumerate().flat_map(|(file, patch)| {
+        if patch.whole || patch.hunks.is_empty() {
+            vec![Unit { file, hunk: None }]
+        } else {
+            (0..patch.hunks.len()).map(|hunk| Unit { file, hunk: Some(hunk) }).collect()
+        }
+    }).collect()
+}
+
+/// 改动块的简短描述，例如 `src/main.rs @@ -10,6 +10,8 @@ fn main()`
+fn describe(files: &[FilePatch], unit: Unit) -> String {
+    let file = &files[unit.file];
+    match unit.hunk {
+        Some(hunk) => format!("{} {}", file.path, file.hunks[hunk].lines().next().unwrap_or_default()),
+        None => format!("{} (整个文件)", file.path),
+    }
+}
+
+/// 生成只包含指定改动块的补丁，文件和改动块保持原有顺序
+fn build_patch(files: &[FilePatch], all: &[Unit], selected: &[usize]) -> String {
+    let mut patch = String::new();
+    for (index, file) in files.iter().enumerate() {
+        let chosen: Vec<Unit> = all.iter().enumerate()
+            .filter(|(i, unit)| unit.file == index && selected.contains(i))
+            .map(|(_, unit)| *unit)
+            .collect();
+        if chosen.is_empty() {
+            continue;
+        }
+        patch.push_str(&file.header);
+        for (i, hunk) in file.hunks.iter().enumerate() {
+            if chosen.iter().any(|unit| unit.hunk.is_none() || unit.hunk == Some(i)) {
+                patch.push_str(hunk);
+            }
+        }
+    }
+    patch
+}
+
+/// 交给模型分组的改动块列表，每个改动块最多展示 `preview` 行
+fn listing(files: &[FilePatch], all: &[Unit], preview: usize) -> String {
+    all.iter().enumerate().map(|(i, unit)| {
+        let file = &files[unit.file];
+        let body = match unit.hunk {
+            Some(hunk) => file.hunks[hunk].lines().skip(1).collect::<Vec<_>>(),
+            None => file.header.lines().skip(1).collect(),
+        };
+        let mut text = format!("[{}] {}", i + 1, describe(files, *unit));
+        for line in body.iter().take(preview) {
+            text.push('\n');
+            text.push_str(line);
+        }
+        if body.len() > preview {
+            text.push_str(&format!("\n... ({} lines omitted)", body.len() - preview));
+        }
+        text
+    }).collect::<Vec<_>>().join("\n\n")
+}
+
+/// 解析模型返回的分组，忽略无效和重复的编号，未分配的改动块放入最后一组
+fn parse_groups(response: &str, count: usize) -> Vec<Group> {
+    let number = Regex::new(r"\d+").unwrap();
+    let mut groups: Vec<Group> = Vec::new();
+    let mut assigned = vec![false; count];
+    for line in response.lines().map(str::trim) {
+        if let Some(title) = line.strip_prefix("GROUP:") {
+            groups.push(Group { title: title.trim().to_string(), units: Vec::new() });
+        } else if let (Some(ids), Some(group)) = (line.strip_prefix("HUNKS:"), groups.last_mut()) {
+            for id in number.find_iter(ids).filter_map(|m| m.as_str().parse::<usize>().ok()) {
+                if (1..=count).contains(&id) && !assigned[id - 1] {
+                    assigned[id - 1] = true;
+                    group.units.push(id - 1);
+                }
+            }
+        }
+    }
+    groups.retain(|group| !group.units.is_empty());
+
+    let rest: Vec<usize> = (0..count).filter(|i| !assigned[*i]).collect();
+    if !rest.is_empty() {
+        groups.push(Group { title: "其他改动".to_string(), units: rest });
+    }
+    groups
+}
+
+async fn propose_groups(
+    context: &MessageContext<'_>,
+    files: &[FilePatch],
+    all: &[Unit],
+    message: Option<&str>,
+) -> Result<Vec<Group>> {
+    let system_prompt = match context.mode {
+        LanguageMode::ChineseOnly => "下面是 git 仓库中已暂存的改动，已切分为带编号的改动块。\
+请把改动块分成若干组，每组是一个内容连贯、可以独立提交的改动。每个改动块必须且只能属于一组，\
+相互依赖的改动块放在同一组，按提交的先后顺序排列各组。只按以下格式回复，每组一段：\n\
+GROUP: <该组的提交标题，使用 type(scope): 描述 格式>\nHUNKS: <逗号分隔的改动块编号>",
+        _ => "Below are the staged changes of a git repository, split into numbered hunks. \
+Group the hunks into coherent changesets that can each be committed on its own. Every hunk must belong to exactly one group, \
+hunks that depend on each other must be in the same group, and the groups must be ordered so each commit builds on the previous ones. \
+Respond ONLY in the following format, one block per group:\n\
+GROUP: <commit title for the group, in type(scope): message format>\nHUNKS: <comma separated hunk numbers>",
+    };
+
+    let service = context.config.get_default_service()?;
+    let budget = diff_budget::context_window(service).saturating_sub(context.config.max_tokens as usize) * 4 / 5;
+    let mut content = String::new();
+    for preview in PREVIEW_LINES {
+        content = listing(files, all, preview);
+        if diff_budget::estimate_tokens(&content) <= budget {
+            break;
+        }
+    }
+    if let Some(message) = message {
+        content = format!("User description:\n{}\n\n{}", message, content);
+    }
+
+    let response = ai_service::chat_with_live_output(context.translator, system_prompt, &content).await?;
+    debug!("分组结果：\n{}", response);
+    Ok(parse_groups(&response, all.len()))
+}
+
+fn print_groups(files: &[FilePatch], all: &[Unit], groups: &[Group]) {
+    for (i, group) in groups.iter().enumerate() {
+        println!("\n{}", Style::title(&format!("分组 {}: {}", i + 1, group.title)));
+        for unit in &group.units {
+            print!("{}", Style::plain(&format!("  [{}] {}", unit + 1, describe(files, all[*unit]))));
+        }
+    }
+}
+
+fn select(prompt: &str, items: &[String]) -> Result<usize> {
+    Ok(Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt(prompt)
+        .items(items)
+        .default(0)
+        .interact()?)
+}
+
+/// 展示分组并让用户调整，确认后返回最终分组，取消时返回 None
+fn adjust_groups(files: &[FilePatch], all: &[Unit], mut groups: Vec<Group>) -> Result<Option<Vec<Group>>> {
+    loop {
+        print_groups(files, all, &groups);
+        let titles: Vec<String> = groups.iter().enumerate()
+            .map(|(i, group)| format!("分组 {}: {}", i + 1, group.title))
+            .collect();
+
+        match Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+            .with_prompt("是否按此分组提交？")
+            .items(&MENU_ITEMS)
+            .default(0)
+            .interact()?
+        {
+            0 => return Ok(Some(groups)),
+            1 => {
+                let mut units: Vec<(usize, usize)> = groups.iter().enumerate()
+                    .flat_map(|(g, group)| group.units.iter().map(move |unit| (*unit, g)))
+                    .collect();
+                units.sort();
+                let labels: Vec<String> = units.iter()
+                    .map(|(unit, g)| format!("[{}] {}（分组 {}）", unit + 1, describe(files, all[*unit]), g + 1))
+                    .collect();
+                let (unit, from) = units[select("请选择要移动的改动块", &labels)?];
+
+                let mut targets = titles.clone();
+                targets.push("新建分组".to_string());
+                let to = select("移动到", &targets)?;
+                if to == from {
+                    continue;
+                }
+                if to == groups.len() {
+                    let title: String = Input::new().with_prompt("新分组的标题").interact_text()?;
+                    groups.push(Group { title, units: Vec::new() });
+                }
+                groups[from].units.retain(|u| *u != unit);
+                groups[to].units.push(unit);
+                groups[to].units.sort();
+            }
+            2 => {
+                if groups.len() < 2 {
+                    println!("{}", Style::yellow("只有一个分组，无需合并"));
+                    continue;
+                }
+                let from = select("请选择要合并的分组", &titles)?;
+                let others: Vec<usize> = (0..groups.len()).filter(|g| *g != from).collect();
+                let labels: Vec<String> = others.iter().map(|g| titles[*g].clone()).collect();
+                let to = others[select("合并到", &labels)?];
+                let units = std::mem::take(&mut groups[from].units);
+                groups[to].units.extend(units);
+                groups[to].units.sort();
+            }
+            3 => {
+                let g = select("请选择要修改标题的分组", &titles)?;
+                groups[g].title = Input::new()
+                    .with_prompt("分组标题")
+                    .with_initial_text(groups[g].title.clone())
+                    .interact_text()?;
+            }
+            _ => return Ok(None),
+        }
+        groups.retain(|group| !group.units.is_empty());
+    }
+}
+
+/// 执行 git 命令，`index` 指定使用的索引文件，`input` 作为标准输入
+fn git(args: &[&str], index: Option<&PathBuf>, input: Option<&str>) -> Result<String> {
+    let mut command = Command::new("git");
+    command.args(args)
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(index) = index {
+        command.env("GIT_INDEX_FILE", index);
+    }
+    let mut child = command.spawn().context("无法执行 git 命令")?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("git {} 执行失败: {}", args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// 执行 git 命令并返回去掉首尾空白的输出
+fn git_line(args: &[&str], index: Option<&PathBuf>) -> Result<String> {
+    git(args, index, None).map(|output| output.trim().to_string())
+}
+
+/// 在临时索引中依次应用各组补丁，确认结果与原暂存内容一致
+fn verify(patches: &[String], expected_tree: &str) -> Result<()> {
+    let index = PathBuf::from(git_line(&["rev-parse", "--git-path", "commit-helper-split.index"], None)?);
+    let result = (|| {
+        git(&["read-tree", "HEAD"], Some(&index), None)?;
+        for (i, patch) in patches.iter().enumerate() {
+            git(&["apply", "--cached", "-"], Some(&index), Some(patch))
+                .with_context(|| format!("分组 {} 的改动无法单独应用", i + 1))?;
+        }
+        let tree = git_line(&["write-tree"], Some(&index))?;
+        if tree != expected_tree {
+            return Err(anyhow::anyhow!("拆分后的结果与原暂存内容不一致"));
+        }
+        Ok(())
+    })();
+    let _ = std::fs::remove_file(&index);
+    result
+}
+
+/// 恢复拆分前的 HEAD 和暂存区，工作区不受影响
+fn restore(head: &str, tree: &str) {
+    let result = git(&["reset", "--soft", head], None, None)
+        .and_then(|_| git(&["read-tree", tree], None, None));
+    match result {
+        Ok(_) => println!("{}", Style::yellow("已恢复到拆分前的提交和暂存区")),
+        Err(e) => {
+            warn!("恢复暂存区失败: {}", e);
+            println!("{}", Style::red(&format!(
+                "恢复失败，可以手动执行 git reset --soft {} && git read-tree {} 恢复", head, tree)));
+        }
+    }
+}
+
+/// 将暂存的改动按分组依次提交
+pub async fn split_and_commit(context: &MessageContext<'_>, diff: &str, message: Option<String>) -> Result<()> {
+    let patch = git(&["diff", "--cached", "--binary", "--no-color", "--no-ext-diff", "--src-prefix=a/", "--dst-prefix=b/"], None, None)?;
+    let files = parse_patch(&patch);
+    let all = units(&files);
+    if all.len() < 2 {
+        println!("{}", Style::yellow("暂存的改动只有一个改动块，无需拆分"));
+        return commit::commit_once(context, diff, message).await;
+    }
+    let head = git_line(&["rev-parse", "--verify", "HEAD"], None)
+        .map_err(|_| anyhow::anyhow!("当前分支还没有任何提交，无法拆分提交"))?;
+    let tree = git_line(&["write-tree"], None)?;
+
+    println!("\n正在将 {} 个改动块分组...", all.len());
+    let groups = propose_groups(context, &files, &all, message.as_deref()).await?;
+    let Some(groups) = adjust_groups(&files, &all, groups)? else {
+        println!("已取消提交");
+        return Ok(());
+    };
+    if groups.len() == 1 {
+        return commit::commit_once(context, diff, message).await;
+    }
+
+    let patches: Vec<String> = groups.iter().map(|group| build_patch(&files, &all, &group.units)).collect();
+    verify(&patches, &tree)?;
+
+    for (i, (group, patch)) in groups.iter().zip(&patches).enumerate() {
+        println!("\n{}", Style::title(&format!("正在提交分组 {}/{}: {}", i + 1, groups.len(), group.title)));
+        let result = async {
+            git(&["read-tree", "HEAD"], None, None)?;
+            git(&["apply", "--cached", "-"], None, Some(patch))?;
+            let description = match &message {
+                Some(message) => format!("{}\n{}", group.title, message),
+                None => group.title.clone(),
+            };
+            match commit::compose_message(context, &commit::get_staged_diff()?, Some(description)).await? {
+                Some(content) => commit::run_git_commit(&content, &[]).map(|_| true),
+                None => Ok(false),
+            }
+        }.await;
+
+        match result {
+            Ok(true) => {}
+            Ok(false) => {
+                println!("已取消提交");
+                restore(&head, &tree);
+                return Ok(());
+            }
+            Err(e) => {
+                restore(&head, &tree);
+                return Err(e);
+            }
+        }
+    }
+
+    println!("提交成功！共创建 {} 个提交", groups.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATCH: &str = "\
+diff --git a/src/main.rs b/src/main.rs
+index 1111111..2222222 100644
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,3 +1,3 @@
+ fn main() {
+-    old();
++    new();
+ }
+@@ -20,2 +20,3 @@ fn helper()
+ let a = 1;
++let b = 2;
+diff --git a/docs/new.md b/docs/new.md
+new file mode 100644
+index 0000000..3333333
+--- /dev/null
++++ b/docs/new.md
+@@ -0,0 +1 @@
++hello
+";
+
+    #[test]
+    fn test_parse_patch() {
+        let files = parse_patch(PATCH);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "src/main.rs");
+        assert_eq!(files[0].hunks.len(), 2);
+        assert!(files[0].header.ends_with("+++ b/src/main.rs\n"));
+        assert!(files[0].hunks[1].starts_with("@@ -20,2 +20,3 @@ fn helper()\n"));
+        assert!(files[1].whole);
+
+        let all = units(&files);
+        assert_eq!(all, vec![
+            Unit { file: 0, hunk: Some(0) },
+            Unit { file: 0, hunk: Some(1) },
+            Unit { file: 1, hunk: None },
+        ]);
+        assert_eq!(describe(&files, all[2]), "docs/new.md (整个文件)");
+    }
+
+    #[test]
+    fn test_build_patch() {
+        let files = parse_patch(PATCH);
+        let all = units(&files);
+        assert_eq!(build_patch(&files, &all, &[0, 1, 2]), PATCH);
+
+        let patch = build_patch(&files, &all, &[2, 1]);
+        assert!(!patch.contains("new();"));
+        assert!(patch.starts_with("diff --git a/src/main.rs b/src/main.rs\nindex 1111111..2222222 100644\n--- a/src/main.rs\n+++ b/src/main.rs\n@@ -20,2"));
+        assert!(patch.ends_with("+hello\n"));
+    }
+
+    #[test]
+    fn test_parse_groups() {
+        let response = "GROUP: feat(cli): add new entry\nHUNKS: 1, 3, 9\n\nGROUP: docs: add guide\nHUNKS: 3\nGROUP: chore: nothing\nHUNKS:";
+        assert_eq!(parse_groups(response, 4), vec![
+            Group { title: "feat(cli): add new entry".to_string(), units: vec![0, 2] },
+            Group { title: "其他改动".to_string(), units: vec![1, 3] },
+        ]);
+        assert_eq!(parse_groups("unexpected", 2), vec![Group { title: "其他改动".to_string(), units: vec![0, 1] }]);
+    }
+
+    #[test]
+    fn test_listing_truncates_hunks() {
+        let files = parse_patch(PATCH);
+        let all = units(&files);
+        let text = listing(&files, &all, 1);
+        assert!(text.starts_with("[1] src/main.rs @@ -1,3 +1,3 @@\n fn main() {\n... (3 lines omitted)\n\n[2]"));
+        assert!(listing(&files, &all, 0).contains("[3] docs/new.md (整个文件)\n... (4 lines omitted)"));
+    }
+}
@@ -0,0 +1,233 @@
+// 仓库提交风格示例
+// 从 git log 中挑选最近的、格式规范的提交信息作为示例交给模型，让生成结果沿用仓库已有的
+// scope 写法、尾部字段顺序和工单前缀等习惯。挑选结果按仓库缓存，使用时按模型上下文截取。
+
+use crate::config::AIServiceConfig;
+use crate::diff_budget;
+use crate::git;
+use anyhow::{Context, Result};
+use directories::ProjectDirs;
+use log::{debug, warn};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 从 git log 中读取的提交数量
+const SAMPLE_COMMITS: usize = 200;
+/// 缓存中保留的候选示例数量
+const CACHED_EXAMPLES: usize = 20;
+/// 最多交给模型的示例数量
+const MAX_EXAMPLES: usize = 5;
+/// 示例最多占用的 token 数，模型上下文较小时按上下文的十分之一计算
+const MAX_EXAMPLE_TOKENS: usize = 1500;
+/// 缓存有效期
+const CACHE_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+/// 超过该长度的提交信息不适合作为示例
+const MAX_MESSAGE_CHARS: usize = 2000;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StyleCache {
+    repo: PathBuf,
+    created_at: u64,
+    examples: Vec<String>,
+}
+
+/// 获取当前仓库的提交风格示例，总长度不超过模型上下文允许的预算
+///
+/// 不在 git 仓库中或读取提交历史失败时返回空列表，不影响提交信息的生成。
+pub fn examples(service: &AIServiceConfig, max_tokens: u64) -> Vec<String> {
+    let Some(root) = git::repo_root() else {
+        return Vec::new();
+    };
+    let candidates = match load_cached(&root) {
+        Some(candidates) => candidates,
+        None => match sample(&root) {
+            Ok(candidates) => {
+                save_cache(&root, &candidates);
+                candidates
+            }
+            Err(e) => {
+                warn!("读取仓库提交历史失败，不使用提交风格示例: {}", e);
+                return Vec::new();
+            }
+        },
+    };
+
+    let budget = (diff_budget::context_window(service).saturating_sub(max_tokens as usize) / 10)
+        .min(MAX_EXAMPLE_TOKENS);
+    let selected = within_budget(candidates, budget);
+    debug!("使用 {} 条仓库提交信息作为风格示例，预算 {} tokens", selected.len(), budget);
+    selected
+}
+
+/// 按顺序选取示例，直到数量或 token 数达到上限
+fn within_budget(candidates: Vec<String>, budget: usize) -> Vec<String> {
+    let mut used = 0;
+    candidates.into_iter()
+        .filter(|example| {
+            let tokens = diff_budget::estimate_tokens(example);
+            if used + tokens > budget {
+                return false;
+            }
+            used += tokens;
+            true
+        })
+        .take(MAX_EXAMPLES)
+        .collect()
+}
+
+/// 读取最近的非合并提交，按质量排序后返回最好的一批
+fn sample(root: &Path) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .current_dir(root)
+        .args(["log", "--no-merges", "-n", &SAMPLE_COMMITS.to_string(), "--format=%B%x1e"])
+        .output()
+        .context("无法执行 git log")?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("git log 执行失败: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    let log = String::from_utf8_lossy(&output.stdout);
+    Ok(rank(log.split('\x1e').map(str::trim)))
+}
+
+/// 过滤掉不适合作为示例的提交，按得分从高到低排序，得分相同时保持原有的时间顺序
+fn rank<'a>(messages: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut scored: Vec<(u32, &str)> = messages
+        .filter_map(|message| score(message).map(|score| (score, message)))
+        .collect();
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored.into_iter()
+        .take(CACHED_EXAMPLES)
+        .map(|(_, message)| message.to_string())
+        .collect()
+}
+
+/// 评估提交信息作为示例的价值，不适合作为示例时返回 None
+fn score(message: &str) -> Option<u32> {
+    let title = message.lines().next()?.trim();
+    let title_len = title.chars().count();
+    if !(8..=100).contains(&title_len) || message.chars().count() > MAX_MESSAGE_CHARS {
+        return None;
+    }
+    if git::is_auto_generated_commit(title)
+        || ["fixup!", "squash!", "amend!", "WIP", "wip"].iter().any(|prefix| title.starts_with(prefix))
+    {
+        return None;
+    }
+
+    let conventional = Regex::new(r"^[a-z]+(\([^)]+\))?!?: \S").unwrap();
+    let trailer = Regex::new(r"^[A-Za-z][A-Za-z-]*: \S").unwrap();
+    let rest: Vec<&str> = message.lines().skip(1).filter(|line| !line.trim().is_empty()).collect();
+    let trailers = rest.iter().filter(|line| trailer.is_match(line)).count();
+
+    let mut score = 0;
+    if conventional.is_match(title) {
+        score += 3;
+        if title.contains('(') {
+            score += 1;
+        }
+    }
+    if rest.len() > trailers {
+        score += 2;
+    }
+    if trailers > 0 {
+        score += 1;
+    }
+    if title_len <= 72 {
+        score += 1;
+    }
+    Some(score)
+}
+
+/// 缓存文件以仓库根目录路径的 SHA-256 命名，哈希结果不随 Rust 版本变化，升级工具链后仍能命中原来的缓存
+fn cache_path(root: &Path) -> Option<PathBuf> {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let digest = Sha256::digest(root.to_string_lossy().as_bytes());
+    let name: String = digest[..8].iter().map(|byte| format!("{:02x}", byte)).collect();
+    ProjectDirs::from("com", "githelper", "git-commit-helper")
+        .map(|dirs| dirs.cache_dir().join("commit_style").join(format!("{}.json", name)))
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn load_cached(root: &Path) -> Option<Vec<String>> {
+    let path = cache_path(root)?;
+    let content = std::fs::read_to_string(&path).ok()?;
+    let cache: StyleCache = serde_json::from_str(&content).ok()?;
+    if cache.repo != root || now().saturating_sub(cache.created_at) > CACHE_TTL_SECS {
+        return None;
+    }
+    debug!("使用缓存的提交风格示例: {}", path.display());
+    Some(cache.examples)
+}
+
+fn save_cache(root: &Path, examples: &[String]) {
+    let Some(path) = cache_path(root) else {
+        return;
+    };
+    let cache = StyleCache { repo: root.to_path_buf(), created_at: now(), examples: examples.to_vec() };
+    let result = path.parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&path, serde_json::to_string_pretty(&cache)?));
+    if let Err(e) = result {
+        warn!("无法写入提交风格缓存 {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_filters_unsuitable_commits() {
+        assert_eq!(score("Merge branch 'master' into dev"), None);
+        assert_eq!(score("Revert \"feat: add dock plugin\""), None);
+        assert_eq!(score("fixup! feat(dock): add plugin"), None);
+        assert_eq!(score("wip"), None);
+        assert_eq!(score(&format!("feat: {}", "x".repeat(100))), None);
+
+        let plain = score("Update README for release").unwrap();
+        let conventional = score("docs: update README for release").unwrap();
+        let full = score("feat(dock): add plugin loader\n\nLoad plugins lazily.\n\nLog: 新增插件加载\nPMS: BUG-1").unwrap();
+        assert!(plain < conventional && conventional < full);
+    }
+
+    #[test]
+    fn test_rank_prefers_better_and_recent_commits() {
+        let messages = [
+            "fix: crash on exit",
+            "Merge pull request #1 from fork",
+            "feat(dock): add plugin loader\n\nLoad plugins lazily.",
+            "fix: wrong icon size",
+        ];
+        assert_eq!(rank(messages.into_iter()), vec![
+            "feat(dock): add plugin loader\n\nLoad plugins lazily.",
+            "fix: crash on exit",
+            "fix: wrong icon size",
+        ]);
+    }
+
+    #[test]
+    fn test_within_budget() {
+        let candidates: Vec<String> = (0..10).map(|i| format!("fix: issue number {:02}", i)).collect();
+        let tokens = diff_budget::estimate_tokens(&candidates[0]);
+        assert_eq!(within_budget(candidates.clone(), tokens * 2), candidates[..2].to_vec());
+        assert_eq!(within_budget(candidates.clone(), usize::MAX).len(), MAX_EXAMPLES);
+        assert!(within_budget(candidates, 0).is_empty());
+    }
+
+    #[test]
+    fn test_cache_path_is_stable() {
+        // 文件名只取决于路径本身，不能随 Rust 版本变化
+        let Some(path) = cache_path(Path::new("/nonexistent/repo")) else {
+            return;
+        };
+        assert_eq!(path.file_name().and_then(|name| name.to_str()), Some("5b6e8e2d129e523b.json"));
+    }
+}
@@ -0,0 +1,764 @@
+// 提交信息提示词模板
+// 提示词由标题、正文、Log、Influence、尾部字段几个段落组合而成，每个段落可以单独开关；
+// 每条规则和示例的中英文写法定义在一起，按语言模式渲染，双语模式先输出英文再输出中文。
+// 配置目录下的 commit_prompt.txt（或 commit_prompt 配置项指定的文件）可以覆盖内置模板。
+
+use crate::config::Config;
+use crate::conventional;
+use log::{debug, info, warn};
+use regex::Regex;
+use std::path::PathBuf;
+
+/// 提交信息的语言模式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LanguageMode {
+    ChineseOnly,
+    EnglishOnly,
+    Bilingual,
+}
+
+impl LanguageMode {
+    pub const ALL: [LanguageMode; 3] = [Self::ChineseOnly, Self::EnglishOnly, Self::Bilingual];
+
+    /// 根据语言选项确定模式，两个选项都未设置时使用中英双语
+    pub fn determine(only_chinese: bool, only_english: bool) -> Self {
+        if only_english {
+            Self::EnglishOnly
+        } else if only_chinese {
+            Self::ChineseOnly
+        } else {
+            Self::Bilingual
+        }
+    }
+
+    /// 展示给用户的名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::ChineseOnly => "仅中文",
+            Self::EnglishOnly => "仅英文",
+            Self::Bilingual => "中英双语",
+        }
+    }
+
+    /// 自定义模板中用于条件块的名称
+    fn name(&self) -> &'static str {
+        match self {
+            Self::ChineseOnly => "chinese",
+            Self::EnglishOnly => "english",
+            Self::Bilingual => "bilingual",
+        }
+    }
+
+    /// 规则说明使用的语言，双语模式用英文描述格式要求
+    fn rule_language(&self) -> Language {
+        match self {
+            Self::ChineseOnly => Language::Chinese,
+            _ => Language::English,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Language {
+    English,
+    Chinese,
+}
+
+/// 同一段文字的英文和中文写法
+struct Text {
+    en: &'static str,
+    zh: &'static str,
+}
+
+impl Text {
+    fn get(&self, language: Language) -> &'static str {
+        match language {
+            Language::English => self.en,
+            Language::Chinese => self.zh,
+        }
+    }
+}
+
+/// 提交信息中可以单独开关的段落
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Section {
+    Title,
+    Body,
+    Log,
+    Influence,
+    Trailers,
+}
+
+impl Section {
+    pub const ALL: [Section; 5] = [Self::Title, Self::Body, Self::Log, Self::Influence, Self::Trailers];
+
+    /// 自定义模板中用于条件块的名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Title => "title",
+            Self::Body => "body",
+            Self::Log => "log",
+            Self::Influence => "influence",
+            Self::Trailers => "trailers",
+        }
+    }
+}
+
+/// 各段落的开关，默认生成标题、正文、Log 和 Influence，不生成尾部字段
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sections {
+    pub title: bool,
+    pub body: bool,
+    pub log: bool,
+    pub influence: bool,
+    pub trailers: bool,
+}
+
+impl Default for Sections {
+    fn default() -> Self {
+        Self {
+            title: true,
+            body: true,
+            log: true,
+            influence: true,
+            trailers: false,
+        }
+    }
+}
+
+impl Sections {
+    pub fn contains(&self, section: Section) -> bool {
+        match section {
+            Section::Title => self.title,
+            Section::Body => self.body,
+            Section::Log => self.log,
+            Section::Influence => self.influence,
+            Section::Trailers => self.trailers,
+        }
+    }
+}
+
+/// 段落的格式规则和示例
+struct SectionTemplate {
+    section: Section,
+    /// 段落的格式说明
+    rule: Text,
+    /// 双语模式中英文部分的格式说明
+    bilingual_rule: &'static str,
+    /// 段落之后空一行的说明
+    separator: Text,
+    bilingual_separator: &'static str,
+    /// 双语模式中中文部分的说明及其后空一行的说明，不需要翻译的段落为 None
+    translation: Option<(&'static str, &'static str)>,
+    /// 排在所有格式说明之后的补充要求
+    details: &'static [Text],
+    example: Text,
+}
+
+const SECTION_TEMPLATES: [SectionTemplate; 5] = [
+    SectionTemplate {
+        section: Section::Title,
+        rule: Text {
+            en: "First line: type: message or type(scope): message (under 50 characters)",
+            zh: "第一行为标题：type: message 或 type(scope): message（不超过50个字符）",
+        },
+        bilingual_rule: "First line in English: type: message or type(scope): message (under 50 characters)",
+        separator: Text {
+            en: "Empty line after the title",
+            zh: "标题下方空一行",
+        },
+        bilingual_separator: "Empty line after the title",
+        translation: Some(("Chinese title (translate the English title)", "Empty line after Chinese title")),
+        details: &[Text {
+            en: "Type must be one of: {types}",
+            zh: "type 必须是以下之一：{types}",
+        }],
+        example: Text {
+            en: "feat: add user authentication module",
+            zh: "feat: 添加用户认证模块",
+        },
+    },
+    SectionTemplate {
+        section: Section::Body,
+        rule: Text {
+            en: "Detailed explanation in English (what was changed and why)",
+            zh: "详细的中文说明（解释做了什么改动以及为什么需要这些改动）",
+        },
+        bilingual_rule: "Detailed explanation in English (what was changed and why)",
+        separator: Text {
+            en: "Empty line after explanation",
+            zh: "说明下方空一行",
+        },
+        bilingual_separator: "Empty line after English explanation",
+        translation: Some(("Chinese explanation (translate the English explanation)", "Empty line after Chinese explanation")),
+        details: &[
+            Text {
+                en: "Focus on both WHAT changed and WHY it was necessary",
+                zh: "关注点：变更内容（做了什么）和变更原因（为什么）",
+            },
+            Text {
+                en: "Include any important technical details or context",
+                zh: "包含重要的技术细节或上下文",
+            },
+        ],
+        example: Text {
+            en: "1. Implement JWT-based authentication system
+2. Add user login and registration endpoints
+3. Include password hashing with bcrypt
+4. Set up token refresh mechanism",
+            zh: "1. 实现基于 JWT 的认证系统
+2. 添加用户登录和注册端点
+3. 包含使用 bcrypt 的密码哈希处理
+4. 设置令牌刷新机制",
+        },
+    },
+    SectionTemplate {
+        section: Section::Log,
+        rule: Text {
+            en: "Log field (ONLY if this change involves user-facing features/UI changes that product managers would communicate to users)",
+            zh: "Log 字段（仅当此次变更涉及用户可感知的功能/UI层面变化，产品经理会向用户说明的内容时才添加）",
+        },
+        bilingual_rule: "Log field in English (ONLY if this change involves user-facing features/UI changes)",
+        separator: Text {
+            en: "Empty line after Log field (if present)",
+            zh: "Log 字段下方空一行（如果存在 Log 字段）",
+        },
+        bilingual_separator: "Empty line after English Log field (if present)",
+        translation: Some(("Chinese Log field (translate the English Log field, only if present)", "Empty line after Chinese Log field (if present)")),
+        details: &[],
+        example: Text {
+            en: "Log: Added user authentication feature with login and registration",
+            zh: "Log: 新增用户登录注册功能",
+        },
+    },
+    SectionTemplate {
+        section: Section::Influence,
+        rule: Text {
+            en: "Influence section with black-box testing recommendations",
+            zh: "Influence 部分，提供黑盒测试的重点和范围",
+        },
+        bilingual_rule: "Influence section in English with black-box testing recommendations",
+        separator: Text {
+            en: "Empty line after Influence section",
+            zh: "Influence 部分下方空一行",
+        },
+        bilingual_separator: "Empty line after English Influence section",
+        translation: Some(("Chinese Influence section (translate the English testing suggestions)", "Empty line after Chinese Influence section")),
+        details: &[],
+        example: Text {
+            en: "Influence:
+1. Test user registration with valid and invalid inputs
+2. Verify login functionality with correct and incorrect credentials
+3. Test JWT token generation and validation
+4. Verify password security and hashing
+5. Test token refresh mechanism and expiration handling
+6. Verify access control for protected endpoints",
+            zh: "Influence:
+1. 测试用户注册功能，包括有效和无效输入
+2. 验证登录功能，测试正确和错误的凭据
+3. 测试 JWT 令牌生成和验证流程
+4. 验证密码安全性和哈希处理
+5. 测试令牌刷新机制和过期处理
+6. 验证受保护端点的访问控制",
+        },
+    },
+    SectionTemplate {
+        section: Section::Trailers,
+        rule: Text {
+            en: "Trailer lines such as Fixes: #123 at the very end, ONLY for references given in the user description, never invent Change-Id or Signed-off-by",
+            zh: "仅当用户描述中给出了 issue 等引用时，在末尾添加 Fixes: #123 等尾部字段，不要编造 Change-Id 或 Signed-off-by",
+        },
+        bilingual_rule: "Trailer lines such as Fixes: #123 at the very end, ONLY for references given in the user description, never invent Change-Id or Signed-off-by",
+        separator: Text {
+            en: "Empty line after trailer lines",
+            zh: "尾部字段下方空一行",
+        },
+        bilingual_separator: "Empty line after trailer lines",
+        // 尾部字段不翻译，双语模式中放在中文部分之后
+        translation: None,
+        details: &[],
+        example: Text {
+            en: "Fixes: #123",
+            zh: "Fixes: #123",
+        },
+    },
+];
+
+/// 双语模式同时生成标题和正文时，中文标题和说明合为一步
+const TITLE_AND_BODY_TRANSLATION: (&str, &str) = ("Chinese title and explanation (translate the English content)", "Empty line after Chinese explanation");
+
+const HEADER: Text = Text {
+    en: "Please analyze the git diff content and generate a commit message in English only:",
+    zh: "请分析以下 git diff 内容，并按照以下格式生成提交信息：",
+};
+
+const BILINGUAL_HEADER: &str = "Please analyze the git diff content and generate a detailed bilingual commit message with:";
+
+const NO_CHINESE_RULE: &str = "DO NOT include any Chinese content";
+
+const NO_MARKDOWN_RULE: Text = Text {
+    en: "DO NOT wrap the response in any markdown or code block markers",
+    zh: "不要使用任何 markdown 或代码块标记",
+};
+
+const TITLE_PUNCTUATION_RULE: &str = "标题结尾不要使用标点符号";
+
+const EXAMPLE_HEADER: Text = Text {
+    en: "Example response format:",
+    zh: "示例格式：",
+};
+
+const STYLE_EXAMPLES_HEADER: Text = Text {
+    en: "Recent commit messages in this repository. Follow their conventions such as scopes, trailer order and ticket prefixes, while keeping the format and language required above:",
+    zh: "以下是本仓库最近的提交信息，请沿用其中的习惯（如 scope 写法、尾部字段顺序、工单前缀），同时保持上述格式和语言要求：",
+};
+
+const SCOPE_HINT: Text = Text {
+    en: "All changed files belong to the \"{scope}\" module, use it as the scope in the title, e.g. feat({scope}): message",
+    zh: "本次改动的文件都属于 {scope} 模块，标题请使用它作为 scope，例如 feat({scope}): 描述",
+};
+
+const BREAKING_HINT: Text = Text {
+    en: "The change removes these public APIs: {names}. If it breaks compatibility for callers, add ! after the type/scope in the title and a \"BREAKING CHANGE: \" trailer at the end describing the impact and how to migrate",
+    zh: "本次改动删除了以下公开接口：{names}。如果会破坏调用方的兼容性，请在标题的 type/scope 后加 !，并在末尾添加 \"BREAKING CHANGE: \" 尾部字段说明影响和迁移方式",
+};
+
+const FOOTER: &str = "Please respond with ONLY the commit message following this format,
+DO NOT end commit titles with any punctuation.";
+
+/// 根据暂存的改动推断出的信息，附在提示词中引导模型
+#[derive(Debug, Clone, Default)]
+pub struct PromptHints {
+    /// 根据改动文件推断出的 scope
+    pub scope: Option<String>,
+    /// diff 中被删除的公开接口
+    pub breaking: Vec<String>,
+    /// 仓库提交风格示例
+    pub examples: Vec<String>,
+}
+
+/// 按语言模式和段落开关渲染内置模板，`types` 为允许的提交类型
+///
+/// 规则依次为各段落的格式说明（段落之间插入空一行的说明）、各段落的补充要求和通用要求；
+/// 双语模式先列出英文部分，再按顺序列出对应的中文部分，不翻译的段落放在最后。
+pub fn render(mode: LanguageMode, sections: Sections, types: &[String]) -> String {
+    let language = mode.rule_language();
+    let enabled: Vec<&SectionTemplate> = SECTION_TEMPLATES.iter()
+        .filter(|template| sections.contains(template.section))
+        .collect();
+
+    // (格式说明, 空一行的说明)
+    let mut parts: Vec<(&str, &str)> = Vec::new();
+    let mut examples: Vec<&str> = Vec::new();
+    if mode == LanguageMode::Bilingual {
+        let (translated, untranslated): (Vec<&SectionTemplate>, Vec<&SectionTemplate>) = enabled.iter()
+            .partition(|template| template.translation.is_some());
+        let mut steps: Vec<(&str, &str)> = translated.iter().filter_map(|t| t.translation).collect();
+        if sections.title && sections.body {
+            steps.splice(0..2, [TITLE_AND_BODY_TRANSLATION]);
+        }
+        parts.extend(translated.iter().map(|t| (t.bilingual_rule, t.bilingual_separator)));
+        parts.extend(steps);
+        parts.extend(untranslated.iter().map(|t| (t.bilingual_rule, t.bilingual_separator)));
+        examples.extend(translated.iter().map(|t| t.example.en));
+        examples.extend(translated.iter().map(|t| t.example.zh));
+        examples.extend(untranslated.iter().map(|t| t.example.en));
+    } else {
+        parts.extend(enabled.iter().map(|t| (t.rule.get(language), t.separator.get(language))));
+        examples.extend(enabled.iter().map(|t| t.example.get(language)));
+    }
+
+    let types = types.join("/");
+    let mut rules: Vec<String> = Vec::new();
+    for (i, (rule, separator)) in parts.iter().enumerate() {
+        rules.push(rule.replace("{types}", &types));
+        if i + 1 < parts.len() {
+            rules.push(separator.to_string());
+        }
+    }
+    rules.extend(enabled.iter()
+        .flat_map(|template| template.details.iter().map(|detail| detail.get(language).replace("{types}", &types))));
+    if mode == LanguageMode::EnglishOnly {
+        rules.push(NO_CHINESE_RULE.to_string());
+    }
+    rules.push(NO_MARKDOWN_RULE.get(language).to_string());
+    if mode == LanguageMode::ChineseOnly && sections.title {
+        rules.push(TITLE_PUNCTUATION_RULE.to_string());
+    }
+
+    let header = match mode {
+        LanguageMode::Bilingual => BILINGUAL_HEADER,
+        _ => HEADER.get(language),
+    };
+    let mut prompt = String::from(header);
+    for (i, rule) in rules.iter().enumerate() {
+        prompt.push_str(&format!("\n{}. {}", i + 1, rule));
+    }
+
+    prompt.push_str(&format!("\n\n{}\n{}", EXAMPLE_HEADER.get(language), examples.join("\n\n")));
+
+    if language == Language::English {
+        prompt.push_str("\n\n");
+        prompt.push_str(FOOTER);
+    }
+    prompt
+}
+
+/// 展开自定义模板
+///
+/// `{{#名称}}...{{/名称}}` 仅在对应段落开启或处于对应语言模式时保留，
+/// 段落名称为 title/body/log/influence/trailers，语言模式为 chinese/english/bilingual；
+/// `{{default}}` 替换为内置模板的渲染结果，便于在其基础上追加规则；`{{types}}` 替换为允许的提交类型。
+pub fn expand(template: &str, mode: LanguageMode, sections: Sections, types: &[String]) -> String {
+    let conditions = Section::ALL.iter()
+        .map(|section| (section.name(), sections.contains(*section)))
+        .chain(LanguageMode::ALL.iter().map(|m| (m.name(), *m == mode)));
+
+    let mut result = template.to_string();
+    for (name, enabled) in conditions {
+        // 标记独占一行时连同换行一起去掉，避免留下多余的空行
+        let block = Regex::new(&format!(r"(?s)\{{\{{#{0}\}}\}}\n?(.*?)\{{\{{/{0}\}}\}}\n?", name)).unwrap();
+        result = block.replace_all(&result, if enabled { "$1" } else { "" }).into_owned();
+    }
+
+    if result.contains("{{default}}") {
+        result = result.replace("{{default}}", &render(mode, sections, types));
+    }
+    result = result.replace("{{types}}", &types.join("/"));
+
+    let unknown = Regex::new(r"\{\{[#/]?\w+\}\}").unwrap();
+    if let Some(tag) = unknown.find(&result) {
+        warn!("提交信息模板中存在无法识别的标记: {}", tag.as_str());
+    }
+    result.trim_end().to_string()
+}
+
+/// 自定义模板文件：优先使用配置中指定的文件，否则使用配置目录下的 commit_prompt.txt
+fn template_path(config: &Config) -> Option<PathBuf> {
+    match &config.commit_prompt {
+        Some(path) => Some(Config::prompt_path(path)),
+        None => Config::config_path().ok()
+            .and_then(|path| path.parent().map(|dir| dir.join("commit_prompt.txt"))),
+    }
+}
+
+/// 读取自定义模板，文件不存在或读取失败时返回 None
+fn load_template(config: &Config) -> Option<String> {
+    let path = template_path(config)?;
+    if !path.exists() {
+        if config.commit_prompt.is_some() {
+            warn!("提交信息模板文件不存在: {}，使用内置模板", path.display());
+        }
+        return None;
+    }
+
+    info!("正在使用 {} 模板文件生成提交信息", path.display());
+    match std::fs::read_to_string(&path) {
+        Ok(content) => Some(content),
+        Err(err) => {
+            log::error!("无法读取提交信息模板文件 {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+/// 构建生成提交信息的完整提示词
+///
+/// 依次为模板、scope 和破坏性变更提示、仓库提交风格示例和用户描述，最后引出变更内容。
+pub fn build_prompt(
+    config: &Config,
+    mode: LanguageMode,
+    sections: Sections,
+    hints: &PromptHints,
+    user_message: Option<&str>,
+) -> String {
+    let types = conventional::allowed_types(config);
+    let mut prompt = match load_template(config) {
+        Some(template) => expand(&template, mode, sections, &types),
+        None => render(mode, sections, &types),
+    };
+    debug!("提交信息模板: {:?}, 段落: {:?}", mode, sections);
+
+    let language = mode.rule_language();
+    if let Some(scope) = hints.scope.as_deref().filter(|_| sections.title) {
+        prompt.push_str("\n\n");
+        prompt.push_str(&SCOPE_HINT.get(language).replace("{scope}", scope));
+    }
+    if !hints.breaking.is_empty() {
+        prompt.push_str("\n\n");
+        prompt.push_str(&BREAKING_HINT.get(language).replace("{names}", &hints.breaking.join(", ")));
+    }
+
+    if !hints.examples.is_empty() {
+        prompt.push_str("\n\n");
+        prompt.push_str(STYLE_EXAMPLES_HEADER.get(language));
+        for example in &hints.examples {
+            prompt.push_str("\n---\n");
+            prompt.push_str(example);
+        }
+        prompt.push_str("\n---");
+    }
+
+    let chinese = mode == LanguageMode::ChineseOnly;
+    match (user_message, chinese) {
+        (Some(msg), true) => prompt.push_str(&format!("\n\n用户描述：\n{}\n\n变更内容：\n", msg)),
+        (Some(msg), false) => prompt.push_str(&format!("\n\nUser Description:\n{}\n\nChanges:\n", msg)),
+        (None, true) => prompt.push_str("\n\n变更内容：\n"),
+        (None, false) => prompt.push_str("\n\nHere are the changes:\n"),
+    }
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers(prompt: &str) -> Vec<usize> {
+        prompt.lines()
+            .take_while(|line| !line.is_empty())
+            .filter_map(|line| line.split_once(". ").and_then(|(n, _)| n.parse().ok()))
+            .collect()
+    }
+
+    fn types() -> Vec<String> {
+        conventional::DEFAULT_COMMIT_TYPES.iter().map(|t| t.to_string()).collect()
+    }
+
+    // 改用模板引擎之前内置的提示词，默认段落的渲染结果应与之一致
+    const BASELINE_ENGLISH: &str = r#"Please analyze the git diff content and generate a commit message in English only:
+1. First line: type: message (under 50 characters)
+2. Empty line after the title
+3. Detailed explanation in English (what was changed and why)
+4. Empty line after explanation
+5. Log field (ONLY if this change involves user-facing features/UI changes that product managers would communicate to users)
+6. Empty line after Log field (if present)
+7. Influence section with black-box testing recommendations
+8. Type must be one of: feat/fix/docs/style/refactor/test/chore
+9. Focus on both WHAT changed and WHY it was necessary
+10. Include any important technical details or context
+11. DO NOT include any Chinese content
+12. DO NOT wrap the response in any markdown or code block markers
+
+Example response format:
+feat: add user authentication module
+
+1. Implement JWT-based authentication system
+2. Add user login and registration endpoints
+3. Include password hashing with bcrypt
+4. Set up token refresh mechanism
+
+Log: Added user authentication feature with login and registration
+
+Influence:
+1. Test user registration with valid and invalid inputs
+2. Verify login functionality with correct and incorrect credentials
+3. Test JWT token generation and validation
+4. Verify password security and hashing
+5. Test token refresh mechanism and expiration handling
+6. Verify access control for protected endpoints
+
+Please respond with ONLY the commit message following this format,
+DO NOT end commit titles with any punctuation."#;
+
+    const BASELINE_CHINESE: &str = r#"请分析以下 git diff 内容，并按照以下格式生成提交信息：
+1. 第一行为标题：type: message（不超过50个字符）
+
+3. 详细的中文说明（解释做了什么改动以及为什么需要这些改动）
+4. 说明下方空一行
+5. Log 字段（仅当此次变更涉及用户可感知的功能/UI层面变化，产品经理会向用户说明的内容时才添加）
+6. Log 字段下方空一行（如果存在 Log 字段）
+7. Influence 部分，提供黑盒测试的重点和范围
+8. type 必须是以下之一：feat/fix/docs/style/refactor/test/chore
+9. 关注点：变更内容（做了什么）和变更原因（为什么）
+10. 包含重要的技术细节或上下文
+11. 不要使用任何 markdown 或代码块标记
+12. 标题结尾不要使用标点符号
+
+示例格式：
+feat: 添加用户认证模块
+
+1. 实现基于 JWT 的认证系统
+2. 添加用户登录和注册端点
+3. 包含使用 bcrypt 的密码哈希处理
+4. 设置令牌刷新机制
+
+Log: 新增用户登录注册功能
+
+Influence:
+1. 测试用户注册功能，包括有效和无效输入
+2. 验证登录功能，测试正确和错误的凭据
+3. 测试 JWT 令牌生成和验证流程
+4. 验证密码安全性和哈希处理
+5. 测试令牌刷新机制和过期处理
+6. 验证受保护端点的访问控制"#;
+
+    const BASELINE_BILINGUAL: &str = r#"Please analyze the git diff content and generate a detailed bilingual commit message with:
+1. First line in English: type: message (under 50 characters)
+2. Empty line after the title
+3. Detailed explanation in English (what was changed and why)
+4. Empty line after English explanation
+5. Log field in English (ONLY if this change involves user-facing features/UI changes)
+6. Empty line after English Log field (if present)
+7. Influence section in English with black-box testing recommendations
+
+9. Chinese title and explanation (translate the English content)
+10. Empty line after Chinese explanation
+11. Chinese Log field (translate the English Log field, only if present)
+12. Empty line after Chinese Log field (if present)
+13. Chinese Influence section (translate the English testing suggestions)
+14. Type must be one of: feat/fix/docs/style/refactor/test/chore
+15. Focus on both WHAT changed and WHY it was necessary
+16. Include any important technical details or context
+17. DO NOT wrap the response in any markdown or code block markers
+
+Example response format:
+feat: add user authentication module
+
+1. Implement JWT-based authentication system
+2. Add user login and registration endpoints
+3. Include password hashing with bcrypt
+4. Set up token refresh mechanism
+
+Log: Added user authentication feature with login and registration
+
+Influence:
+1. Test user registration with valid and invalid inputs
+2. Verify login functionality with correct and incorrect credentials
+3. Test JWT token generation and validation
+4. Verify password security and hashing
+5. Test token refresh mechanism and expiration handling
+6. Verify access control for protected endpoints
+
+feat: 添加用户认证模块
+
+1. 实现基于 JWT 的认证系统
+2. 添加用户登录和注册端点
+3. 包含使用 bcrypt 的密码哈希处理
+4. 设置令牌刷新机制
+
+Log: 新增用户登录注册功能
+
+Influence:
+1. 测试用户注册功能，包括有效和无效输入
+2. 验证登录功能，测试正确和错误的凭据
+3. 测试 JWT 令牌生成和验证流程
+4. 验证密码安全性和哈希处理
+5. 测试令牌刷新机制和过期处理
+6. 验证受保护端点的访问控制
+
+Please respond with ONLY the commit message following this format,
+DO NOT end commit titles with any punctuation."#;
+
+    /// 与旧提示词的有意差异：标题格式允许 scope，补上旧中文模板缺失的第 2 条和旧双语模板缺失的第 8 条
+    fn baseline(template: &str) -> String {
+        template
+            .replace("type: message (under", "type: message or type(scope): message (under")
+            .replace("type: message（不超过", "type: message 或 type(scope): message（不超过")
+            .replace("（不超过50个字符）\n\n3.", "（不超过50个字符）\n2. 标题下方空一行\n3.")
+            .replace("recommendations\n\n9.", "recommendations\n8. Empty line after English Influence section\n9.")
+    }
+
+    #[test]
+    fn test_render_matches_baseline() {
+        let types: Vec<String> = ["feat", "fix", "docs", "style", "refactor", "test", "chore"].iter().map(|t| t.to_string()).collect();
+        for (mode, template) in [
+            (LanguageMode::EnglishOnly, BASELINE_ENGLISH),
+            (LanguageMode::ChineseOnly, BASELINE_CHINESE),
+            (LanguageMode::Bilingual, BASELINE_BILINGUAL),
+        ] {
+            assert_eq!(render(mode, Sections::default(), &types), baseline(template), "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn test_render_default_sections() {
+        let prompt = render(LanguageMode::EnglishOnly, Sections::default(), &types());
+        assert!(prompt.starts_with("Please analyze the git diff content and generate a commit message in English only:"));
+        assert!(prompt.contains("Log: Added user authentication feature"));
+        assert!(prompt.contains("Influence:\n1. Test user registration"));
+        assert!(prompt.contains(NO_CHINESE_RULE));
+        assert!(prompt.contains("Type must be one of: feat/fix/docs/style/refactor/perf/test/build/ci/chore/revert\n"));
+        assert!(!prompt.contains("Fixes: #123"));
+        assert!(prompt.ends_with(FOOTER));
+
+        let count = numbers(&prompt).len();
+        assert_eq!(numbers(&prompt), (1..=count).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_render_toggles_sections() {
+        let sections = Sections { influence: false, log: false, trailers: true, ..Default::default() };
+        let prompt = render(LanguageMode::ChineseOnly, sections, &types());
+        assert!(prompt.starts_with(HEADER.zh));
+        assert!(!prompt.contains("Influence"));
+        assert!(!prompt.contains("Log"));
+        assert!(prompt.contains("feat: 添加用户认证模块\n\n1. 实现基于 JWT 的认证系统"));
+        assert!(prompt.ends_with("4. 设置令牌刷新机制\n\nFixes: #123"));
+        assert!(!prompt.contains("Please respond"));
+
+        // 仅生成标题时不需要分段说明
+        let sections = Sections { body: false, log: false, influence: false, ..Default::default() };
+        let prompt = render(LanguageMode::EnglishOnly, sections, &types());
+        assert!(!prompt.contains("Empty line"));
+        assert!(prompt.contains("Example response format:\nfeat: add user authentication module\n\nPlease respond"));
+    }
+
+    #[test]
+    fn test_render_bilingual() {
+        let prompt = render(LanguageMode::Bilingual, Sections::default(), &types());
+        assert!(prompt.starts_with(BILINGUAL_HEADER));
+        assert!(prompt.contains("Chinese title and explanation (translate the English content)"));
+        assert!(!prompt.contains(NO_CHINESE_RULE));
+        let english = prompt.find("Log: Added user").unwrap();
+        let chinese = prompt.find("Log: 新增用户登录注册功能").unwrap();
+        assert!(english < chinese);
+    }
+
+    #[test]
+    fn test_build_prompt_with_hints() {
+        let mut config = Config::new();
+        config.commit_prompt = Some(PathBuf::from("/nonexistent/commit_prompt.txt"));
+        let hints = PromptHints {
+            examples: vec!["feat(dock): add plugin loader".to_string(), "fix(dock): crash on exit\n\nPMS: BUG-1".to_string()],
+            ..Default::default()
+        };
+        let prompt = build_prompt(&config, LanguageMode::ChineseOnly, Sections::default(), &hints, Some("修复崩溃"));
+        assert!(prompt.contains(&format!("{}\n---\nfeat(dock): add plugin loader\n---\nfix(dock): crash on exit\n\nPMS: BUG-1\n---\n\n用户描述：",
+            STYLE_EXAMPLES_HEADER.zh)));
+
+        let prompt = build_prompt(&config, LanguageMode::EnglishOnly, Sections::default(), &PromptHints::default(), None);
+        assert!(!prompt.contains(STYLE_EXAMPLES_HEADER.en));
+        assert!(!prompt.contains("scope in the title"));
+        assert!(prompt.ends_with("\n\nHere are the changes:\n"));
+
+        config.commit_types = vec!["feat".to_string(), "fix".to_string()];
+        let hints = PromptHints {
+            scope: Some("dock".to_string()),
+            breaking: vec!["old_api".to_string(), "Plugin".to_string()],
+            ..Default::default()
+        };
+        let prompt = build_prompt(&config, LanguageMode::EnglishOnly, Sections::default(), &hints, None);
+        assert!(prompt.contains("Type must be one of: feat/fix\n"));
+        assert!(prompt.contains("use it as the scope in the title, e.g. feat(dock): message"));
+        assert!(prompt.contains("removes these public APIs: old_api, Plugin."));
+    }
+
+    #[test]
+    fn test_expand_custom_template() {
+        let template = "{{default}}\n{{#chinese}}\n标题使用 模块: 描述 格式\n{{/chinese}}\n{{#english}}Use module: message titles\n{{/english}}{{#log}}Log 必填{{/log}}";
+        let sections = Sections { log: false, ..Default::default() };
+
+        let prompt = expand(template, LanguageMode::ChineseOnly, sections, &types());
+        assert!(prompt.starts_with(&render(LanguageMode::ChineseOnly, sections, &types())));
+        assert!(prompt.ends_with("\n标题使用 模块: 描述 格式"));
+        assert!(!prompt.contains("Use module"));
+        assert!(!prompt.contains("Log 必填"));
+
+        let prompt = expand("{{#english}}{{#influence}}with tests{{/influence}}{{/english}}",
+            LanguageMode::EnglishOnly, Sections::default(), &types());
+        assert_eq!(prompt, "with tests");
+
+        let prompt = expand("Types: {{types}}", LanguageMode::EnglishOnly, Sections::default(), &["feat".to_string(), "fix".to_string()]);
+        assert_eq!(prompt, "Types: feat/fix");
+    }
+}
@@ -1,22 +1,26 @@
 use anyhow::{Context, Result};
-use dialoguer::{Confirm, Input};
+use dialoguer::{Confirm, Input, Select};
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 use std::fs;
 use copilot_client::CopilotClient;
 use copilot_client::get_github_token;
 use log::{debug, info, warn};
 use dialoguer::console::Term;
 use crate::ai_service;
+use crate::config_migration;
+use crate::profile::{self, ActiveProfile, Profile};
+use crate::repo_config::{self, RepoConfig};
+use crate::secret;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
+    #[serde(default)]
+    pub version: u32,  // 配置文件格式版本，见 config_migration
     #[serde(default)]
     pub default_service_id: String,  // 改为使用服务ID
-    // 保留原字段以确保向后兼容性
-    #[serde(default = "default_service_type")]
-    pub default_service: AIService,
     pub services: Vec<AIServiceConfig>,
     #[serde(default = "default_ai_review")]
     pub ai_review: bool,  // 添加 AI Review 开关
@@ -30,6 +34,224 @@ pub struct Config {
     pub only_chinese: bool,  // 是否默认只使用中文
     #[serde(default = "default_only_english")]
     pub only_english: bool,  // 是否默认只使用英文
+    #[serde(default)]
+    pub retry: RetryConfig,  // 网络请求重试策略
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub diff_ignore: Vec<String>,  // 发送给 AI 前从 diff 中过滤的文件，gitignore 语法
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review_prompt: Option<PathBuf>,  // 自定义代码审查提示词文件，相对路径基于配置文件所在目录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_prompt: Option<PathBuf>,  // 自定义提交信息模板文件，相对路径基于配置文件所在目录
+    #[serde(default)]
+    pub learn_style: bool,  // 是否从仓库的提交历史中选取示例，让生成的提交信息沿用仓库风格
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commit_types: Vec<String>,  // 允许的提交类型，为空时使用 conventional::DEFAULT_COMMIT_TYPES
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub scopes: BTreeMap<String, String>,  // 文件路径规则（gitignore 语法）到 scope 的映射
+    #[serde(default)]
+    pub lint: LintConfig,  // 提交信息检查规则
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change_id: Option<bool>,  // 是否为提交生成 Gerrit Change-Id，未设置时仅在远程仓库为 Gerrit 时生成
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,  // 按名称保存的配置方案
+    #[serde(skip)]
+    pub origin: ConfigOrigin,  // 各项设置的来源，仅用于展示
+}
+
+/// 配置项的来源
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigSource {
+    Default,
+    Global,
+    Profile,
+    Repo,
+}
+
+/// 记录每个配置项来自默认值、全局配置还是仓库配置
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOrigin {
+    global_keys: BTreeSet<String>,
+    pub profile: Option<ActiveProfile>,
+    profile_keys: BTreeSet<String>,
+    pub repo_path: Option<PathBuf>,
+    repo_keys: BTreeSet<String>,
+    pub secrets_resolved: bool,  // 密钥引用已解析为明文
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "默认值"),
+            ConfigSource::Global => write!(f, "全局配置"),
+            ConfigSource::Profile => write!(f, "配置方案"),
+            ConfigSource::Repo => write!(f, "仓库配置"),
+        }
+    }
+}
+
+impl ConfigOrigin {
+    /// 多个配置项共同决定一个设置时，取优先级最高的来源
+    pub fn source_of(&self, keys: &[&str]) -> ConfigSource {
+        let sources: Vec<ConfigSource> = keys.iter().map(|key| self.source(key)).collect();
+        if sources.contains(&ConfigSource::Repo) {
+            ConfigSource::Repo
+        } else if sources.contains(&ConfigSource::Profile) {
+            ConfigSource::Profile
+        } else if sources.contains(&ConfigSource::Global) {
+            ConfigSource::Global
+        } else {
+            ConfigSource::Default
+        }
+    }
+
+    pub fn source(&self, key: &str) -> ConfigSource {
+        if self.repo_keys.contains(key) {
+            ConfigSource::Repo
+        } else if self.profile_keys.contains(key) {
+            ConfigSource::Profile
+        } else if self.global_keys.contains(key) {
+            ConfigSource::Global
+        } else {
+            ConfigSource::Default
+        }
+    }
+
+    pub fn mark_repo(&mut self, key: &str) {
+        self.repo_keys.insert(key.to_string());
+    }
+
+    pub fn mark_profile(&mut self, key: &str) {
+        self.profile_keys.insert(key.to_string());
+    }
+}
+
+/// AI 服务请求的重试策略
+///
+/// 超时、连接失败以及 HTTP 429/5xx 会按指数退避自动重试，
+/// 服务端返回 `Retry-After` 时优先使用其指定的等待时间，超过 `max_retry_after_secs` 时由用户决定是否等待。
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RetryConfig {
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,  // 最多请求次数（包含第一次请求）
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,  // 第一次重试前的等待时间
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,  // 单次等待时间上限
+    #[serde(default = "default_max_retry_after_secs")]
+    pub max_retry_after_secs: u64,  // 自动等待 Retry-After 的上限
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            initial_backoff_ms: default_initial_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+            max_retry_after_secs: default_max_retry_after_secs(),
+        }
+    }
+}
+
+impl RetryConfig {
+    /// 第 `attempt` 次请求失败后的等待时间，每次翻倍且不超过上限
+    pub fn backoff(&self, attempt: u32) -> std::time::Duration {
+        let factor = 1u64 << attempt.saturating_sub(1).min(16);
+        let millis = self.initial_backoff_ms.saturating_mul(factor).min(self.max_backoff_ms);
+        std::time::Duration::from_millis(millis)
+    }
+
+    /// 第 `attempt` 次请求失败后的等待时间，服务端指定了 `Retry-After` 时使用其值
+    ///
+    /// `Retry-After` 超过 `max_retry_after_secs` 时返回 Err，不自动等待。
+    pub fn delay(&self, attempt: u32, retry_after: Option<std::time::Duration>) -> Result<std::time::Duration, std::time::Duration> {
+        match retry_after {
+            Some(delay) if delay.as_secs() > self.max_retry_after_secs => Err(delay),
+            Some(delay) => Ok(delay),
+            None => Ok(self.backoff(attempt)),
+        }
+    }
+}
+
+/// 提交信息检查规则，检查逻辑见 lint 模块
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LintConfig {
+    #[serde(default)]
+    pub mode: LintMode,  // commit-msg 钩子中的检查方式
+    #[serde(default = "default_max_title_length")]
+    pub max_title_length: usize,  // 标题最多字符数
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_trailers: Vec<String>,  // 必须包含的尾部字段，例如 Log、PMS
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            mode: LintMode::default(),
+            max_title_length: default_max_title_length(),
+            required_trailers: Vec::new(),
+        }
+    }
+}
+
+/// commit-msg 钩子中检查提交信息的方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LintMode {
+    /// 不检查
+    #[default]
+    Off,
+    /// 只显示问题，不阻止提交
+    Warn,
+    /// 存在错误时拒绝提交
+    Reject,
+    /// 自动修正能修正的问题，仍有错误时拒绝提交
+    Fix,
+}
+
+impl LintMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LintMode::Off => "不检查",
+            LintMode::Warn => "仅提示",
+            LintMode::Reject => "存在错误时拒绝提交",
+            LintMode::Fix => "自动修正，仍有错误时拒绝提交",
+        }
+    }
+}
+
+impl std::str::FromStr for LintMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "off" => Ok(LintMode::Off),
+            "warn" => Ok(LintMode::Warn),
+            "reject" => Ok(LintMode::Reject),
+            "fix" => Ok(LintMode::Fix),
+            _ => Err(format!("不支持的检查方式: {}，可选值: off、warn、reject、fix", s)),
+        }
+    }
+}
+
+fn default_max_title_length() -> usize {
+    50
+}
+
+fn default_max_attempts() -> u32 {
+    3
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_max_backoff_ms() -> u64 {
+    30000
+}
+
+fn default_max_retry_after_secs() -> u64 {
+    120
 }
 
 // 添加默认值函数
@@ -41,11 +263,6 @@ fn default_only_english() -> bool {
     false
 }
 
-// 默认服务类型，用于向后兼容
-fn default_service_type() -> AIService {
-    AIService::OpenAI
-}
-
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct GerritConfig {
     pub username: Option<String>,
@@ -64,7 +281,7 @@ fn default_timeout() -> u64 {
 }
 
 // 生成唯一服务ID的函数
-fn generate_service_id() -> String {
+pub(crate) fn generate_service_id() -> String {
     use std::time::{SystemTime, UNIX_EPOCH};
     use std::sync::atomic::{AtomicU32, Ordering};
 
@@ -77,6 +294,16 @@ fn generate_service_id() -> String {
     format!("service_{}_{}", timestamp, counter)
 }
 
+// 解析 "Name: Value" 格式的 HTTP 头
+fn parse_header(input: &str) -> Option<(String, String)> {
+    let (key, value) = input.split_once(':')?;
+    let key = key.trim();
+    if key.is_empty() || key.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((key.to_string(), value.trim().to_string()))
+}
+
 // 添加响应的最大token
 fn default_max_tokens() -> u64 {
     2048
@@ -90,6 +317,69 @@ pub struct AIServiceConfig {
     pub model: Option<String>,  // 新增字段
     #[serde(default = "generate_service_id")]
     pub id: String,  // 新增唯一标识符
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,  // 自定义服务的显示名称
+    #[serde(default, skip_serializing_if = "AuthScheme::is_bearer")]
+    pub auth_scheme: AuthScheme,  // API Key 的传递方式
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra_headers: BTreeMap<String, String>,  // 每次请求附加的 HTTP 头
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_tokens: Option<usize>,  // 模型上下文窗口大小，未设置时根据模型名称推断
+}
+
+impl AIServiceConfig {
+    pub fn new(service: AIService) -> Self {
+        Self {
+            service,
+            api_key: String::new(),
+            api_endpoint: None,
+            model: None,
+            id: generate_service_id(),
+            name: None,
+            auth_scheme: AuthScheme::default(),
+            extra_headers: BTreeMap::new(),
+            context_tokens: None,
+        }
+    }
+
+    /// 用于界面展示的服务名称，自定义服务优先使用用户设置的名称
+    pub fn display_name(&self) -> String {
+        match &self.name {
+            Some(name) if !name.is_empty() => name.clone(),
+            _ => format!("{:?}", self.service),
+        }
+    }
+}
+
+/// API Key 的认证方式
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub enum AuthScheme {
+    /// `Authorization: Bearer <key>`
+    #[default]
+    Bearer,
+    /// `api-key: <key>`，常见于 Azure OpenAI 及各类网关
+    ApiKey,
+    /// 不发送任何认证信息
+    None,
+}
+
+impl AuthScheme {
+    fn is_bearer(&self) -> bool {
+        *self == AuthScheme::Bearer
+    }
+}
+
+impl std::str::FromStr for AuthScheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bearer" => Ok(AuthScheme::Bearer),
+            "api-key" | "apikey" => Ok(AuthScheme::ApiKey),
+            "none" => Ok(AuthScheme::None),
+            _ => Err(format!("不支持的认证方式: {}，可选值: bearer、api-key、none", s)),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -101,13 +391,189 @@ pub enum AIService {
     Gemini,  // 新增
     Grok,    // 新增
     Qwen,    // 新增
+    Custom,  // 自定义 OpenAI 兼容服务
+    Ollama,  // 本地 Ollama 服务
+    LlamaCpp,  // 本地 llama.cpp server
+}
+
+impl AIService {
+    /// 未配置 api_endpoint 时使用的默认地址
+    pub fn default_endpoint(&self) -> &'static str {
+        match self {
+            AIService::DeepSeek => "https://api.deepseek.com/v1",
+            AIService::OpenAI => "https://api.openai.com/v1",
+            AIService::Claude => "https://api.anthropic.com/v1",
+            AIService::Copilot => "",  // Copilot 不需要 endpoint
+            AIService::Gemini => "https://generativelanguage.googleapis.com/v1beta",
+            AIService::Grok => "https://api.x.ai/v1",
+            AIService::Qwen => "https://dashscope.aliyuncs.com/compatible-mode/v1",
+            AIService::Custom => "",  // 自定义服务必须填写 endpoint
+            AIService::Ollama => "http://localhost:11434",
+            AIService::LlamaCpp => "http://localhost:8080/v1",
+        }
+    }
+
+    /// 未配置 model 时使用的默认模型
+    pub fn default_model(&self) -> &'static str {
+        match self {
+            AIService::DeepSeek => "deepseek-chat",
+            AIService::OpenAI => "gpt-3.5-turbo",
+            AIService::Claude => "claude-3-sonnet-20240229",
+            AIService::Copilot => "copilot-chat",
+            AIService::Gemini => "gemini-2.0-flash",
+            AIService::Grok => "grok-3-latest",
+            AIService::Qwen => "qwen-plus",
+            AIService::Custom => "",  // 自定义服务必须填写模型
+            AIService::Ollama => "llama3.1",
+            AIService::LlamaCpp => "local-model",
+        }
+    }
+
+    /// 是否为无需联网的本地服务
+    pub fn is_local(&self) -> bool {
+        matches!(self, AIService::Ollama | AIService::LlamaCpp)
+    }
+}
+
+impl std::str::FromStr for AIService {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "deepseek" => Ok(AIService::DeepSeek),
+            "openai" => Ok(AIService::OpenAI),
+            "claude" => Ok(AIService::Claude),
+            "copilot" => Ok(AIService::Copilot),
+            "gemini" => Ok(AIService::Gemini),
+            "grok" => Ok(AIService::Grok),
+            "qwen" => Ok(AIService::Qwen),
+            "custom" => Ok(AIService::Custom),
+            "ollama" => Ok(AIService::Ollama),
+            "llamacpp" | "llama.cpp" | "llama-cpp" => Ok(AIService::LlamaCpp),
+            _ => Err(format!(
+                "不支持的服务类型: {}，可选值: deepseek、openai、claude、copilot、gemini、grok、qwen、custom、ollama、llamacpp", s)),
+        }
+    }
+}
+
+/// 通过命令行参数添加或修改服务时的设置，未指定的字段保持不变
+#[derive(Debug, Default)]
+pub struct ServiceOptions {
+    pub api_key: Option<String>,
+    pub endpoint: Option<String>,
+    pub model: Option<String>,
+    pub alias: Option<String>,
+    pub auth_scheme: Option<AuthScheme>,
+    pub headers: Vec<String>,  // "Name: Value" 格式
+}
+
+impl ServiceOptions {
+    pub fn is_empty(&self) -> bool {
+        self.api_key.is_none()
+            && self.endpoint.is_none()
+            && self.model.is_none()
+            && self.alias.is_none()
+            && self.auth_scheme.is_none()
+            && self.headers.is_empty()
+    }
+
+    fn apply(self, service: &mut AIServiceConfig) -> Result<()> {
+        if let Some(api_key) = self.api_key {
+            service.api_key = api_key;
+        }
+        if let Some(endpoint) = self.endpoint {
+            if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+                return Err(anyhow::anyhow!("API 地址必须以 http:// 或 https:// 开头: {}", endpoint));
+            }
+            service.api_endpoint = Some(endpoint.trim_end_matches('/').to_string());
+        }
+        if let Some(model) = self.model {
+            service.model = Some(model);
+        }
+        if let Some(alias) = self.alias {
+            service.name = if alias.is_empty() { None } else { Some(alias) };
+        }
+        if let Some(auth_scheme) = self.auth_scheme {
+            service.auth_scheme = auth_scheme;
+        }
+        if !self.headers.is_empty() {
+            service.extra_headers = self.headers.iter()
+                .map(|header| parse_header(header)
+                    .ok_or_else(|| anyhow::anyhow!("无效的 HTTP 头: {}，格式应为 \"Name: Value\"", header)))
+                .collect::<Result<_>>()?;
+        }
+        Ok(())
+    }
+
+    /// 根据命令行参数创建新服务，并检查该服务类型的必填项
+    fn into_service(self, service: AIService) -> Result<AIServiceConfig> {
+        let mut config = AIServiceConfig::new(service.clone());
+        self.apply(&mut config)?;
+
+        match service {
+            AIService::Custom if config.api_endpoint.is_none() || config.model.is_none() => {
+                return Err(anyhow::anyhow!("自定义服务必须指定 --endpoint 和 --model"));
+            }
+            AIService::Copilot if config.api_key.is_empty() => {
+                config.api_key = get_github_token()
+                    .map_err(|e| anyhow::anyhow!("无法获取 GitHub 令牌: {}，请使用 --key 指定", e))?;
+            }
+            ref service if service.is_local() => config.auth_scheme = AuthScheme::None,
+            _ => {}
+        }
+        if config.api_key.is_empty() && config.auth_scheme != AuthScheme::None {
+            return Err(anyhow::anyhow!("{} 服务需要 API Key，请使用 --key 或 --key-env 指定", config.display_name()));
+        }
+        Ok(config)
+    }
+}
+
+/// 交互式选择要添加的 AI 服务类型
+pub fn select_service_type() -> Result<AIService> {
+    println!("\n请选择要添加的 AI 服务:");
+    println!("1) DeepSeek");
+    println!("2) OpenAI");
+    println!("3) Claude");
+    println!("4) Copilot");
+    println!("5) Gemini");
+    println!("6) Grok");
+    println!("7) Qwen");
+    println!("8) 自定义 (OpenAI 兼容接口)");
+    println!("9) Ollama (本地)");
+    println!("10) llama.cpp (本地)");
+
+    let selection = Input::<String>::new()
+        .with_prompt("请输入对应的数字")
+        .report(true)
+        .validate_with(|input: &String| -> Result<(), &str> {
+            match input.parse::<usize>() {
+                Ok(n) if (1..=10).contains(&n) => Ok(()),
+                _ => Err("请输入 1-10 之间的数字")
+            }
+        })
+        .interact()?
+        .parse::<usize>()?;
+
+    Ok(match selection {
+        1 => AIService::DeepSeek,
+        2 => AIService::OpenAI,
+        3 => AIService::Claude,
+        4 => AIService::Copilot,
+        5 => AIService::Gemini,
+        6 => AIService::Grok,
+        7 => AIService::Qwen,
+        8 => AIService::Custom,
+        9 => AIService::Ollama,
+        10 => AIService::LlamaCpp,
+        _ => unreachable!(),
+    })
 }
 
 impl Config {
     pub fn new() -> Self {
         Self {
+            version: config_migration::CURRENT_VERSION,
             default_service_id: String::new(),
-            default_service: AIService::OpenAI, // Changed from ChatGPT
             services: Vec::new(),
             ai_review: true,  // 默认开启
             timeout_seconds: default_timeout(),
@@ -115,10 +581,96 @@ impl Config {
             gerrit: None,
             only_chinese: false,  // 默认关闭
             only_english: false,  // 默认关闭
+            retry: RetryConfig::default(),
+            diff_ignore: Vec::new(),
+            review_prompt: None,
+            commit_prompt: None,
+            learn_style: false,
+            commit_types: Vec::new(),
+            scopes: BTreeMap::new(),
+            lint: LintConfig::default(),
+            change_id: None,
+            profiles: BTreeMap::new(),
+            origin: ConfigOrigin::default(),
         }
     }
 
+    /// 加载运行时使用的配置：叠加仓库配置并解析密钥引用
+    ///
+    /// 需要修改并保存配置时请使用 [`Config::load_global`]，避免把仓库配置和解析后的密钥写入全局配置。
     pub fn load() -> Result<Self> {
+        let mut config = Self::load_effective()?;
+        config.resolve_secrets()?;
+        Ok(config)
+    }
+
+    /// 加载全局配置，依次叠加选中的配置方案和当前仓库的 `.git-commit-helper.toml`，密钥保持引用原样，用于展示
+    pub fn load_effective() -> Result<Self> {
+        let mut config = Self::load_global()?;
+        if let Some(active) = profile::select(&config)? {
+            debug!("使用配置方案 {}（{}）", active.name, active.reason);
+            let profile = config.profiles[&active.name].clone();
+            profile.apply(&mut config, active)?;
+        }
+        if let Some(path) = repo_config::find() {
+            debug!("加载仓库配置: {}", path.display());
+            RepoConfig::load(&path)?.apply(&mut config, &path)?;
+        }
+        Ok(config)
+    }
+
+    /// 将 API Key、附加的 HTTP 头和 Gerrit 认证信息中的 `env:`、`cmd:`、`keyring:` 引用解析为实际值
+    ///
+    /// 默认服务解析失败时报错；其他服务和 Gerrit 只给出警告，避免用不到的配置影响正常使用。
+    pub fn resolve_secrets(&mut self) -> Result<()> {
+        let default_id = self.get_default_service().map(|s| s.id.clone()).ok();
+        for service in &mut self.services {
+            let is_default = default_id.as_deref() == Some(service.id.as_str());
+            match secret::resolve(&service.api_key) {
+                Ok(key) => service.api_key = key,
+                Err(e) if is_default => {
+                    return Err(e.context(format!("无法解析 {} 的 API Key", service.display_name())));
+                }
+                Err(e) => {
+                    warn!("无法解析 {} 的 API Key: {:#}", service.display_name(), e);
+                    service.api_key.clear();
+                }
+            }
+
+            // 附加的 HTTP 头常用于传递网关 Token，同样支持引用；解析失败的头不发送
+            let mut headers = BTreeMap::new();
+            for (name, value) in std::mem::take(&mut service.extra_headers) {
+                match secret::resolve(&value) {
+                    Ok(resolved) => { headers.insert(name, resolved); }
+                    Err(e) if is_default => {
+                        return Err(e.context(format!("无法解析 {} 的 HTTP 头 {}", service.display_name(), name)));
+                    }
+                    Err(e) => warn!("无法解析 {} 的 HTTP 头 {}: {:#}", service.display_name(), name, e),
+                }
+            }
+            service.extra_headers = headers;
+        }
+
+        if let Some(gerrit) = &mut self.gerrit {
+            for (name, field) in [("password", &mut gerrit.password), ("token", &mut gerrit.token)] {
+                if let Some(value) = field.as_deref() {
+                    match secret::resolve(value) {
+                        Ok(resolved) => *field = Some(resolved),
+                        Err(e) => {
+                            warn!("无法解析 Gerrit {}: {:#}", name, e);
+                            *field = None;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.origin.secrets_resolved = true;
+        Ok(())
+    }
+
+    /// 只加载全局配置文件
+    pub fn load_global() -> Result<Self> {
         let config_path = Self::config_path()?;
         debug!("尝试加载配置文件: {}", config_path.display());
 
@@ -130,27 +682,44 @@ impl Config {
         let content = fs::read_to_string(&config_path)
             .with_context(|| format!("无法读取配置文件: {}", config_path.display()))?;
 
-        let mut config: Config = serde_json::from_str(&content)
+        let mut value: serde_json::Value = serde_json::from_str(&content)
             .with_context(|| format!("配置文件格式错误: {}", config_path.display()))?;
+        let migrated_from = config_migration::migrate(&mut value)?;
+        let global_keys = value.as_object()
+            .map(|object| object.keys().cloned().collect())
+            .unwrap_or_default();
+        let mut config: Config = serde_json::from_value(value)
+            .with_context(|| format!("配置文件格式错误: {}", config_path.display()))?;
+        config.origin.global_keys = global_keys;
+        if let Some(from) = migrated_from {
+            config.write_migrated(&config_path, from);
+        }
 
-        // 确保所有服务都有ID，为向后兼容性
-        config.ensure_service_ids();
-
-        info!("已加载配置，使用 {:?} 服务", config.default_service);
+        info!("已加载配置，使用 {} 服务", config.default_service_name());
         Ok(config)
     }
 
-    // 确保所有服务都有唯一ID
-    fn ensure_service_ids(&mut self) {
-        for service in &mut self.services {
-            if service.id.is_empty() {
-                service.id = generate_service_id();
-            }
+    /// 加载全局配置，配置文件不存在时返回默认配置
+    ///
+    /// 配置文件存在但无法加载时报错，避免用默认配置覆盖原文件。
+    pub fn load_global_or_new() -> Result<Self> {
+        if !Self::config_path()?.exists() {
+            return Ok(Self::new());
         }
+        Self::load_global()
+    }
 
-        // 如果没有设置默认服务ID，且有服务存在，设置为第一个服务的ID
-        if self.default_service_id.is_empty() && !self.services.is_empty() {
-            self.default_service_id = self.services[0].id.clone();
+    /// 备份旧版本配置文件后写回迁移结果，失败时只在内存中使用迁移后的配置
+    fn write_migrated(&self, config_path: &std::path::Path, from: u32) {
+        let result = config_migration::backup(config_path, from).and_then(|backup| {
+            fs::write(config_path, serde_json::to_string_pretty(self)?)
+                .with_context(|| format!("无法写入配置文件: {}", config_path.display()))?;
+            Ok(backup)
+        });
+        match result {
+            Ok(backup) => warn!("配置文件已从版本 {} 升级到版本 {}，原文件已备份到 {}",
+                from, config_migration::CURRENT_VERSION, backup.display()),
+            Err(e) => warn!("配置文件升级后未能写回，本次仅在内存中使用新格式: {:#}", e),
         }
     }
 
@@ -186,7 +755,7 @@ impl Config {
                     .interact_text()?;
 
                 let password: String = Input::new()
-                    .with_prompt("请输入 Gerrit 密码")
+                    .with_prompt("请输入 Gerrit 密码（支持 env:/cmd:/keyring: 引用）")
                     .interact_text()?;
 
                 gerrit_config.username = Some(username);
@@ -194,7 +763,7 @@ impl Config {
             }
             2 => {
                 let token: String = Input::new()
-                    .with_prompt("请输入 Gerrit Token")
+                    .with_prompt("请输入 Gerrit Token（支持 env:/cmd:/keyring: 引用）")
                     .interact_text()?;
 
                 gerrit_config.token = Some(token);
@@ -259,7 +828,7 @@ impl Config {
         loop {
             println!("\n当前已配置的 AI 服务:");
             for (i, s) in services.iter().enumerate() {
-                println!("{}. {:?}", i + 1, s.service);
+                println!("{}. {}", i + 1, s.display_name());
             }
 
             if !Confirm::with_theme(&dialoguer::theme::ColorfulTheme::default())
@@ -270,37 +839,7 @@ impl Config {
                 break;
             }
 
-            println!("\n请选择要添加的 AI 服务:");
-            println!("1) DeepSeek");
-            println!("2) OpenAI");
-            println!("3) Claude");
-            println!("4) Copilot");
-            println!("5) Gemini");
-            println!("6) Grok");
-            println!("7) Qwen");
-
-            let selection = Input::<String>::new()
-                .with_prompt("请输入对应的数字")
-                .report(true)
-                .validate_with(|input: &String| -> Result<(), &str> {
-                    match input.parse::<usize>() {
-                        Ok(n) if n >= 1 && n <= 7 => Ok(()),
-                        _ => Err("请输入 1-7 之间的数字")
-                    }
-                })
-                .interact()?
-                .parse::<usize>()?;
-
-            let service = match selection {
-                1 => AIService::DeepSeek,
-                2 => AIService::OpenAI,
-                3 => AIService::Claude,
-                4 => AIService::Copilot,
-                5 => AIService::Gemini,
-                6 => AIService::Grok,
-                7 => AIService::Qwen,
-                _ => unreachable!(),
-            };
+            let service = select_service_type()?;
 
             let config = Config::input_service_config(service).await?;
             services.push(config);
@@ -312,7 +851,7 @@ impl Config {
 
         println!("\n请选择默认的 AI 服务:");
         for (i, s) in services.iter().enumerate() {
-            println!("{}. {:?}", i + 1, s.service);
+            println!("{}. {}", i + 1, s.display_name());
         }
 
         let services_len = services.len();
@@ -329,14 +868,8 @@ impl Config {
 
         let mut config = Config {
             default_service_id: services[default_index - 1].id.clone(),
-            default_service: services[default_index - 1].service.clone(),
             services,
-            ai_review: true,  // 默认开启
-            timeout_seconds: default_timeout(),
-            max_tokens: default_max_tokens(),
-            gerrit: None,
-            only_chinese: false,  // 默认关闭
-            only_english: false,  // 默认关闭
+            ..Config::new()
         };
 
         // 确保配置目录存在
@@ -357,17 +890,16 @@ impl Config {
         {
             println!("正在测试翻译功能...");
             // 创建一个临时的 Config 对象，确保只测试默认服务
-            let test_config = Config {
+            let mut test_config = Config {
                 default_service_id: config.services[default_index - 1].id.clone(),
-                default_service: config.default_service.clone(),
                 services: vec![config.services[default_index - 1].clone()],
-                ai_review: true,
                 timeout_seconds: config.timeout_seconds,
                 max_tokens: config.max_tokens,
-                gerrit: None,
-                only_chinese: false,
-                only_english: false,
+                retry: config.retry.clone(),
+                diff_ignore: config.diff_ignore.clone(),
+                ..Config::new()
             };
+            test_config.resolve_secrets()?;
             let translator = ai_service::create_translator(&test_config).await?;
             match translator.translate("这是一个测试消息，用于验证翻译功能是否正常。").await {
                 Ok(result) => {
@@ -403,7 +935,7 @@ impl Config {
                     match selection {
                         1 => {
                             // 重新获取当前服务的配置
-                            let new_config = Config::input_service_config(config.default_service.clone()).await?;
+                            let new_config = Config::input_service_config(config.services[default_index - 1].service.clone()).await?;
                             config.services.pop(); // 移除失败的配置
                             config.services.push(new_config); // 添加新配置
                             // 重新保存配置
@@ -478,20 +1010,16 @@ impl Config {
 
                                     // 返回配置，使用用户选择的模型
                                     AIServiceConfig {
-                                        service: AIService::Copilot,
                                         api_key: token,
-                                        api_endpoint: None,
                                         model: Some(model_id),
-                                        id: generate_service_id(),
+                                        ..AIServiceConfig::new(AIService::Copilot)
                                     }
                                 } else {
                                     // 如果没有可用模型列表，使用默认模型
                                     AIServiceConfig {
-                                        service: AIService::Copilot,
                                         api_key: token,
-                                        api_endpoint: None,
                                         model: Some("copilot-chat".to_string()),
-                                        id: generate_service_id(),
+                                        ..AIServiceConfig::new(AIService::Copilot)
                                     }
                                 }
                             },
@@ -512,19 +1040,12 @@ impl Config {
                     }
                 }
             },
-            _ => Config::input_service_config_with_default(&AIServiceConfig {
-                service: service.clone(),
-                api_key: String::new(),
-                api_endpoint: None,
-                model: None,
-                id: generate_service_id(),
-            }).await?,
+            _ => Config::input_service_config_with_default(&AIServiceConfig::new(service.clone())).await?,
         };
 
         // 添加服务
         if self.services.is_empty() {
             self.default_service_id = config.id.clone();
-            self.default_service = config.service.clone();
         }
         self.services.push(config.clone());
 
@@ -534,23 +1055,10 @@ impl Config {
             .default(true)
             .interact()?
         {
-            println!("正在测试 {:?} 服务...", config.service);
-            // 创建一个临时的 Config 对象，只包含要测试的新服务
-            let test_config = Config {
-                default_service_id: config.id.clone(),
-                default_service: config.service.clone(),
-                services: vec![config.clone()],
-                ai_review: true,
-                timeout_seconds: self.timeout_seconds,
-                max_tokens: self.max_tokens,
-                gerrit: None,
-                only_chinese: false,
-                only_english: false,
-            };
-            let translator = ai_service::create_translator(&test_config).await?;
+            println!("正在测试 {} 服务...", config.display_name());
             let text = "这是一个测试消息，用于验证翻译功能是否正常。";
             debug!("开始发送翻译请求");
-            match translator.translate(text).await {
+            match self.test_service(&config, text).await {
                 Ok(result) => {
                     debug!("收到翻译响应");
                     println!("\n测试结果:");
@@ -607,7 +1115,7 @@ impl Config {
             }
         } else {
             self.save()?;
-            println!("✅ {:?} 服务已添加（未测试）", service);
+            println!("✅ {} 服务已添加（未测试）", config.display_name());
         }
 
         info!("AI 服务已添加");
@@ -622,7 +1130,7 @@ impl Config {
         println!("\n已配置的 AI 服务:");
         for (i, s) in self.services.iter().enumerate() {
             let default_marker = if self.is_default_service(s) { " (当前默认)" } else { "" };
-            println!("[{}] {:?}{}", i + 1, s.service, default_marker);
+            println!("[{}] {}{}", i + 1, s.display_name(), default_marker);
 
             // 显示URL信息
             match &s.api_endpoint {
@@ -652,11 +1160,16 @@ impl Config {
             return Err(anyhow::anyhow!("无效的服务编号"));
         }
 
-        let old_config = &self.services[selection - 1];
+        self.edit_service_at(selection - 1).await
+    }
+
+    /// 交互式编辑指定位置的服务
+    pub async fn edit_service_at(&mut self, index: usize) -> Result<()> {
+        let old_config = &self.services[index];
         let new_config = Config::input_service_config_with_default(old_config).await?;
 
         // 不进行测试，直接更新服务
-        self.services[selection - 1] = new_config;
+        self.services[index] = new_config;
         self.save()?;
 
         println!("✅ 服务配置已更新。请稍后使用 'git-commit-helper test' 命令测试该服务。");
@@ -673,7 +1186,7 @@ impl Config {
         println!("\n已配置的 AI 服务:");
         for (i, s) in self.services.iter().enumerate() {
             let default_marker = if self.is_default_service(s) { " (当前默认)" } else { "" };
-            println!("{}. {:?}{}", i + 1, s.service, default_marker);
+            println!("{}. {}{}", i + 1, s.display_name(), default_marker);
 
             // 显示URL信息
             match &s.api_endpoint {
@@ -705,12 +1218,15 @@ impl Config {
             .interact()?
             .parse::<usize>()?;
 
-        let removed = self.services.remove(selection - 1);
+        self.remove_service_at(selection - 1)
+    }
+
+    pub fn remove_service_at(&mut self, index: usize) -> Result<()> {
+        let removed = self.services.remove(index);
 
         // 如果删除的是默认服务，重新设置默认服务
         if removed.id == self.default_service_id && !self.services.is_empty() {
             self.default_service_id = self.services[0].id.clone();
-            self.default_service = self.services[0].service.clone();
         }
 
         self.save()?;
@@ -726,7 +1242,7 @@ impl Config {
         println!("\n已配置的 AI 服务:");
         for (i, s) in self.services.iter().enumerate() {
             let default_marker = if self.is_default_service(s) { " (当前默认)" } else { "" };
-            println!("{}. {:?}{}", i + 1, s.service, default_marker);
+            println!("{}. {}{}", i + 1, s.display_name(), default_marker);
 
             // 显示URL信息
             match &s.api_endpoint {
@@ -758,23 +1274,107 @@ impl Config {
             .interact()?
             .parse::<usize>()?;
 
-        let selected_service = &self.services[selection - 1];
+        self.set_default_service_at(selection - 1)
+    }
+
+    pub fn set_default_service_at(&mut self, index: usize) -> Result<()> {
+        let selected_service = &self.services[index];
         self.default_service_id = selected_service.id.clone();
-        self.default_service = selected_service.service.clone();  // 保持向后兼容
         self.save()?;
         info!("默认 AI 服务设置成功");
         Ok(())
     }
 
+    /// 按服务 ID 或别名查找服务，别名不区分大小写
+    pub fn find_service(&self, key: &str) -> Result<usize> {
+        if let Some(index) = self.services.iter().position(|s| s.id == key) {
+            return Ok(index);
+        }
+
+        let matches: Vec<usize> = self.services.iter().enumerate()
+            .filter(|(_, s)| s.display_name().eq_ignore_ascii_case(key))
+            .map(|(i, _)| i)
+            .collect();
+        match matches.as_slice() {
+            [index] => Ok(*index),
+            [] => Err(anyhow::anyhow!("找不到 ID 或别名为 {} 的 AI 服务", key)),
+            _ => Err(anyhow::anyhow!("有多个 AI 服务匹配 {}，请使用服务 ID 或设置不同的别名", key)),
+        }
+    }
+
+    /// 检查别名是否与其他服务冲突
+    fn check_alias(&self, service: &AIServiceConfig) -> Result<()> {
+        let Some(alias) = service.name.as_deref() else { return Ok(()) };
+        let conflict = self.services.iter()
+            .filter(|s| s.id != service.id)
+            .any(|s| s.id == alias || s.display_name().eq_ignore_ascii_case(alias));
+        if conflict {
+            return Err(anyhow::anyhow!("别名 {} 已被其他 AI 服务使用", alias));
+        }
+        Ok(())
+    }
+
+    /// 构造只包含指定服务的临时配置并发送一条翻译请求进行测试
+    async fn test_service(&self, service: &AIServiceConfig, text: &str) -> Result<String> {
+        let mut test_config = Config {
+            default_service_id: service.id.clone(),
+            services: vec![service.clone()],
+            timeout_seconds: self.timeout_seconds,
+            max_tokens: self.max_tokens,
+            retry: self.retry.clone(),
+            diff_ignore: self.diff_ignore.clone(),
+            ..Config::new()
+        };
+        test_config.resolve_secrets()?;
+        let translator = ai_service::create_translator(&test_config).await?;
+        translator.translate(text).await
+    }
+
+    /// 根据命令行参数添加服务，不进行任何交互
+    pub async fn add_service_with_options(
+        &mut self,
+        service: AIService,
+        options: ServiceOptions,
+        make_default: bool,
+        test: bool,
+    ) -> Result<()> {
+        let config = options.into_service(service)?;
+        self.check_alias(&config)?;
+
+        if test {
+            println!("正在测试 {} 服务...", config.display_name());
+            let result = self.test_service(&config, "这是一个测试消息，用于验证翻译功能是否正常。").await
+                .map_err(|e| e.context("服务测试失败，配置未保存（可使用 --no-test 跳过测试）"))?;
+            println!("译文: {}", result);
+        }
+
+        if make_default || self.services.is_empty() {
+            self.default_service_id = config.id.clone();
+        }
+        self.services.push(config.clone());
+        self.save()?;
+
+        println!("✅ {} 服务已添加，ID: {}", config.display_name(), config.id);
+        info!("AI 服务已添加");
+        Ok(())
+    }
+
+    /// 根据命令行参数修改服务，未指定的设置保持不变
+    pub fn edit_service_with_options(&mut self, index: usize, options: ServiceOptions) -> Result<()> {
+        let mut config = self.services[index].clone();
+        options.apply(&mut config)?;
+        self.check_alias(&config)?;
+
+        self.services[index] = config;
+        self.save()?;
+        println!("✅ {} 服务配置已更新", self.services[index].display_name());
+        info!("AI 服务已修改（未测试）");
+        Ok(())
+    }
+
     pub async fn input_service_config(service: AIService) -> Result<AIServiceConfig> {
         // 对于除 Copilot 以外的服务，使用默认逻辑
-        Config::input_service_config_with_default(&AIServiceConfig {
-            service,
-            api_key: String::new(),
-            api_endpoint: None,
-            model: None,
-            id: generate_service_id(),
-        }).await
+        Config::input_service_config_with_default(&AIServiceConfig::new(service)).await
     }
 
     pub async fn input_service_config_with_default(default: &AIServiceConfig) -> Result<AIServiceConfig> {
@@ -784,7 +1384,7 @@ impl Config {
             if !default.api_key.is_empty() {
                 // 尝试连接 Copilot API 获取可用模型
                 let editor_version = "1.0.0".to_string();
-                match CopilotClient::new_with_models(default.api_key.clone(), editor_version).await {
+                match CopilotClient::new_with_models(secret::resolve(&default.api_key)?, editor_version).await {
                     Ok(client) => {
                         let models = client.get_models().await?;
                         if !models.is_empty() {
@@ -822,11 +1422,9 @@ impl Config {
                             };
 
                             return Ok(AIServiceConfig {
-                                service: default.service.clone(),
-                                api_key: default.api_key.clone(),
                                 api_endpoint: None,
                                 model: Some(model_id),
-                                id: default.id.clone(),  // 保持现有ID
+                                ..default.clone()  // 保持现有ID和 token
                             });
                         }
                     },
@@ -843,11 +1441,9 @@ impl Config {
                     .interact_text()?;
 
                 return Ok(AIServiceConfig {
-                    service: default.service.clone(),
-                    api_key: default.api_key.clone(),  // 保留原有 token
                     api_endpoint: None,
                     model: if model.is_empty() { Some("copilot-chat".to_string()) } else { Some(model) },
-                    id: default.id.clone(),  // 保持现有ID
+                    ..default.clone()  // 保留原有 token 和ID
                 });
             } else {
                 // 如果没有 API key，直接处理 Copilot 验证，而不是递归调用
@@ -897,20 +1493,16 @@ impl Config {
 
                                     // 返回配置，使用用户选择的模型
                                     return Ok(AIServiceConfig {
-                                        service: AIService::Copilot,
                                         api_key: token,
-                                        api_endpoint: None,
                                         model: Some(model_id),
-                                        id: generate_service_id(),
+                                        ..AIServiceConfig::new(AIService::Copilot)
                                     });
                                 } else {
                                     // 如果没有可用模型列表，使用默认模型
                                     return Ok(AIServiceConfig {
-                                        service: AIService::Copilot,
                                         api_key: token,
-                                        api_endpoint: None,
                                         model: Some("copilot-chat".to_string()),
-                                        id: generate_service_id(),
+                                        ..AIServiceConfig::new(AIService::Copilot)
                                     });
                                 }
                             },
@@ -933,36 +1525,28 @@ impl Config {
             }
         }
 
+        if default.service == AIService::Custom {
+            return Config::input_custom_service_config(default);
+        }
+
+        if default.service.is_local() {
+            return Config::input_local_service_config(default).await;
+        }
+
         // 非 Copilot 服务需要 API Key
         let api_key: String = Input::new()
-            .with_prompt("请输入 API Key")
+            .with_prompt("请输入 API Key（支持 env:变量名、cmd:命令、keyring:服务/账户 引用）")
             .with_initial_text(&default.api_key)
             .interact_text()?;
 
-        let default_endpoint = match default.service {
-            AIService::DeepSeek => "https://api.deepseek.com/v1",
-            AIService::OpenAI => "https://api.openai.com/v1",
-            AIService::Claude => "https://api.anthropic.com/v1",
-            AIService::Copilot => "",  // Copilot 不需要 endpoint
-            AIService::Gemini => "https://generativelanguage.googleapis.com/v1beta",
-            AIService::Grok => "https://api.x.ai/v1",
-            AIService::Qwen => "https://dashscope.aliyuncs.com/compatible-mode/v1",
-        };
+        let default_endpoint = default.service.default_endpoint();
         let api_endpoint: String = Input::new()
             .with_prompt(format!("请输入 API Endpoint (可选，直接回车使用默认值) [{}]", default_endpoint))
             .with_initial_text(default.api_endpoint.as_deref().unwrap_or(""))
             .allow_empty(true)
             .interact_text()?;
 
-        let default_model_name = match default.service {
-            AIService::DeepSeek => "deepseek-chat",
-            AIService::OpenAI => "gpt-3.5-turbo",
-            AIService::Claude => "claude-3-sonnet-20240229",
-            AIService::Copilot => "copilot-chat",
-            AIService::Gemini => "gemini-2.0-flash",
-            AIService::Grok => "grok-3-latest",
-            AIService::Qwen => "qwen-plus",
-        };
+        let default_model_name = default.service.default_model();
         let model: String = Input::new()
             .with_prompt(format!("请输入模型名称 (可选，直接回车使用默认值) [{}]", default_model_name))
             .with_initial_text(default.model.as_deref().unwrap_or(""))
@@ -975,40 +1559,180 @@ impl Config {
             api_endpoint: if api_endpoint.is_empty() { None } else { Some(api_endpoint) },
             model: if model.is_empty() { None } else { Some(model) },
             id: if default.id.is_empty() { generate_service_id() } else { default.id.clone() },  // 保持现有ID或生成新ID
+            ..default.clone()
         })
     }
 
-    pub fn get_default_service(&self) -> Result<&AIServiceConfig> {
-        if self.services.is_empty() {
-            return Err(anyhow::anyhow!("没有配置任何 AI 服务"));
-        }
+    async fn input_local_service_config(default: &AIServiceConfig) -> Result<AIServiceConfig> {
+        let default_endpoint = default.service.default_endpoint();
+        let api_endpoint: String = Input::new()
+            .with_prompt(format!("请输入本地服务地址 (可选，直接回车使用默认值) [{}]", default_endpoint))
+            .with_initial_text(default.api_endpoint.as_deref().unwrap_or(""))
+            .allow_empty(true)
+            .interact_text()?;
+        let api_endpoint = api_endpoint.trim_end_matches('/').to_string();
+        let endpoint = if api_endpoint.is_empty() { default_endpoint } else { api_endpoint.as_str() };
+
+        let current_model = default.model.as_deref().unwrap_or(default.service.default_model());
+        let model = match ai_service::list_local_models(&default.service, endpoint).await {
+            Ok(models) if !models.is_empty() => {
+                let current = models.iter().position(|m| m == current_model).unwrap_or(0);
+                let selection = Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+                    .with_prompt("请选择本地已安装的模型")
+                    .items(&models)
+                    .default(current)
+                    .interact()?;
+                models[selection].clone()
+            }
+            result => {
+                match result {
+                    Ok(_) => println!("⚠️ 本地服务中没有已安装的模型"),
+                    Err(e) => println!("⚠️ 无法获取本地模型列表: {}", e),
+                }
+                Input::new()
+                    .with_prompt("请输入模型名称")
+                    .with_initial_text(current_model)
+                    .interact_text()?
+            }
+        };
+
+        Ok(AIServiceConfig {
+            api_endpoint: if api_endpoint.is_empty() { None } else { Some(api_endpoint) },
+            model: Some(model),
+            auth_scheme: AuthScheme::None,
+            id: if default.id.is_empty() { generate_service_id() } else { default.id.clone() },
+            ..default.clone()
+        })
+    }
+
+    fn input_custom_service_config(default: &AIServiceConfig) -> Result<AIServiceConfig> {
+        let name: String = Input::new()
+            .with_prompt("请输入服务显示名称")
+            .with_initial_text(default.name.as_deref().unwrap_or(""))
+            .interact_text()?;
 
-        // 优先使用服务ID查找默认服务
-        if !self.default_service_id.is_empty() {
-            if let Some(service) = self.services.iter().find(|s| s.id == self.default_service_id) {
-                return Ok(service);
+        let api_endpoint: String = Input::new()
+            .with_prompt("请输入 API Base URL (例如 https://llm.example.com/v1)")
+            .with_initial_text(default.api_endpoint.as_deref().unwrap_or(""))
+            .validate_with(|input: &String| -> Result<(), &str> {
+                if input.starts_with("http://") || input.starts_with("https://") {
+                    Ok(())
+                } else {
+                    Err("请输入以 http:// 或 https:// 开头的地址")
+                }
+            })
+            .interact_text()?;
+
+        let schemes = ["Authorization: Bearer <key>", "api-key: <key>", "无需认证"];
+        let current = match default.auth_scheme {
+            AuthScheme::Bearer => 0,
+            AuthScheme::ApiKey => 1,
+            AuthScheme::None => 2,
+        };
+        let auth_scheme = match Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+            .with_prompt("请选择认证方式")
+            .items(&schemes)
+            .default(current)
+            .interact()?
+        {
+            0 => AuthScheme::Bearer,
+            1 => AuthScheme::ApiKey,
+            _ => AuthScheme::None,
+        };
+
+        let api_key = if auth_scheme == AuthScheme::None {
+            String::new()
+        } else {
+            Input::new()
+                .with_prompt("请输入 API Key（支持 env:变量名、cmd:命令、keyring:服务/账户 引用）")
+                .with_initial_text(&default.api_key)
+                .interact_text()?
+        };
+
+        let model: String = Input::new()
+            .with_prompt("请输入模型名称")
+            .with_initial_text(default.model.as_deref().unwrap_or(""))
+            .interact_text()?;
+
+        let mut extra_headers = default.extra_headers.clone();
+        if !extra_headers.is_empty() {
+            println!("\n当前附加的 HTTP 头:");
+            for (key, value) in &extra_headers {
+                println!("  {}: {}", key, secret::redact(value));
+            }
+            if Confirm::new()
+                .with_prompt("是否清空已有的 HTTP 头？")
+                .default(false)
+                .interact()?
+            {
+                extra_headers.clear();
+            }
+        }
+        loop {
+            let header: String = Input::new()
+                .with_prompt("请输入附加的 HTTP 头 (格式 Name: Value，直接回车结束)")
+                .allow_empty(true)
+                .validate_with(|input: &String| -> Result<(), &str> {
+                    if input.is_empty() || parse_header(input).is_some() {
+                        Ok(())
+                    } else {
+                        Err("格式错误，应为 Name: Value")
+                    }
+                })
+                .interact_text()?;
+            match parse_header(&header) {
+                Some((key, value)) => { extra_headers.insert(key, value); }
+                None => break,
             }
         }
 
-        // 向后兼容：如果没有找到服务ID匹配，使用服务类型查找
-        if let Some(service) = self.services.iter().find(|s| s.service == self.default_service) {
-            return Ok(service);
+        Ok(AIServiceConfig {
+            service: AIService::Custom,
+            api_key,
+            api_endpoint: Some(api_endpoint.trim_end_matches('/').to_string()),
+            model: Some(model),
+            id: if default.id.is_empty() { generate_service_id() } else { default.id.clone() },
+            name: Some(name),
+            auth_scheme,
+            extra_headers,
+            context_tokens: default.context_tokens,
+        })
+    }
+
+    pub fn get_default_service(&self) -> Result<&AIServiceConfig> {
+        if self.services.is_empty() {
+            return Err(anyhow::anyhow!("没有配置任何 AI 服务"));
         }
 
-        // 如果都没找到，返回第一个服务
-        Ok(&self.services[0])
+        // 默认服务ID失效时使用第一个服务
+        Ok(self.services.iter()
+            .find(|s| s.id == self.default_service_id)
+            .unwrap_or(&self.services[0]))
+    }
+
+    /// 默认服务的显示名称，用于日志和界面展示
+    pub fn default_service_name(&self) -> String {
+        self.get_default_service()
+            .map(|s| s.display_name())
+            .unwrap_or_else(|_| "(未配置)".to_string())
     }
 
     // 检查指定服务是否为默认服务
     pub fn is_default_service(&self, service: &AIServiceConfig) -> bool {
-        if !self.default_service_id.is_empty() {
-            return service.id == self.default_service_id;
-        }
-        // 向后兼容：如果没有服务ID，使用服务类型比较
-        service.service == self.default_service
+        self.get_default_service().is_ok_and(|default| default.id == service.id)
     }
 
     pub fn save(&self) -> Result<()> {
+        if let Some(profile) = &self.origin.profile {
+            return Err(anyhow::anyhow!("当前配置已叠加配置方案 {}，不能写回全局配置", profile.name));
+        }
+        if let Some(repo_path) = &self.origin.repo_path {
+            return Err(anyhow::anyhow!(
+                "当前配置已叠加仓库配置 {}，不能写回全局配置", repo_path.display()));
+        }
+        if self.origin.secrets_resolved {
+            return Err(anyhow::anyhow!("当前配置中的密钥引用已被解析，不能写回全局配置"));
+        }
         let config_path = Self::config_path()?;
         if let Some(parent) = config_path.parent() {
             fs::create_dir_all(parent)?;
@@ -1017,6 +1741,16 @@ impl Config {
         Ok(())
     }
 
+    /// 自定义提示词文件的实际路径，相对路径基于配置文件所在目录
+    ///
+    /// 配置中保存原始路径，读取提示词时才解析，保存配置时不会把相对路径写成绝对路径。
+    pub fn prompt_path(path: &Path) -> PathBuf {
+        match Self::config_path() {
+            Ok(config_path) => config_path.parent().map_or_else(|| path.to_path_buf(), |dir| dir.join(path)),
+            Err(_) => path.to_path_buf(),
+        }
+    }
+
     pub fn config_path() -> Result<PathBuf> {
         if let Ok(path) = std::env::var("GIT_COMMIT_HELPER_CONFIG") {
             return Ok(PathBuf::from(path));
@@ -1030,3 +1764,159 @@ impl Config {
         Ok(proj_dirs.config_dir().join("config.json"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(id: &str, alias: Option<&str>) -> AIServiceConfig {
+        let mut service = AIServiceConfig::new(AIService::OpenAI);
+        service.id = id.to_string();
+        service.name = alias.map(str::to_string);
+        service
+    }
+
+    #[test]
+    fn test_parse_service_and_auth() {
+        assert_eq!("openai".parse::<AIService>().unwrap(), AIService::OpenAI);
+        assert_eq!("llama.cpp".parse::<AIService>().unwrap(), AIService::LlamaCpp);
+        assert!("unknown".parse::<AIService>().is_err());
+        assert_eq!("api-key".parse::<AuthScheme>().unwrap(), AuthScheme::ApiKey);
+    }
+
+    #[test]
+    fn test_service_options_apply() {
+        let mut config = service("service_1", None);
+        ServiceOptions {
+            endpoint: Some("https://llm.example.com/v1/".to_string()),
+            alias: Some(String::new()),
+            headers: vec!["X-Team: desktop".to_string()],
+            ..Default::default()
+        }.apply(&mut config).unwrap();
+        assert_eq!(config.api_endpoint.as_deref(), Some("https://llm.example.com/v1"));
+        assert_eq!(config.name, None);
+        assert_eq!(config.extra_headers.get("X-Team").map(String::as_str), Some("desktop"));
+
+        let invalid = ServiceOptions { endpoint: Some("llm.example.com".to_string()), ..Default::default() };
+        assert!(invalid.apply(&mut config).is_err());
+        let invalid = ServiceOptions { headers: vec!["no colon".to_string()], ..Default::default() };
+        assert!(invalid.apply(&mut config).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_header_secrets() {
+        let mut default = service("service_1", None);
+        default.api_key = "sk-plain".to_string();
+        default.extra_headers.insert("X-Api-Token".to_string(), "cmd:printf gw-header-token".to_string());
+        default.extra_headers.insert("X-Team".to_string(), "desktop".to_string());
+        let mut other = service("service_2", None);
+        other.api_key = "sk-plain".to_string();
+        other.extra_headers.insert("X-Api-Token".to_string(), "cmd:exit 1".to_string());
+        let mut config = Config { default_service_id: "service_1".to_string(), services: vec![default, other], ..Config::new() };
+
+        config.resolve_secrets().unwrap();
+        assert_eq!(config.services[0].extra_headers.get("X-Api-Token").map(String::as_str), Some("gw-header-token"));
+        assert_eq!(config.services[0].extra_headers.get("X-Team").map(String::as_str), Some("desktop"));
+        // 非默认服务解析失败的头不发送
+        assert!(config.services[1].extra_headers.is_empty());
+
+        config.services[0].extra_headers.insert("X-Api-Token".to_string(), "cmd:exit 1".to_string());
+        assert!(config.resolve_secrets().is_err());
+    }
+
+    #[test]
+    fn test_prompt_path() {
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "services": [],
+            "default_service_id": "",
+            "review_prompt": "prompts/review.md",
+        })).unwrap();
+        // 保存时保留原始的相对路径，读取时才基于配置目录解析
+        assert_eq!(serde_json::to_value(&config).unwrap()["review_prompt"], "prompts/review.md");
+        let resolved = Config::prompt_path(config.review_prompt.as_deref().unwrap());
+        assert!(resolved.ends_with("prompts/review.md"));
+        assert_eq!(resolved.parent().and_then(Path::parent), Config::config_path().unwrap().parent());
+        assert_eq!(Config::prompt_path(Path::new("/etc/review.md")), PathBuf::from("/etc/review.md"));
+    }
+
+    #[test]
+    fn test_find_service() {
+        let mut config = Config::new();
+        config.services = vec![service("service_1", Some("Work")), service("service_2", None), service("service_3", None)];
+
+        assert_eq!(config.find_service("service_2").unwrap(), 1);
+        assert_eq!(config.find_service("work").unwrap(), 0);
+        assert!(config.find_service("missing").is_err());
+        // 未设置别名的同类服务显示名称相同，只能通过 ID 区分
+        assert!(config.find_service("openai").is_err());
+        assert!(config.check_alias(&service("service_4", Some("WORK"))).is_err());
+        assert!(config.check_alias(&service("service_1", Some("work"))).is_ok());
+    }
+
+    #[test]
+    fn test_custom_service() {
+        assert_eq!(AIService::Custom.default_endpoint(), "");
+        assert_eq!(AIService::Custom.default_model(), "");
+        assert!(!AIService::Custom.is_local());
+
+        // 自定义服务没有默认地址和模型，必须显式指定
+        let options = ServiceOptions {
+            api_key: Some("sk-test".to_string()),
+            model: Some("gpt-4o".to_string()),
+            ..Default::default()
+        };
+        let err = options.into_service(AIService::Custom).unwrap_err();
+        assert!(err.to_string().contains("--endpoint 和 --model"));
+
+        let options = ServiceOptions {
+            endpoint: Some("https://llm.example.com/v1/".to_string()),
+            model: Some("gpt-4o".to_string()),
+            alias: Some("Gateway".to_string()),
+            auth_scheme: Some(AuthScheme::ApiKey),
+            ..Default::default()
+        };
+        let err = options.into_service(AIService::Custom).unwrap_err();
+        assert!(err.to_string().contains("Gateway 服务需要 API Key"));
+
+        let config = ServiceOptions {
+            endpoint: Some("https://llm.example.com/v1/".to_string()),
+            model: Some("gpt-4o".to_string()),
+            auth_scheme: Some(AuthScheme::None),
+            ..Default::default()
+        }.into_service(AIService::Custom).unwrap();
+        assert_eq!(config.api_endpoint.as_deref(), Some("https://llm.example.com/v1"));
+        assert_eq!(config.model.as_deref(), Some("gpt-4o"));
+        assert!(config.api_key.is_empty());
+    }
+
+    #[test]
+    fn test_local_service() {
+        assert_eq!(AIService::Ollama.default_endpoint(), "http://localhost:11434");
+        assert_eq!(AIService::Ollama.default_model(), "llama3.1");
+        assert_eq!(AIService::LlamaCpp.default_endpoint(), "http://localhost:8080/v1");
+        assert_eq!(AIService::LlamaCpp.default_model(), "local-model");
+        assert!(AIService::Ollama.is_local() && AIService::LlamaCpp.is_local());
+        assert!(!AIService::OpenAI.is_local());
+
+        // 本地服务无需 API Key，也不发送认证头
+        let config = ServiceOptions::default().into_service(AIService::Ollama).unwrap();
+        assert_eq!(config.auth_scheme, AuthScheme::None);
+        assert_eq!(config.api_endpoint, None);
+        let config = ServiceOptions {
+            endpoint: Some("http://127.0.0.1:9000/v1".to_string()),
+            model: Some("qwen2.5".to_string()),
+            ..Default::default()
+        }.into_service(AIService::LlamaCpp).unwrap();
+        assert_eq!(config.auth_scheme, AuthScheme::None);
+        assert_eq!(config.api_endpoint.as_deref(), Some("http://127.0.0.1:9000/v1"));
+
+        let err = ServiceOptions::default().into_service(AIService::OpenAI).unwrap_err();
+        assert!(err.to_string().contains("OpenAI 服务需要 API Key"));
+        let config = ServiceOptions {
+            api_key: Some("env:OPENAI_API_KEY".to_string()),
+            ..Default::default()
+        }.into_service(AIService::OpenAI).unwrap();
+        assert_eq!(config.auth_scheme, AuthScheme::Bearer);
+    }
+}
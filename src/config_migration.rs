@@ -0,0 +1,203 @@
+// 配置文件版本迁移
+// 配置文件格式发生不兼容变化时递增 CURRENT_VERSION，并在 MIGRATIONS 末尾追加一步迁移。
+// 加载旧版本配置时逐步升级到当前版本，写回前先备份原文件；
+// 遇到更新版本程序写入的配置直接报错，避免静默丢弃不认识的字段。
+
+use anyhow::{Context, Result};
+use log::info;
+use serde_json::{Map, Value};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 当前程序使用的配置文件版本，没有 `version` 字段的配置视为版本 0
+pub const CURRENT_VERSION: u32 = 1;
+
+type Migration = fn(&mut Map<String, Value>) -> Result<()>;
+
+/// `MIGRATIONS[n]` 将版本 n 的配置升级到版本 n + 1
+const MIGRATIONS: [Migration; CURRENT_VERSION as usize] = [migrate_v0_to_v1];
+
+/// 读取配置文件的版本号
+pub fn version_of(config: &Value) -> Result<u32> {
+    match config.get("version") {
+        None => Ok(0),
+        Some(version) => version.as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| anyhow::anyhow!("配置文件中的 version 字段无效: {}", version)),
+    }
+}
+
+/// 将配置升级到当前版本，返回升级前的版本号，无需升级时返回 None
+pub fn migrate(config: &mut Value) -> Result<Option<u32>> {
+    let version = version_of(config)?;
+    if version > CURRENT_VERSION {
+        return Err(anyhow::anyhow!(
+            "配置文件版本为 {}，当前程序只支持到版本 {}，请升级 git-commit-helper 后再使用",
+            version, CURRENT_VERSION));
+    }
+    if version == CURRENT_VERSION {
+        return Ok(None);
+    }
+
+    let object = config.as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("配置文件格式错误: 顶层必须是 JSON 对象"))?;
+    for (from, step) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        info!("迁移配置文件: 版本 {} -> {}", from, from + 1);
+        step(object).with_context(|| format!("配置文件从版本 {} 迁移失败", from))?;
+        object.insert("version".to_string(), Value::from(from as u32 + 1));
+    }
+    Ok(Some(version))
+}
+
+/// 迁移前备份原配置文件，已存在同名备份时追加时间戳和序号，从不覆盖已有的备份
+pub fn backup(path: &Path, version: u32) -> Result<PathBuf> {
+    let content = fs::read(path)
+        .with_context(|| format!("无法读取配置文件: {}", path.display()))?;
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let mut attempt = 0;
+    loop {
+        let backup = match attempt {
+            0 => PathBuf::from(format!("{}.v{}.bak", path.display(), version)),
+            1 => PathBuf::from(format!("{}.v{}.{}.bak", path.display(), version, timestamp)),
+            n => PathBuf::from(format!("{}.v{}.{}-{}.bak", path.display(), version, timestamp, n - 1)),
+        };
+        attempt += 1;
+        // create_new 保证同一秒内多次迁移时不会覆盖其他进程刚写入的备份
+        match fs::OpenOptions::new().write(true).create_new(true).open(&backup) {
+            Ok(mut file) => {
+                file.write_all(&content)
+                    .with_context(|| format!("无法备份配置文件到 {}", backup.display()))?;
+                return Ok(backup);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e).with_context(|| format!("无法备份配置文件到 {}", backup.display())),
+        }
+    }
+}
+
+/// 版本 0 -> 1：
+/// - 服务类型 ChatGPT 更名为 OpenAI
+/// - 为缺少 ID 的服务生成 ID
+/// - 用 `default_service_id` 取代按服务类型记录默认服务的 `default_service`
+fn migrate_v0_to_v1(config: &mut Map<String, Value>) -> Result<()> {
+    let legacy_default = config.remove("default_service");
+    let legacy_default = match legacy_default.as_ref().and_then(Value::as_str) {
+        Some("ChatGPT") => Some("OpenAI"),
+        other => other,
+    };
+
+    let mut services = match config.remove("services") {
+        Some(Value::Array(services)) => services,
+        None | Some(Value::Null) => Vec::new(),
+        Some(_) => return Err(anyhow::anyhow!("services 字段必须是数组")),
+    };
+    for service in &mut services {
+        let service = service.as_object_mut()
+            .ok_or_else(|| anyhow::anyhow!("services 中的每一项必须是 JSON 对象"))?;
+        if service.get("service").and_then(Value::as_str) == Some("ChatGPT") {
+            service.insert("service".to_string(), Value::from("OpenAI"));
+        }
+        if service.get("id").and_then(Value::as_str).is_none_or(str::is_empty) {
+            service.insert("id".to_string(), Value::from(crate::config::generate_service_id()));
+        }
+    }
+
+    let id_of = |service: &Value| service["id"].as_str().map(str::to_string);
+    let current_id = config.get("default_service_id").and_then(Value::as_str).unwrap_or_default();
+    let default_id = services.iter()
+        .find(|s| !current_id.is_empty() && s["id"].as_str() == Some(current_id))
+        .or_else(|| services.iter().find(|s| legacy_default.is_some() && s["service"].as_str() == legacy_default))
+        .or_else(|| services.first())
+        .and_then(id_of)
+        .unwrap_or_default();
+
+    config.insert("default_service_id".to_string(), Value::from(default_id));
+    config.insert("services".to_string(), Value::Array(services));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_migrate_legacy_config() {
+        let mut config = json!({
+            "default_service": "ChatGPT",
+            "services": [
+                { "service": "DeepSeek", "api_key": "sk-1", "api_endpoint": null, "model": null },
+                { "service": "ChatGPT", "api_key": "sk-2", "api_endpoint": null, "model": null }
+            ],
+            "ai_review": false
+        });
+
+        assert_eq!(migrate(&mut config).unwrap(), Some(0));
+        assert_eq!(config["version"], json!(CURRENT_VERSION));
+        assert!(config.get("default_service").is_none());
+        assert_eq!(config["services"][1]["service"], json!("OpenAI"));
+        assert_eq!(config["default_service_id"], config["services"][1]["id"]);
+        assert_ne!(config["services"][0]["id"], config["services"][1]["id"]);
+        assert_eq!(config["ai_review"], json!(false));
+
+        // 迁移后的配置可以被当前版本加载
+        let loaded: crate::config::Config = serde_json::from_value(config.clone()).unwrap();
+        assert_eq!(loaded.get_default_service().unwrap().api_key, "sk-2");
+
+        // 已是当前版本时不做任何修改
+        let before = config.clone();
+        assert_eq!(migrate(&mut config).unwrap(), None);
+        assert_eq!(config, before);
+    }
+
+    #[test]
+    fn test_migrate_keeps_existing_default_id() {
+        let mut config = json!({
+            "default_service_id": "b",
+            "default_service": "OpenAI",
+            "services": [
+                { "service": "OpenAI", "api_key": "", "id": "a" },
+                { "service": "Claude", "api_key": "", "id": "b" }
+            ]
+        });
+        migrate(&mut config).unwrap();
+        assert_eq!(config["default_service_id"], json!("b"));
+        assert_eq!(config["services"][0]["id"], json!("a"));
+    }
+
+    #[test]
+    fn test_rejects_newer_version() {
+        let mut config = json!({ "version": CURRENT_VERSION + 1, "services": [] });
+        let err = migrate(&mut config).unwrap_err();
+        assert!(err.to_string().contains("请升级"));
+
+        assert!(migrate(&mut json!({ "version": "1" })).is_err());
+    }
+
+    #[test]
+    fn test_backup() {
+        let dir = std::env::temp_dir().join(format!("gch-migration-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+        fs::write(&path, "{}").unwrap();
+
+        let first = backup(&path, 0).unwrap();
+        assert_eq!(first, dir.join("config.json.v0.bak"));
+        let second = backup(&path, 0).unwrap();
+        let third = backup(&path, 0).unwrap();
+        assert_ne!(first, second);
+        assert_ne!(second, third);
+        assert_eq!(fs::read_to_string(second).unwrap(), "{}");
+
+        // 已有的备份不会被覆盖
+        fs::write(&path, "{\"version\": 1}").unwrap();
+        backup(&path, 0).unwrap();
+        assert_eq!(fs::read_to_string(&first).unwrap(), "{}");
+        assert_eq!(fs::read_to_string(third).unwrap(), "{}");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,236 @@
+// Conventional Commits 规范支持
+// 解析 `type(scope)!: subject` 格式的标题，根据暂存文件和配置的路径映射推断 scope，
+// 并在 diff 删除公开接口时提示模型标记破坏性变更。
+
+use crate::config::Config;
+use ignore::gitignore::GitignoreBuilder;
+use log::warn;
+use regex::Regex;
+use std::collections::BTreeMap;
+
+/// 默认允许的提交类型，可以通过配置中的 `commit_types` 修改
+pub const DEFAULT_COMMIT_TYPES: &[&str] = &[
+    "feat", "fix", "docs", "style", "refactor", "perf", "test", "build", "ci", "chore", "revert",
+];
+
+/// 破坏性变更的尾部字段
+pub const BREAKING_CHANGE_TRAILER: &str = "BREAKING CHANGE";
+
+/// 当前配置允许的提交类型
+pub fn allowed_types(config: &Config) -> Vec<String> {
+    if config.commit_types.is_empty() {
+        DEFAULT_COMMIT_TYPES.iter().map(|t| t.to_string()).collect()
+    } else {
+        config.commit_types.clone()
+    }
+}
+
+/// `type(scope)!: subject` 格式的提交标题
+#[derive(Debug, Clone, PartialEq)]
+pub struct Title {
+    pub commit_type: String,
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub subject: String,
+}
+
+impl Title {
+    pub fn parse(title: &str) -> Option<Self> {
+        let regex = Regex::new(r"^([A-Za-z]+)(?:\(([^()]*)\))?(!)?:\s*(.*)$").unwrap();
+        let captures = regex.captures(title.trim())?;
+        Some(Self {
+            commit_type: captures[1].to_string(),
+            scope: captures.get(2).map(|scope| scope.as_str().trim().to_string()),
+            breaking: captures.get(3).is_some(),
+            subject: captures[4].trim().to_string(),
+        })
+    }
+
+    pub fn format(&self) -> String {
+        let scope = self.scope.as_ref().map(|scope| format!("({})", scope)).unwrap_or_default();
+        let breaking = if self.breaking { "!" } else { "" };
+        format!("{}{}{}: {}", self.commit_type, scope, breaking, self.subject)
+    }
+}
+
+/// 根据配置的路径映射推断 scope
+///
+/// 映射的键使用 gitignore 语法，一个文件匹配多条规则时使用最长的规则。
+/// 所有匹配到的文件属于同一 scope 时返回该 scope，没有文件匹配或涉及多个 scope 时返回 None。
+pub fn infer_scope(scopes: &BTreeMap<String, String>, files: &[String]) -> Option<String> {
+    let mut matchers = Vec::new();
+    for (pattern, scope) in scopes {
+        let mut builder = GitignoreBuilder::new("");
+        if let Err(e) = builder.add_line(None, pattern) {
+            warn!("忽略无效的 scope 规则 {}: {}", pattern, e);
+            continue;
+        }
+        match builder.build() {
+            Ok(matcher) => matchers.push((pattern.len(), matcher, scope)),
+            Err(e) => warn!("忽略无效的 scope 规则 {}: {}", pattern, e),
+        }
+    }
+
+    let mut found: Option<&String> = None;
+    for file in files {
+        let scope = matchers.iter()
+            .filter(|(_, matcher, _)| matcher.matched_path_or_any_parents(file, false).is_ignore())
+            .max_by_key(|(len, _, _)| *len)
+            .map(|(_, _, scope)| *scope);
+        match (found, scope) {
+            (_, None) => {}
+            (None, Some(scope)) => found = Some(scope),
+            (Some(current), Some(scope)) if current != scope => return None,
+            _ => {}
+        }
+    }
+    found.cloned()
+}
+
+/// 检测 diff 中被删除且没有重新添加的公开接口，返回接口名称
+///
+/// 按文件扩展名识别 Rust、Go、JavaScript/TypeScript、Python 和 Java 的公开声明。
+pub fn detect_breaking_changes(diff: &str) -> Vec<String> {
+    let rust = Regex::new(r"^\s*pub\s+(?:async\s+|const\s+|unsafe\s+)*(?:fn|struct|enum|trait|type|const|static|mod)\s+([A-Za-z_]\w*)").unwrap();
+    let go = Regex::new(r"^func\s+(?:\([^)]*\)\s*)?([A-Z]\w*)|^type\s+([A-Z]\w*)").unwrap();
+    let js = Regex::new(r"^\s*export\s+(?:default\s+)?(?:async\s+)?(?:function\*?|class|const|let|var|interface|type|enum)\s+([A-Za-z_$][\w$]*)").unwrap();
+    let python = Regex::new(r"^(?:async\s+)?(?:def|class)\s+([A-Za-z]\w*)").unwrap();
+    let java = Regex::new(r"^\s*public\s+(?:(?:static|final|abstract|synchronized)\s+)*(?:class|interface|enum|record|[\w<>\[\],\s]+?)\s+([A-Za-z_]\w*)\s*[({<]").unwrap();
+
+    let mut file = String::new();
+    let mut removed: Vec<String> = Vec::new();
+    let mut added: Vec<String> = Vec::new();
+    for line in diff.lines() {
+        if let Some(header) = line.strip_prefix("diff --git ") {
+            file = header.rsplit(' ').next().unwrap_or_default().to_string();
+            continue;
+        }
+        if line.starts_with("---") || line.starts_with("+++") {
+            continue;
+        }
+        let (list, code) = match (line.strip_prefix('-'), line.strip_prefix('+')) {
+            (Some(code), _) => (&mut removed, code),
+            (_, Some(code)) => (&mut added, code),
+            _ => continue,
+        };
+
+        let extension = file.rsplit('.').next().unwrap_or_default();
+        let regex = match extension {
+            "rs" => &rust,
+            "go" => &go,
+            "js" | "jsx" | "mjs" | "ts" | "tsx" => &js,
+            "py" => &python,
+            "java" => &java,
+            _ => continue,
+        };
+        if let Some(name) = regex.captures(code)
+            .and_then(|captures| captures.iter().skip(1).flatten().next().map(|m| m.as_str().to_string()))
+        {
+            list.push(name);
+        }
+    }
+
+    let mut result: Vec<String> = Vec::new();
+    for name in removed {
+        if !added.contains(&name) && !result.contains(&name) {
+            result.push(name);
+        }
+    }
+    result
+}
+
+/// 提交信息是否已标记为破坏性变更
+pub fn is_marked_breaking(message: &str) -> bool {
+    message.lines().next().and_then(Title::parse).is_some_and(|title| title.breaking)
+        || message.lines().any(|line| line.starts_with(BREAKING_CHANGE_TRAILER))
+}
+
+/// 补全标题：缺少 scope 时加上推断的 scope，包含 `BREAKING CHANGE:` 尾部字段时在类型后加上 `!`
+///
+/// 标题不符合 `type: subject` 格式时原样返回。
+pub fn apply_to_message(message: &str, scope: Option<&str>) -> String {
+    let first_line = message.lines().next().unwrap_or_default();
+    let Some(mut title) = Title::parse(first_line) else {
+        return message.to_string();
+    };
+
+    if title.scope.is_none() {
+        title.scope = scope.map(str::to_string);
+    }
+    if message.lines().any(|line| line.starts_with(BREAKING_CHANGE_TRAILER)) {
+        title.breaking = true;
+    }
+    title.format() + &message[first_line.len()..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_title() {
+        let title = Title::parse("feat(dock)!: drop legacy plugin API").unwrap();
+        assert_eq!(title.commit_type, "feat");
+        assert_eq!(title.scope.as_deref(), Some("dock"));
+        assert!(title.breaking);
+        assert_eq!(title.subject, "drop legacy plugin API");
+        assert_eq!(title.format(), "feat(dock)!: drop legacy plugin API");
+
+        let title = Title::parse("fix: 修复崩溃").unwrap();
+        assert_eq!(title.scope, None);
+        assert!(!title.breaking);
+        assert!(Title::parse("Update README").is_none());
+    }
+
+    #[test]
+    fn test_infer_scope() {
+        let scopes = BTreeMap::from([
+            ("panels/dock/".to_string(), "dock".to_string()),
+            ("panels/dock/tray/".to_string(), "tray".to_string()),
+            ("*.qml".to_string(), "ui".to_string()),
+        ]);
+        let files = |names: &[&str]| names.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(infer_scope(&scopes, &files(&["panels/dock/main.cpp", "README.md"])), Some("dock".to_string()));
+        assert_eq!(infer_scope(&scopes, &files(&["panels/dock/tray/item.cpp"])), Some("tray".to_string()));
+        assert_eq!(infer_scope(&scopes, &files(&["panels/dock/a.cpp", "panels/dock/tray/b.cpp"])), None);
+        assert_eq!(infer_scope(&scopes, &files(&["src/main.rs"])), None);
+    }
+
+    #[test]
+    fn test_detect_breaking_changes() {
+        let diff = "\
+diff --git src/lib.rs src/lib.rs
+--- src/lib.rs
++++ src/lib.rs
+-pub fn old_api() {}
+-pub struct Renamed;
++pub struct Renamed {}
+-fn private_helper() {}
+diff --git web/api.ts web/api.ts
+--- web/api.ts
++++ web/api.ts
+-export function fetchUser(id: string) {}
+diff --git README.md README.md
+-pub fn not_code() {}
+";
+        assert_eq!(detect_breaking_changes(diff), vec!["old_api", "fetchUser"]);
+        assert!(detect_breaking_changes("diff --git a.go a.go\n-func helper() {}\n").is_empty());
+        assert_eq!(detect_breaking_changes("diff --git a.go a.go\n-func (s *Server) Start() {}\n"), vec!["Start"]);
+    }
+
+    #[test]
+    fn test_apply_to_message() {
+        let message = "feat: add tray menu\n\nbody";
+        assert_eq!(apply_to_message(message, Some("dock")), "feat(dock): add tray menu\n\nbody");
+        assert_eq!(apply_to_message(message, None), message);
+        assert_eq!(apply_to_message("fix(tray): crash", Some("dock")), "fix(tray): crash");
+        assert_eq!(apply_to_message("refactor: x\n\nBREAKING CHANGE: removed", None),
+            "refactor!: x\n\nBREAKING CHANGE: removed");
+        assert_eq!(apply_to_message("Update README", Some("dock")), "Update README");
+
+        assert!(is_marked_breaking("feat(api)!: drop v1"));
+        assert!(is_marked_breaking("feat: drop v1\n\nBREAKING CHANGE: v1 removed"));
+        assert!(!is_marked_breaking("feat: add v2"));
+    }
+}
@@ -0,0 +1,310 @@
+// 大型改动的 token 预算控制
+// 根据模型的上下文窗口估算 diff 是否放得下，放不下时先按文件分块总结，再用摘要代替原始 diff
+
+use crate::ai_service::Translator;
+use crate::config::{AIService, AIServiceConfig};
+use crate::terminal_format::{print_progress, Style};
+use log::{debug, info};
+
+/// 为提示词模板、分隔符等额外内容预留的 token 数
+const SAFETY_MARGIN_TOKENS: usize = 512;
+
+/// 单个分块最多占用输入预算的比例，留出空间给总结提示词
+const CHUNK_BUDGET_PERCENT: usize = 80;
+
+const SUMMARY_PROMPT: &str = r#"You are summarizing one part of a large git diff so that a commit message or code review can be written later from the summaries alone.
+
+For each file in the diff, output:
+- The file path
+- What changed and why it likely changed (new features, bug fixes, refactors, removed code)
+- Important identifiers: functions, types, config keys, commands
+- Anything risky or noteworthy for a reviewer
+
+Rules:
+1. Be concise, use short bullet points
+2. Do not repeat code verbatim unless it is a short identifier
+3. Do not invent changes that are not in the diff
+4. Answer in the same language as the code comments if they are Chinese, otherwise in English"#;
+
+/// 经过预算处理后发送给模型的改动内容
+pub struct PreparedDiff {
+    pub content: String,
+    /// 原始 diff 超出预算、已被替换为分块摘要
+    pub condensed: bool,
+}
+
+/// 估算文本的 token 数
+///
+/// 不同模型的分词器差异较大，这里采用保守估计：
+/// ASCII 字符约 4 个一个 token，中文等非 ASCII 字符按每个字符一个 token 计算。
+pub fn estimate_tokens(text: &str) -> usize {
+    let (ascii, other) = text.chars().fold((0usize, 0usize), |(ascii, other), c| {
+        if c.is_ascii() { (ascii + 1, other) } else { (ascii, other + 1) }
+    });
+    ascii.div_ceil(4) + other
+}
+
+/// 模型的上下文窗口大小（token 数）
+///
+/// 优先使用服务配置中的 `context_tokens`，否则根据模型名称推断，无法识别时按服务类型给出保守值。
+pub fn context_window(service: &AIServiceConfig) -> usize {
+    if let Some(tokens) = service.context_tokens {
+        return tokens;
+    }
+
+    let model = service.model.as_deref()
+        .unwrap_or_else(|| service.service.default_model())
+        .to_lowercase();
+    let known = [
+        ("gemini", 1_000_000),
+        ("gpt-4.1", 1_000_000),
+        ("claude", 200_000),
+        ("gpt-4o", 128_000),
+        ("gpt-4-turbo", 128_000),
+        ("grok", 128_000),
+        ("qwen-long", 1_000_000),
+        ("qwen", 128_000),
+        ("deepseek", 64_000),
+        ("gpt-3.5", 16_000),
+        ("gpt-4", 8_000),
+    ];
+    if let Some((_, tokens)) = known.iter().find(|(prefix, _)| model.contains(prefix)) {
+        return *tokens;
+    }
+
+    match service.service {
+        AIService::Gemini => 1_000_000,
+        AIService::Claude => 200_000,
+        AIService::OpenAI | AIService::Grok | AIService::Qwen => 128_000,
+        AIService::DeepSeek | AIService::Copilot => 64_000,
+        AIService::Custom => 32_000,
+        // 本地模型默认的上下文通常较小
+        AIService::Ollama | AIService::LlamaCpp => 8_000,
+    }
+}
+
+/// 扣除输出和提示词后，留给 diff 的 token 预算
+fn input_budget(service: &AIServiceConfig, max_tokens: u64, system_prompt: &str) -> usize {
+    context_window(service)
+        .saturating_sub(max_tokens as usize)
+        .saturating_sub(estimate_tokens(system_prompt))
+        .saturating_sub(SAFETY_MARGIN_TOKENS)
+}
+
+/// 按文件拆分 diff，每一段以 `diff --git` 开头
+pub fn split_by_file(diff: &str) -> Vec<String> {
+    let mut files: Vec<String> = Vec::new();
+    for line in diff.split_inclusive('\n') {
+        if line.starts_with("diff --git ") || files.is_empty() {
+            files.push(String::new());
+        }
+        files.last_mut().unwrap().push_str(line);
+    }
+    files
+}
+
+/// 将单个文件的 diff 按 hunk 拆分，文件头会附加到每一段之前
+fn split_by_hunk(file_diff: &str) -> Vec<String> {
+    let mut header = String::new();
+    let mut hunks: Vec<String> = Vec::new();
+    for line in file_diff.split_inclusive('\n') {
+        if line.starts_with("@@") {
+            hunks.push(header.clone());
+        }
+        match hunks.last_mut() {
+            Some(hunk) => hunk.push_str(line),
+            None => header.push_str(line),
+        }
+    }
+    if hunks.is_empty() {
+        hunks.push(header);
+    }
+    hunks
+}
+
+/// 按字符截断超出预算的内容
+fn truncate_to_budget(text: &str, budget: usize) -> String {
+    if estimate_tokens(text) <= budget {
+        return text.to_string();
+    }
+    let mut used = 0;
+    let mut end = 0;
+    for (index, c) in text.char_indices() {
+        // 与 estimate_tokens 保持一致的保守估算
+        used += if c.is_ascii() { 1 } else { 4 };
+        if used > budget * 4 {
+            break;
+        }
+        end = index + c.len_utf8();
+    }
+    format!("{}\n... (内容过长，已截断)\n", &text[..end])
+}
+
+/// 将 diff 切分为不超过预算的若干块，尽量保持文件完整，单个文件过大时再按 hunk 拆分
+pub fn split_into_chunks(diff: &str, budget: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    for file in split_by_file(diff) {
+        if estimate_tokens(&file) <= budget {
+            pieces.push(file);
+        } else {
+            pieces.extend(split_by_hunk(&file).into_iter().map(|hunk| truncate_to_budget(&hunk, budget)));
+        }
+    }
+
+    let mut chunks: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for piece in pieces {
+        if !current.is_empty() && estimate_tokens(&current) + estimate_tokens(&piece) > budget {
+            chunks.push(std::mem::take(&mut current));
+        }
+        current.push_str(&piece);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// 确保发送给模型的改动内容在上下文预算之内
+///
+/// diff 放得下时原样返回；否则分块调用模型总结，返回合并后的摘要并提示用户。
+pub async fn prepare_diff(
+    translator: &dyn Translator,
+    service: &AIServiceConfig,
+    max_tokens: u64,
+    system_prompt: &str,
+    diff: &str,
+) -> anyhow::Result<PreparedDiff> {
+    let budget = input_budget(service, max_tokens, system_prompt);
+    let diff_tokens = estimate_tokens(diff);
+    debug!("diff 约 {} tokens，可用预算 {} tokens", diff_tokens, budget);
+
+    if diff_tokens <= budget {
+        return Ok(PreparedDiff { content: diff.to_string(), condensed: false });
+    }
+
+    let summary_budget = input_budget(service, max_tokens, SUMMARY_PROMPT) * CHUNK_BUDGET_PERCENT / 100;
+    if summary_budget == 0 {
+        return Err(anyhow::anyhow!(
+            "模型上下文过小，无法处理当前改动，请在服务配置中调大 context_tokens 或减小 max_tokens"));
+    }
+
+    let chunks = split_into_chunks(diff, summary_budget);
+    print!("{}", Style::yellow(&format!(
+        "改动内容约 {} tokens，超出 {} 的可用上下文（约 {} tokens），将分 {} 块总结后再处理",
+        diff_tokens, service.display_name(), budget, chunks.len())));
+
+    let mut summaries = Vec::with_capacity(chunks.len());
+    for (index, chunk) in chunks.iter().enumerate() {
+        let message = format!("正在总结改动 ({}/{})", index + 1, chunks.len());
+        print_progress(&message, Some((index * 100 / chunks.len()) as u8));
+        let summary = translator.chat(SUMMARY_PROMPT, chunk).await?;
+        summaries.push(summary.trim().to_string());
+    }
+    print_progress("正在总结改动", Some(100));
+    info!("已将 {} tokens 的 diff 压缩为 {} 段摘要", diff_tokens, summaries.len());
+
+    let content = format!(
+        "注意：原始 diff 过大，以下内容是按文件分块生成的改动摘要，而不是原始 diff。\n\n{}",
+        summaries.join("\n\n---\n\n"));
+    Ok(PreparedDiff { content: truncate_to_budget(&content, budget), condensed: true })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    fn file_diff(path: &str, hunks: usize, lines_per_hunk: usize) -> String {
+        let mut diff = format!("diff --git a/{0} b/{0}\n--- a/{0}\n+++ b/{0}\n", path);
+        for hunk in 0..hunks {
+            diff.push_str(&format!("@@ -{0},{1} +{0},{1} @@\n", hunk * 100 + 1, lines_per_hunk));
+            for line in 0..lines_per_hunk {
+                diff.push_str(&format!("+let value_{} = compute({});\n", line, line));
+            }
+        }
+        diff
+    }
+
+    struct EchoSummarizer;
+
+    #[async_trait]
+    impl crate::ai_service::AiService for EchoSummarizer {
+        async fn chat(&self, _system_prompt: &str, user_content: &str) -> anyhow::Result<String> {
+            let path = user_content.lines().next().unwrap_or_default();
+            Ok(format!("summary of {}", path))
+        }
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        assert_eq!(estimate_tokens("中文"), 2);
+    }
+
+    #[test]
+    fn test_context_window() {
+        let mut service = AIServiceConfig::new(AIService::OpenAI);
+        assert_eq!(context_window(&service), 16_000);
+        service.model = Some("gpt-4o-mini".to_string());
+        assert_eq!(context_window(&service), 128_000);
+        service.context_tokens = Some(4096);
+        assert_eq!(context_window(&service), 4096);
+
+        let service = AIServiceConfig::new(AIService::Ollama);
+        assert_eq!(context_window(&service), 8_000);
+    }
+
+    #[test]
+    fn test_split_into_chunks_keeps_files_together() {
+        let diff = format!("{}{}{}", file_diff("a.rs", 1, 10), file_diff("b.rs", 1, 10), file_diff("c.rs", 1, 10));
+        let file_tokens = estimate_tokens(&file_diff("a.rs", 1, 10));
+
+        let chunks = split_into_chunks(&diff, file_tokens * 2);
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[0].contains("a.rs") && chunks[0].contains("b.rs"));
+        assert!(chunks[1].starts_with("diff --git a/c.rs"));
+        assert_eq!(chunks.concat(), diff);
+    }
+
+    #[test]
+    fn test_split_into_chunks_splits_large_file_by_hunk() {
+        let diff = file_diff("big.rs", 4, 20);
+        let hunk_tokens = split_by_hunk(&diff).iter().map(|hunk| estimate_tokens(hunk)).max().unwrap();
+
+        let chunks = split_into_chunks(&diff, hunk_tokens);
+        assert_eq!(chunks.len(), 4);
+        for chunk in &chunks {
+            assert!(chunk.starts_with("diff --git a/big.rs"));
+            assert_eq!(chunk.matches("@@ -").count(), 1);
+            assert!(estimate_tokens(chunk) <= hunk_tokens);
+        }
+    }
+
+    #[test]
+    fn test_truncate_to_budget() {
+        let text = "x".repeat(100);
+        assert_eq!(truncate_to_budget(&text, 25), text);
+        let truncated = truncate_to_budget(&text, 10);
+        assert!(truncated.starts_with(&"x".repeat(40)));
+        assert!(truncated.contains("已截断"));
+    }
+
+    #[tokio::test]
+    async fn test_prepare_diff() {
+        let mut service = AIServiceConfig::new(AIService::OpenAI);
+        service.context_tokens = Some(4096);
+        let small = file_diff("a.rs", 1, 5);
+        let prepared = prepare_diff(&EchoSummarizer, &service, 1024, "prompt", &small).await.unwrap();
+        assert!(!prepared.condensed);
+        assert_eq!(prepared.content, small);
+
+        let large: String = (0..20).map(|i| file_diff(&format!("src/file_{}.rs", i), 2, 30)).collect();
+        let prepared = prepare_diff(&EchoSummarizer, &service, 1024, "prompt", &large).await.unwrap();
+        assert!(prepared.condensed);
+        assert!(prepared.content.contains("summary of diff --git a/src/file_0.rs"));
+        assert!(estimate_tokens(&prepared.content) < estimate_tokens(&large));
+    }
+}
@@ -0,0 +1,209 @@
+// diff 过滤
+// 锁文件、生成代码、第三方代码和二进制文件的改动对生成提交信息帮助不大，
+// 发送给 AI 之前将它们替换为一行统计信息，节省 token 并减少干扰
+
+use crate::config::Config;
+use crate::diff_budget::split_by_file;
+use crate::git;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use log::{debug, warn};
+
+/// 默认忽略的文件，可以在配置的 `diff_ignore` 中使用 `!pattern` 重新包含
+pub const DEFAULT_IGNORE_PATTERNS: &[&str] = &[
+    // 锁文件
+    "Cargo.lock",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+    "composer.lock",
+    "Gemfile.lock",
+    "poetry.lock",
+    "Pipfile.lock",
+    "uv.lock",
+    "go.sum",
+    "flake.lock",
+    // 压缩和生成的代码
+    "*.min.js",
+    "*.min.css",
+    "*.map",
+    "*.pb.go",
+    "*.pb.cc",
+    "*.pb.h",
+    "*_pb2.py",
+    "*_pb2_grpc.py",
+    // 第三方代码
+    "vendor/",
+    "node_modules/",
+];
+
+pub struct DiffFilter {
+    matcher: Gitignore,
+}
+
+impl DiffFilter {
+    /// 依次加载默认规则和配置中的 `diff_ignore`，后加载的规则优先
+    ///
+    /// 仓库 `.git-commit-helper.toml` 中的 `diff_ignore` 在加载配置时追加在全局规则之后。
+    pub fn load(config: Option<&Config>) -> Self {
+        let root = git::repo_root().unwrap_or_default();
+        let extra = config.map(|c| c.diff_ignore.as_slice()).unwrap_or_default();
+
+        let mut builder = GitignoreBuilder::new(&root);
+        for pattern in DEFAULT_IGNORE_PATTERNS.iter().copied().chain(extra.iter().map(String::as_str)) {
+            if let Err(e) = builder.add_line(None, pattern) {
+                warn!("忽略无效的 diff 过滤规则 {}: {}", pattern, e);
+            }
+        }
+
+        let matcher = builder.build().unwrap_or_else(|e| {
+            warn!("构建 diff 过滤规则失败: {}", e);
+            Gitignore::empty()
+        });
+        Self { matcher }
+    }
+
+    fn is_ignored(&self, path: &str) -> bool {
+        self.matcher.matched_path_or_any_parents(path, false).is_ignore()
+    }
+
+    /// 将被忽略文件和二进制文件的改动替换为一行统计信息
+    pub fn apply(&self, diff: &str) -> String {
+        split_by_file(diff)
+            .into_iter()
+            .map(|file| {
+                let Some(path) = file_path(&file) else { return file };
+                if is_binary(&file) {
+                    debug!("diff 过滤二进制文件: {}", path);
+                    format!("Binary file changed in {} (diff omitted)\n", path)
+                } else if self.is_ignored(&path) {
+                    debug!("diff 过滤文件: {}", path);
+                    format!("{} lines changed in {} (diff omitted)\n", changed_lines(&file), path)
+                } else {
+                    file
+                }
+            })
+            .collect()
+    }
+}
+
+/// 过滤暂存区 diff 的便捷函数，配置加载失败时仅使用默认规则
+pub fn filter_diff(diff: &str) -> String {
+    let config = Config::load_effective().ok();
+    DiffFilter::load(config.as_ref()).apply(diff)
+}
+
+/// 从单个文件的 diff 中解析文件路径，兼容带 `a/` `b/` 前缀和 `--no-prefix` 两种格式
+fn file_path(file_diff: &str) -> Option<String> {
+    let header = file_diff.lines().next()?.strip_prefix("diff --git ")?;
+    let prefixed = header.starts_with("a/") && header.contains(" b/");
+    let strip = |path: &str, prefix: &str| -> String {
+        let path = path.trim_end();
+        if prefixed { path.strip_prefix(prefix).unwrap_or(path).to_string() } else { path.to_string() }
+    };
+
+    for line in file_diff.lines().skip(1) {
+        if line.starts_with("@@") {
+            break;
+        }
+        if let Some(path) = line.strip_prefix("+++ ").filter(|p| *p != "/dev/null") {
+            return Some(strip(path, "b/"));
+        }
+        if let Some(path) = line.strip_prefix("rename to ") {
+            return Some(path.to_string());
+        }
+        if let Some(path) = line.strip_prefix("--- ").filter(|p| *p != "/dev/null") {
+            return Some(strip(path, "a/"));
+        }
+    }
+
+    // 二进制文件和纯模式变更没有 +++ 行，从头部解析：两个路径相同时各占一半
+    let mid = header.len() / 2;
+    if header.len() % 2 == 1 && header.is_char_boundary(mid) && header[..mid] == header[mid + 1..] {
+        return Some(header[..mid].to_string());
+    }
+    if prefixed {
+        let (_, new) = header.split_once(" b/")?;
+        return Some(new.to_string());
+    }
+    None
+}
+
+fn is_binary(file_diff: &str) -> bool {
+    file_diff.lines().any(|line| {
+        line == "GIT binary patch" || (line.starts_with("Binary files ") && line.ends_with(" differ"))
+    })
+}
+
+fn changed_lines(file_diff: &str) -> usize {
+    file_diff
+        .lines()
+        .skip_while(|line| !line.starts_with("@@"))
+        .filter(|line| line.starts_with('+') || line.starts_with('-'))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(extra: &[&str]) -> DiffFilter {
+        let mut builder = GitignoreBuilder::new("");
+        for pattern in DEFAULT_IGNORE_PATTERNS.iter().chain(extra) {
+            builder.add_line(None, pattern).unwrap();
+        }
+        DiffFilter { matcher: builder.build().unwrap() }
+    }
+
+    const SOURCE_DIFF: &str = "diff --git a/src/main.rs b/src/main.rs
+index 1111111..2222222 100644
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,2 +1,2 @@
+-fn main() {}
++fn main() { run(); }
+";
+
+    const LOCK_DIFF: &str = "diff --git a/Cargo.lock b/Cargo.lock
+index 1111111..2222222 100644
+--- a/Cargo.lock
++++ b/Cargo.lock
+@@ -1,3 +1,3 @@
+ [[package]]
+-version = \"1.0.0\"
++version = \"1.0.1\"
+";
+
+    #[test]
+    fn test_file_path() {
+        assert_eq!(file_path(SOURCE_DIFF).as_deref(), Some("src/main.rs"));
+        assert_eq!(file_path("diff --git src/lib.rs src/lib.rs\n--- src/lib.rs\n+++ src/lib.rs\n").as_deref(), Some("src/lib.rs"));
+        assert_eq!(file_path("diff --git a/old.rs b/old.rs\ndeleted file mode 100644\n--- a/old.rs\n+++ /dev/null\n").as_deref(), Some("old.rs"));
+        assert_eq!(file_path("diff --git a/a.rs b/b.rs\nsimilarity index 100%\nrename from a.rs\nrename to b.rs\n").as_deref(), Some("b.rs"));
+        assert_eq!(file_path("diff --git a/logo.png b/logo.png\nBinary files a/logo.png and b/logo.png differ\n").as_deref(), Some("logo.png"));
+        assert_eq!(file_path("diff --git logo.png logo.png\nBinary files logo.png and logo.png differ\n").as_deref(), Some("logo.png"));
+    }
+
+    #[test]
+    fn test_apply_default_patterns() {
+        let binary = "diff --git a/logo.png b/logo.png\nindex 1111111..2222222 100644\nBinary files a/logo.png and b/logo.png differ\n";
+        let vendored = "diff --git a/vendor/lib/mod.go b/vendor/lib/mod.go\n--- a/vendor/lib/mod.go\n+++ b/vendor/lib/mod.go\n@@ -1 +1 @@\n-a\n+b\n";
+        let diff = format!("{}{}{}{}", SOURCE_DIFF, LOCK_DIFF, binary, vendored);
+
+        let filtered = filter(&[]).apply(&diff);
+        assert_eq!(filtered, format!("{}{}{}{}",
+            SOURCE_DIFF,
+            "2 lines changed in Cargo.lock (diff omitted)\n",
+            "Binary file changed in logo.png (diff omitted)\n",
+            "2 lines changed in vendor/lib/mod.go (diff omitted)\n"));
+    }
+
+    #[test]
+    fn test_apply_custom_patterns() {
+        let diff = format!("{}{}", SOURCE_DIFF, LOCK_DIFF);
+
+        let filtered = filter(&["src/*.rs", "!Cargo.lock"]).apply(&diff);
+        assert_eq!(filtered, format!("2 lines changed in src/main.rs (diff omitted)\n{}", LOCK_DIFF));
+    }
+}
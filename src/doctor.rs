@@ -0,0 +1,378 @@
+// 环境诊断
+// 依次检查配置文件、AI 服务、Git Hook、Gerrit 认证和终端能力，
+// 以表格形式输出每项检查的结果和修复建议。
+
+use crate::ai_service;
+use crate::config::{AIService, AIServiceConfig, Config};
+use crate::gerrit;
+use crate::git;
+use crate::secret;
+use crate::terminal_format::{print_progress, Style};
+use anyhow::Result;
+use dialoguer::console::{measure_text_width, Term};
+use std::collections::BTreeSet;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self { name: name.into(), status: Status::Pass, detail: detail.into(), hint: None }
+    }
+
+    fn warn(name: impl Into<String>, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self { name: name.into(), status: Status::Warn, detail: detail.into(), hint: Some(hint.into()) }
+    }
+
+    fn fail(name: impl Into<String>, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self { name: name.into(), status: Status::Fail, detail: detail.into(), hint: Some(hint.into()) }
+    }
+}
+
+/// 运行全部检查并输出结果，存在失败项时返回错误
+pub async fn run() -> Result<()> {
+    let mut checks = Vec::new();
+
+    let global = check_config_file(&mut checks);
+    let effective = match &global {
+        Some(_) => match Config::load_effective() {
+            Ok(config) => {
+                checks.push(Check::pass("配置叠加", describe_layers(&config)));
+                Some(config)
+            }
+            Err(e) => {
+                checks.push(Check::fail("配置叠加", format!("{:#}", e),
+                    "检查 --profile/GIT_COMMIT_HELPER_PROFILE 指定的配置方案和仓库的 .git-commit-helper.toml"));
+                global.clone()
+            }
+        },
+        None => None,
+    };
+
+    if let Some(config) = &effective {
+        checks.push(check_service_entries(config));
+        for service in &config.services {
+            print_progress(&format!("正在检查 {} 服务", service.display_name()), None);
+            checks.push(check_service(config, service).await);
+        }
+        print!("\r\x1b[K");
+    }
+
+    checks.push(check_hook());
+    checks.push(check_gerrit(effective.as_ref()).await);
+    checks.push(check_terminal());
+
+    print_table(&checks);
+
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    if failed > 0 {
+        return Err(anyhow::anyhow!("诊断发现 {} 项失败", failed));
+    }
+    Ok(())
+}
+
+fn check_config_file(checks: &mut Vec<Check>) -> Option<Config> {
+    let path = match Config::config_path() {
+        Ok(path) => path,
+        Err(e) => {
+            checks.push(Check::fail("配置文件", format!("{:#}", e), "设置 GIT_COMMIT_HELPER_CONFIG 指定配置文件路径"));
+            return None;
+        }
+    };
+    if !path.exists() {
+        checks.push(Check::fail("配置文件", format!("{} 不存在", path.display()),
+            "运行 git-commit-helper config 进行配置"));
+        return None;
+    }
+
+    match Config::load_global() {
+        Ok(config) => {
+            checks.push(Check::pass("配置文件", format!("{}（版本 {}）", path.display(), config.version)));
+            Some(config)
+        }
+        Err(e) => {
+            checks.push(Check::fail("配置文件", format!("{:#}", e),
+                "修正配置文件中的错误，或删除后运行 git-commit-helper config 重新配置"));
+            None
+        }
+    }
+}
+
+fn describe_layers(config: &Config) -> String {
+    let mut layers = vec!["全局配置".to_string()];
+    if let Some(profile) = &config.origin.profile {
+        layers.push(format!("配置方案 {}（{}）", profile.name, profile.reason));
+    }
+    if let Some(path) = &config.origin.repo_path {
+        layers.push(format!("仓库配置 {}", path.display()));
+    }
+    layers.join(" + ")
+}
+
+/// 检查服务列表本身：ID 是否唯一、默认服务是否有效、别名是否冲突
+fn check_service_entries(config: &Config) -> Check {
+    const NAME: &str = "服务配置";
+    if config.services.is_empty() {
+        return Check::fail(NAME, "没有配置任何 AI 服务", "运行 git-commit-helper ai add 添加服务");
+    }
+
+    let mut ids = BTreeSet::new();
+    if let Some(service) = config.services.iter().find(|s| s.id.is_empty() || !ids.insert(s.id.as_str())) {
+        return Check::fail(NAME, format!("服务 ID 为空或重复: {:?}", service.id),
+            "在配置文件中为每个服务设置不同的 id");
+    }
+    if let Some(service) = config.services.iter()
+        .find(|s| s.service == AIService::Custom && (s.api_endpoint.is_none() || s.model.is_none()))
+    {
+        return Check::fail(NAME, format!("自定义服务 {} 缺少 API 地址或模型", service.display_name()),
+            format!("运行 git-commit-helper ai edit {} --endpoint <地址> --model <模型>", service.id));
+    }
+    if !config.services.iter().any(|s| s.id == config.default_service_id) {
+        return Check::warn(NAME, format!("默认服务 ID {:?} 不存在，将使用第一个服务", config.default_service_id),
+            "运行 git-commit-helper ai set-default 重新选择默认服务");
+    }
+
+    let mut aliases = BTreeSet::new();
+    if let Some(service) = config.services.iter()
+        .filter(|s| s.name.as_deref().is_some_and(|name| !name.is_empty()))
+        .find(|s| !aliases.insert(s.display_name().to_lowercase()))
+    {
+        return Check::warn(NAME, format!("别名 {} 被多个服务使用", service.display_name()),
+            format!("运行 git-commit-helper ai edit {} --alias <新别名>", service.id));
+    }
+
+    Check::pass(NAME, format!("{} 个服务，默认使用 {}", config.services.len(), config.default_service_name()))
+}
+
+/// 解析密钥后发送一次轻量请求，检查服务是否可达、认证是否有效
+async fn check_service(config: &Config, service: &AIServiceConfig) -> Check {
+    let default_marker = if config.is_default_service(service) { "（默认）" } else { "" };
+    let name = format!("AI 服务 {}{}", service.display_name(), default_marker);
+    let edit_hint = format!("运行 git-commit-helper ai edit {} 修改配置", service.id);
+
+    let mut service = service.clone();
+    match secret::resolve(&service.api_key) {
+        Ok(key) => service.api_key = key,
+        Err(e) => return Check::fail(name, format!("无法解析 API Key: {:#}", e), edit_hint),
+    }
+
+    let timeout = Duration::from_secs(config.timeout_seconds.max(1));
+    // 只请求一次，避免重试耗尽超时时间后把 HTTP 错误报告为没有响应
+    let result = tokio::time::timeout(timeout, async {
+        let mut translator = ai_service::create_translator_for_service(&service).await?;
+        translator.disable_retry();
+        translator.probe().await
+    }).await;
+    match result {
+        Ok(Ok(detail)) => Check::pass(name, detail),
+        Ok(Err(e)) => Check::fail(name, format!("{:#}", e),
+            format!("检查 API Key、API 地址和网络连接，或运行 git-commit-helper ai edit {} 修改配置", service.id)),
+        Err(_) => Check::fail(name, format!("{} 秒内没有响应", timeout.as_secs()),
+            "检查网络连接，或运行 git-commit-helper ai set-timeout 调大超时时间"),
+    }
+}
+
+/// 检查 commit-msg hook 是否由本工具安装、指向的程序是否存在且版本一致
+fn check_hook() -> Check {
+    const NAME: &str = "Git Hook";
+    let Some(hook) = hook_path() else {
+        return Check::warn(NAME, "当前目录不在 git 仓库中，跳过检查", "在仓库中运行 git-commit-helper doctor");
+    };
+    let Ok(content) = std::fs::read_to_string(&hook) else {
+        return Check::warn(NAME, format!("{} 不存在", hook.display()), "运行 git-commit-helper install 安装 hook");
+    };
+    let Some(binary) = hook_binary(&content) else {
+        return Check::warn(NAME, format!("{} 不是由 git-commit-helper 安装的", hook.display()),
+            "运行 git-commit-helper install --force 安装 hook");
+    };
+    let reinstall = "运行 git-commit-helper install --force 重新安装 hook";
+
+    if !is_executable(&hook) {
+        return Check::fail(NAME, format!("{} 没有可执行权限", hook.display()), format!("运行 chmod +x {}", hook.display()));
+    }
+    if !binary.exists() {
+        return Check::fail(NAME, format!("hook 指向的程序不存在: {}", binary.display()), reinstall);
+    }
+
+    let current = env!("CARGO_PKG_VERSION");
+    match binary_version(&binary) {
+        Some(version) if version == current => Check::pass(NAME, format!("{}（版本 {}）", binary.display(), version)),
+        Some(version) => Check::warn(NAME,
+            format!("hook 使用的程序版本为 {}，当前程序版本为 {}", version, current), reinstall),
+        None => Check::warn(NAME, format!("无法获取 {} 的版本", binary.display()), reinstall),
+    }
+}
+
+fn hook_path() -> Option<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks/commit-msg"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let path = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    // --git-path 返回的相对路径基于当前目录
+    Some(std::env::current_dir().map(|dir| dir.join(&path)).unwrap_or(path))
+}
+
+/// 从 hook 脚本中找出调用本工具的程序路径，跳过备份的旧 hook
+fn hook_binary(content: &str) -> Option<PathBuf> {
+    let regex = regex::Regex::new(r#""([^"$]+)"\s+(?:--no-review\s+)?"\$1""#).ok()?;
+    let binary = regex.captures_iter(content)
+        .map(|captures| captures[1].to_string())
+        .find(|path| !path.ends_with("commit-msg.old"))?;
+    Some(PathBuf::from(binary))
+}
+
+fn binary_version(binary: &Path) -> Option<String> {
+    let output = Command::new(binary).arg("--version").output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.split_whitespace().last().map(str::to_string)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|meta| meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    true
+}
+
+/// 根据远程仓库地址推断 Gerrit 服务地址
+fn gerrit_base_url(remote: &str) -> Option<String> {
+    gerrit::gerrit_host(remote).map(|host| format!("https://{}", host))
+}
+
+async fn check_gerrit(config: Option<&Config>) -> Check {
+    const NAME: &str = "Gerrit 认证";
+    let configured = config.and_then(|c| c.gerrit.as_ref())
+        .is_some_and(|g| g.token.is_some() || (g.username.is_some() && g.password.is_some()));
+    let from_env = std::env::var("GERRIT_TOKEN").is_ok()
+        || (std::env::var("GERRIT_USERNAME").is_ok() && std::env::var("GERRIT_PASSWORD").is_ok());
+    let base_url = git::remote_urls().iter().find_map(|url| gerrit_base_url(url));
+
+    match (configured || from_env, base_url) {
+        (false, None) => Check::pass(NAME, "未配置（仅审查 Gerrit 链接时需要）"),
+        (false, Some(base_url)) => Check::warn(NAME, format!("检测到 Gerrit 仓库 {}，但未配置认证信息", base_url),
+            "运行 git-commit-helper config 配置 Gerrit 认证，或设置 GERRIT_TOKEN 环境变量"),
+        (true, None) => Check::pass(NAME, "已配置，当前仓库不是 Gerrit 仓库，跳过在线验证"),
+        (true, Some(base_url)) => match gerrit::check_auth(&base_url).await {
+            Ok(account) => Check::pass(NAME, format!("{} 认证通过，账号 {}", base_url, account)),
+            Err(e) => Check::fail(NAME, format!("{}: {:#}", base_url, e),
+                "检查 Gerrit 用户名/密码或 Token（Gerrit 设置中的 HTTP Credentials）"),
+        },
+    }
+}
+
+/// 报告终端能力：交互式确认、流式输出和彩色输出都依赖终端
+fn check_terminal() -> Check {
+    const NAME: &str = "终端";
+    let stdin_tty = std::io::stdin().is_terminal();
+    let stdout_tty = std::io::stdout().is_terminal();
+    let term = std::env::var("TERM").unwrap_or_default();
+    let no_color = std::env::var_os("NO_COLOR").is_some() || term == "dumb";
+
+    let mut detail = vec![
+        format!("标准输入{}终端", if stdin_tty { "是" } else { "不是" }),
+        format!("标准输出{}终端", if stdout_tty { "是" } else { "不是" }),
+    ];
+    if stdout_tty {
+        detail.push(format!("宽度 {} 列", Term::stdout().size().1));
+    }
+    if !term.is_empty() {
+        detail.push(format!("TERM={}", term));
+    }
+    let detail = detail.join("，");
+
+    if !stdin_tty || !stdout_tty {
+        Check::warn(NAME, detail, "非交互环境下不会询问确认、不显示流式输出，失败时直接退出")
+    } else if no_color {
+        Check::warn(NAME, detail, "终端可能不支持彩色输出，ANSI 颜色代码可能显示为乱码")
+    } else {
+        Check::pass(NAME, detail)
+    }
+}
+
+fn print_table(checks: &[Check]) {
+    let name_width = checks.iter().map(|c| measure_text_width(&c.name)).max().unwrap_or(0);
+    println!("{}", Style::title("诊断结果:"));
+    for check in checks {
+        let status = match check.status {
+            Status::Pass => format!("{}通过{}", Style::BRIGHT_GREEN, Style::RESET),
+            Status::Warn => format!("{}警告{}", Style::BRIGHT_YELLOW, Style::RESET),
+            Status::Fail => format!("{}失败{}", Style::BRIGHT_RED, Style::RESET),
+        };
+        let padding = " ".repeat(name_width - measure_text_width(&check.name));
+        print!("{}", Style::plain(&format!("{}  {}{}  {}", status, check.name, padding, check.detail)));
+        if let Some(hint) = &check.hint {
+            print!("{}", Style::plain(&format!("{}{}提示: {}{}",
+                " ".repeat(name_width + 8), Style::GRAY, hint, Style::RESET)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_binary() {
+        let hook = "#!/bin/sh\nif git config --bool git-commit-helper.disable-review >/dev/null 2>&1; then\n    exec \"/usr/bin/git-commit-helper\" --no-review \"$1\"\nelse\n    exec \"/usr/bin/git-commit-helper\" \"$1\"\nfi\n";
+        assert_eq!(hook_binary(hook), Some(PathBuf::from("/usr/bin/git-commit-helper")));
+
+        let with_backup = "#!/bin/sh\nif [ -x \"/repo/.git/hooks/commit-msg.old\" ]; then\n    \"/repo/.git/hooks/commit-msg.old\" \"$1\" || exit $?\nfi\nexec \"/opt/gch\" \"$1\"\n";
+        assert_eq!(hook_binary(with_backup), Some(PathBuf::from("/opt/gch")));
+
+        assert_eq!(hook_binary("#!/bin/sh\nnpx commitlint --edit \"$1\"\n"), None);
+    }
+
+    #[test]
+    fn test_gerrit_base_url() {
+        assert_eq!(gerrit_base_url("ssh://me@review.example.com:29418/project").as_deref(), Some("https://review.example.com"));
+        assert_eq!(gerrit_base_url("https://gerrit.example.com/a/project").as_deref(), Some("https://gerrit.example.com"));
+        assert_eq!(gerrit_base_url("git@github.com:zccrs/git-commit-helper.git"), None);
+    }
+
+    #[test]
+    fn test_service_entries() {
+        let mut config = Config::new();
+        assert_eq!(check_service_entries(&config).status, Status::Fail);
+
+        for (id, alias) in [("a", "Work"), ("b", "work")] {
+            let mut service = AIServiceConfig::new(AIService::OpenAI);
+            service.id = id.to_string();
+            service.name = Some(alias.to_string());
+            config.services.push(service);
+        }
+        config.default_service_id = "missing".to_string();
+        assert_eq!(check_service_entries(&config).status, Status::Warn);
+
+        config.default_service_id = "a".to_string();
+        let check = check_service_entries(&config);
+        assert_eq!(check.status, Status::Warn);
+        assert!(check.detail.contains("别名"));
+
+        config.services[1].id = "a".to_string();
+        assert_eq!(check_service_entries(&config).status, Status::Fail);
+    }
+}
@@ -0,0 +1,219 @@
+// 创建 fixup 提交
+// 对暂存改动删除或修改的行执行 git blame，统计这些行由最近哪些提交引入，
+// 据此建议 fixup 的目标提交，之后可以通过 `git rebase -i --autosquash` 合并到目标提交中。
+
+use crate::terminal_format::Style;
+use anyhow::{Context, Result};
+use dialoguer::Select;
+use log::debug;
+use regex::Regex;
+use std::collections::HashMap;
+use std::process::Command;
+
+/// 只在最近的这些提交中查找目标提交
+const RECENT_COMMITS: usize = 50;
+/// 最多列出的候选提交数量
+const MAX_SUGGESTIONS: usize = 5;
+
+/// 建议的目标提交
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub commit: String,
+    pub title: String,
+    /// 暂存改动涉及的行中由该提交引入的行数
+    pub lines: usize,
+}
+
+fn git(args: &[&str]) -> Result<String> {
+    let output = Command::new("git").args(args).output().context("无法执行 git 命令")?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("git {} 执行失败: {}", args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+}
+
+/// 从 `git diff -U0` 的输出中提取改动涉及的旧文件行，返回 (文件, 起始行, 结束行)
+///
+/// 纯新增的改动块没有旧行，使用插入位置前后的两行；新增的文件没有可以追溯的行，直接跳过。
+fn touched_lines(diff: &str) -> Vec<(String, usize, usize)> {
+    let hunk = Regex::new(r"^@@ -(\d+)(?:,(\d+))? ").unwrap();
+    let mut file: Option<String> = None;
+    let mut in_header = false;
+    let mut ranges = Vec::new();
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            in_header = true;
+            continue;
+        }
+        // 只在文件头中识别 `--- a/路径`，避免把删除的 `-- ` 开头的行当作文件头
+        if let Some(path) = line.strip_prefix("--- ").filter(|_| in_header) {
+            file = path.strip_prefix("a/").map(str::to_string);
+            continue;
+        }
+        if line.starts_with("@@") {
+            in_header = false;
+        }
+        let (Some(path), Some(captures)) = (&file, hunk.captures(line)) else {
+            continue;
+        };
+        let start: usize = captures[1].parse().unwrap_or(0);
+        let count: usize = captures.get(2).map_or(1, |count| count.as_str().parse().unwrap_or(1));
+        if count > 0 {
+            ranges.push((path.clone(), start, start + count - 1));
+        } else if start == 0 {
+            ranges.push((path.clone(), 1, 1));
+        } else {
+            // 在第 start 行之后插入，blame 前后两行，超出文件末尾的行会在 blame 时被忽略
+            ranges.push((path.clone(), start, start));
+            ranges.push((path.clone(), start + 1, start + 1));
+        }
+    }
+    ranges
+}
+
+/// 统计 `git blame --porcelain` 输出中每个提交对应的行数
+fn count_blame(porcelain: &str, counts: &mut HashMap<String, usize>) {
+    let header = Regex::new(r"^([0-9a-f]{40}) \d+ \d+").unwrap();
+    for line in porcelain.lines() {
+        if let Some(captures) = header.captures(line) {
+            *counts.entry(captures[1].to_string()).or_default() += 1;
+        }
+    }
+}
+
+/// 按行数从多到少排列最近的提交，行数相同时较新的提交排在前面
+fn rank(counts: &HashMap<String, usize>, recent: &[(String, String)]) -> Vec<Suggestion> {
+    let mut suggestions: Vec<Suggestion> = recent.iter()
+        .filter_map(|(commit, title)| counts.get(commit).map(|lines| Suggestion {
+            commit: commit.clone(),
+            title: title.clone(),
+            lines: *lines,
+        }))
+        .collect();
+    suggestions.sort_by_key(|suggestion| std::cmp::Reverse(suggestion.lines));
+    suggestions.truncate(MAX_SUGGESTIONS);
+    suggestions
+}
+
+/// 根据暂存的改动建议 fixup 的目标提交
+pub fn suggest() -> Result<Vec<Suggestion>> {
+    let recent: Vec<(String, String)> = git(&["log", "--no-merges", "-n", &RECENT_COMMITS.to_string(), "--format=%H %s"])?
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(commit, title)| (commit.to_string(), title.to_string()))
+        .collect();
+
+    let diff = git(&["diff", "--cached", "-U0", "--no-color", "--no-ext-diff", "--src-prefix=a/", "--dst-prefix=b/"])?;
+    let mut counts = HashMap::new();
+    for (file, start, end) in touched_lines(&diff) {
+        let range = format!("{},{}", start, end);
+        match git(&["blame", "--porcelain", "-L", &range, "HEAD", "--", &file]) {
+            Ok(porcelain) => count_blame(&porcelain, &mut counts),
+            Err(e) => debug!("忽略无法追溯的行 {}:{}: {}", file, range, e),
+        }
+    }
+    Ok(rank(&counts, &recent))
+}
+
+fn short(commit: &str) -> &str {
+    &commit[..commit.len().min(7)]
+}
+
+/// 确定 fixup 的目标提交：未指定时从建议中选择，指定的提交与建议不符时给出提示
+fn choose_target(target: Option<&str>) -> Result<Option<String>> {
+    let suggestions = suggest()?;
+    if let Some(target) = target {
+        let commit = git(&["rev-parse", "--verify", &format!("{}^{{commit}}", target)])
+            .map_err(|_| anyhow::anyhow!("无效的提交: {}", target))?;
+        if let Some(best) = suggestions.first().filter(|_| !suggestions.iter().any(|s| s.commit == commit)) {
+            println!("{}", Style::yellow(&format!(
+                "提示：暂存的改动主要涉及提交 {} {} 引入的代码，请确认目标提交是否正确",
+                short(&best.commit), best.title)));
+        }
+        return Ok(Some(commit));
+    }
+
+    if suggestions.is_empty() {
+        return Err(anyhow::anyhow!(
+            "最近 {} 个提交中没有找到与暂存改动相关的提交，请使用 --fixup <提交> 指定目标提交", RECENT_COMMITS));
+    }
+    let mut items: Vec<String> = suggestions.iter()
+        .map(|s| format!("{} {}（涉及 {} 行）", short(&s.commit), s.title, s.lines))
+        .collect();
+    items.push("取消".to_string());
+    let selection = Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt("请选择要修正的提交")
+        .items(&items)
+        .default(0)
+        .interact()?;
+    Ok(suggestions.get(selection).map(|s| s.commit.clone()))
+}
+
+/// 为暂存的改动创建 fixup 提交，`target` 为空时根据改动建议目标提交
+pub fn commit_fixup(target: Option<&str>) -> Result<()> {
+    let Some(commit) = choose_target(target)? else {
+        println!("已取消提交");
+        return Ok(());
+    };
+
+    // fixup 提交的标题由 git 生成，不需要审查和翻译
+    let status = Command::new("git")
+        .args(["commit", "--fixup", &commit])
+        .env("GIT_COMMIT_HELPER_SKIP_REVIEW", "1")
+        .status()?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("git commit 命令执行失败"));
+    }
+    println!("提交成功！可以使用 git rebase -i --autosquash {}^ 合并到目标提交", short(&commit));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_touched_lines() {
+        let diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -10,3 +10,4 @@ fn main()
+@@ -20 +21 @@
+--- removed comment
++++ added comment
+@@ -30,0 +32,2 @@
+@@ -0,0 +1 @@
+diff --git a/new.rs b/new.rs
+--- /dev/null
++++ b/new.rs
+@@ -0,0 +1,3 @@
+";
+        assert_eq!(touched_lines(diff), vec![
+            ("src/lib.rs".to_string(), 10, 12),
+            ("src/lib.rs".to_string(), 20, 20),
+            ("src/lib.rs".to_string(), 30, 30),
+            ("src/lib.rs".to_string(), 31, 31),
+            ("src/lib.rs".to_string(), 1, 1),
+        ]);
+    }
+
+    #[test]
+    fn test_rank_blamed_commits() {
+        let a = "a".repeat(40);
+        let b = "b".repeat(40);
+        let c = "c".repeat(40);
+        let porcelain = format!("{a} 1 1 2\nauthor x\n\tline\n{a} 2 2\n\tline\n{b} 5 3 1\n\tline\n{c} 1 4 1\n\tline\n");
+        let mut counts = HashMap::new();
+        count_blame(&porcelain, &mut counts);
+
+        // c 不在最近的提交中，a 和 b 按行数排序
+        let recent = vec![(b.clone(), "fix: b".to_string()), (a.clone(), "feat: a".to_string())];
+        let ranked = rank(&counts, &recent);
+        assert_eq!(ranked, vec![
+            Suggestion { commit: a, title: "feat: a".to_string(), lines: 2 },
+            Suggestion { commit: b, title: "fix: b".to_string(), lines: 1 },
+        ]);
+    }
+}
@@ -4,6 +4,11 @@ use log::debug;
 use base64::Engine;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use crate::commit::{self, CommitMessage};
+use crate::config::Config;
+use crate::git;
 
 #[derive(Debug, Deserialize)]
 struct CommitInfo {
@@ -177,6 +182,34 @@ pub async fn get_change_diff(url: &str) -> Result<String> {
     Ok(diff)
 }
 
+/// 使用已配置的认证信息访问 Gerrit 的 `/a/accounts/self`，返回当前登录的账号
+pub async fn check_auth(base_url: &str) -> Result<String> {
+    let api_url = format!("{}/a/accounts/self", base_url.trim_end_matches('/'));
+    debug!("检查 Gerrit 认证: {}", api_url);
+
+    let request = Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .build()?
+        .get(&api_url)
+        .header("Accept", "application/json");
+    let response = add_auth(request).send().await?;
+
+    let status = response.status();
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        return Err(anyhow::anyhow!("Gerrit 认证失败: HTTP {}", status));
+    }
+    if !status.is_success() {
+        return Err(anyhow::anyhow!("访问 Gerrit 失败: HTTP {}", status));
+    }
+
+    let json_text = response.text().await?;
+    let account: serde_json::Value = serde_json::from_str(json_text.trim_start_matches(")]}'\n"))?;
+    Ok(account["username"].as_str()
+        .or_else(|| account["name"].as_str())
+        .unwrap_or("未知账号")
+        .to_string())
+}
+
 // 添加认证信息到请求
 fn add_auth(mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
     // 优先使用配置文件中的认证信息
@@ -218,3 +251,105 @@ fn add_auth(mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
 
     request
 }
+
+/// 执行 git 命令，`input` 作为标准输入，返回去掉首尾空白的输出
+fn git_output(args: &[&str], input: Option<&str>) -> Result<String> {
+    let mut child = Command::new("git")
+        .args(args)
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("git {} 执行失败", args.join(" ")));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// 远程仓库地址指向 Gerrit 时返回其主机名：SSH 端口为 29418，或主机名中有一级域名为 `gerrit`
+///
+/// 只检查主机部分，仓库路径中出现 gerrit（例如 `github.com/me/gerrit-tools`）不算 Gerrit 仓库。
+pub(crate) fn gerrit_host(remote: &str) -> Option<&str> {
+    let rest = remote.split_once("://").map_or(remote, |(_, rest)| rest);
+    let host_port = rest.split('/').next()?;
+    let host_port = host_port.rsplit_once('@').map_or(host_port, |(_, host)| host);
+    let (host, port) = host_port.split_once(':').unwrap_or((host_port, ""));
+    let labelled = host.split('.').any(|label| label.eq_ignore_ascii_case("gerrit"));
+    (port == "29418" || labelled).then_some(host)
+}
+
+/// 是否需要为提交生成 Change-Id
+///
+/// 配置中设置了 `change_id` 时以配置为准；未设置时远程仓库为 Gerrit 且没有通过
+/// `git config gerrit.createChangeId false` 关闭时生成，与 Gerrit 钩子的行为一致。
+pub fn change_id_enabled(config: &Config) -> bool {
+    if let Some(enabled) = config.change_id {
+        return enabled;
+    }
+    if git_output(&["config", "--bool", "--get", "gerrit.createChangeId"], None).is_ok_and(|value| value == "false") {
+        return false;
+    }
+    git::remote_urls().iter().any(|url| gerrit_host(url).is_some())
+}
+
+/// 使用与 Gerrit commit-msg 钩子相同的算法生成 Change-Id：
+/// 对提交者信息、HEAD（没有提交时为空树）和提交信息执行 `git hash-object`，在结果前加上 `I`
+pub fn generate_change_id(message: &str) -> Result<String> {
+    let ident = git_output(&["var", "GIT_COMMITTER_IDENT"], None)?;
+    let refhash = match git_output(&["rev-parse", "--verify", "HEAD"], None) {
+        Ok(head) => head,
+        Err(_) => git_output(&["hash-object", "-t", "tree", "/dev/null"], None)?,
+    };
+    let input = format!("{}\n{}\n{}\n", ident, refhash, message.trim_end());
+    Ok(format!("I{}", git_output(&["hash-object", "--stdin"], Some(&input))?))
+}
+
+/// 提交信息的尾部字段中有 Change-Id 时返回其值，与 Gerrit 一样只读取尾部字段，正文中引用的 Change-Id 不算
+pub fn find_change_id(message: &str) -> Option<String> {
+    CommitMessage::parse(message).marks.iter()
+        .filter_map(|mark| mark.strip_prefix("Change-Id:"))
+        .map(str::trim)
+        .find(|id| id.len() == 41 && id.starts_with('I') && id[1..].chars().all(|c| c.is_ascii_hexdigit()))
+        .map(str::to_string)
+}
+
+/// 需要时为提交信息添加 Change-Id，已有 Change-Id 时保持不变
+pub fn ensure_change_id(config: &Config, message: &str) -> Result<String> {
+    if find_change_id(message).is_some() || !change_id_enabled(config) {
+        return Ok(message.to_string());
+    }
+    let change_id = generate_change_id(message)?;
+    debug!("生成 Change-Id: {}", change_id);
+    Ok(commit::insert_trailer(message, &format!("Change-Id: {}", change_id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gerrit_host() {
+        assert_eq!(gerrit_host("ssh://user@gerrit.uniontech.com:29418/dde/dde-dock"), Some("gerrit.uniontech.com"));
+        assert_eq!(gerrit_host("https://review.example.com:29418/project"), Some("review.example.com"));
+        assert_eq!(gerrit_host("https://Gerrit.example.com/a/project"), Some("Gerrit.example.com"));
+        assert_eq!(gerrit_host("git@github.com:linuxdeepin/dde-dock.git"), None);
+        assert_eq!(gerrit_host("git@github.com:me/gerrit-tools.git"), None);
+        assert_eq!(gerrit_host("https://github.com/me/gerrit"), None);
+        assert_eq!(gerrit_host("https://notgerrit.example.com/project"), None);
+    }
+
+    #[test]
+    fn test_find_change_id() {
+        let id = "I0123456789abcdef0123456789abcdef01234567";
+        assert_eq!(find_change_id(&format!("fix: crash\n\nChange-Id: {}\nSigned-off-by: A <a@b.c>", id)).as_deref(), Some(id));
+        assert_eq!(find_change_id("fix: crash\n\nChange-Id: I123"), None);
+        assert_eq!(find_change_id("fix: crash"), None);
+        // revert 等提交在正文中引用的 Change-Id 不是本提交的 Change-Id
+        let revert = format!("Revert \"fix: crash\"\n\nThis reverts the change with\nChange-Id: {}\nbecause it breaks the build.", id);
+        assert_eq!(find_change_id(&revert), None);
+    }
+}
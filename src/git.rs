@@ -1,21 +1,29 @@
 use crate::commit::CommitMessage;
 use crate::ai_service;
+use crate::lint;
 use crate::review;
 use dialoguer::Confirm;
 use log::{debug, info};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use textwrap::fill;
 
-const MAX_LINE_LENGTH: usize = 72;
+/// 提交信息每行最多字符数
+pub const MAX_LINE_LENGTH: usize = 72;
 
-fn is_auto_generated_commit(title: &str) -> bool {
+pub fn is_auto_generated_commit(title: &str) -> bool {
     let patterns = ["Merge", "Cherry-pick", "Revert"];
     patterns.iter().any(|pattern| title.starts_with(pattern))
 }
 
 pub async fn process_commit_msg(path: &Path, no_review: bool) -> anyhow::Result<()> {
     debug!("开始处理提交消息: {}", path.display());
+    review_and_translate(path, no_review).await?;
 
+    // 在审查和翻译之后检查最终的提交信息
+    lint::check_commit_msg(path)
+}
+
+async fn review_and_translate(path: &Path, no_review: bool) -> anyhow::Result<()> {
     // 检查环境变量是否设置了跳过审查
     if std::env::var("GIT_COMMIT_HELPER_SKIP_REVIEW").is_ok() {
         debug!("检测到跳过审查环境变量，跳过代码审查");
@@ -23,7 +31,7 @@ pub async fn process_commit_msg(path: &Path, no_review: bool) -> anyhow::Result<
     }
 
     let content = std::fs::read_to_string(path)?;
-    let msg = CommitMessage::parse(&content);
+    let mut msg = CommitMessage::parse(&content);
 
     // 检查是否是自动生成的提交消息
     if is_auto_generated_commit(&msg.title) {
@@ -55,53 +63,153 @@ pub async fn process_commit_msg(path: &Path, no_review: bool) -> anyhow::Result<
         return Ok(());
     }
 
-    info!("开始翻译流程，默认使用 {:?} 服务", config.default_service);
+    info!("开始翻译流程，默认使用 {} 服务", config.default_service_name());
+
+    // Log、Influence 段落不翻译，拆分出来后原样放在翻译结果之后
+    let sections = msg.take_sections();
 
     // 翻译标题
     let en_title = ai_service::translate_with_fallback(&config, &msg.title).await?;
-    let en_title = wrap_text(&en_title, MAX_LINE_LENGTH);
-    let original_title = msg.title.clone();
 
     // 翻译正文（如果有的话）
-    let (en_body, cn_body) = if let Some(body) = &msg.body {
-        let en_body = ai_service::translate_with_fallback(&config, body).await?;
-        (Some(wrap_text(&en_body, MAX_LINE_LENGTH)), Some(body.clone()))
-    } else {
-        (None, None)
+    let en_body = match &msg.body {
+        Some(body) => Some(ai_service::translate_with_fallback(&config, body).await?),
+        None => None,
     };
 
-    // 构建新的消息结构
+    let new_msg = bilingual_message(msg, sections, &en_title, en_body.as_deref());
+
+    info!("翻译完成，正在写入文件");
+    std::fs::write(path, new_msg.format())?;
+    info!("处理完成");
+    Ok(())
+}
+
+/// 组合中英双语提交信息：英文标题和正文在前，原有的中文标题和正文在后，
+/// 之后是 Log、Influence 段落，尾部字段保持不变
+fn bilingual_message(msg: CommitMessage, sections: Vec<String>, en_title: &str, en_body: Option<&str>) -> CommitMessage {
     let mut body_parts = Vec::new();
 
     // 添加英文和中文内容
     if let Some(en_body) = en_body {
-        body_parts.push(en_body);
+        body_parts.push(wrap_text(en_body, MAX_LINE_LENGTH));
         body_parts.push(String::new());
     }
 
-    body_parts.push(original_title);
+    body_parts.push(msg.title);
 
-    if let Some(body) = cn_body {
+    if let Some(body) = msg.body {
         body_parts.push(String::new());
         body_parts.push(wrap_text(&body, MAX_LINE_LENGTH));
     }
 
-    let new_msg = CommitMessage {
-        title: en_title,
+    for section in sections {
+        body_parts.push(String::new());
+        body_parts.push(section);
+    }
+
+    CommitMessage {
+        title: wrap_text(en_title, MAX_LINE_LENGTH),
         body: Some(body_parts.join("\n")),
         marks: msg.marks, // 保持原有标记不变
-    };
-
-    info!("翻译完成，正在写入文件");
-    std::fs::write(path, new_msg.format())?;
-    info!("处理完成");
-    Ok(())
+    }
 }
 
-fn contains_chinese(text: &str) -> bool {
+pub fn contains_chinese(text: &str) -> bool {
     text.chars().any(|c| c as u32 >= 0x4E00 && c as u32 <= 0x9FFF)
 }
 
 pub fn wrap_text(text: &str, max_length: usize) -> String {
     fill(text, max_length)
 }
+
+/// 提交信息中的注释符号，读取 `core.commentChar`，未设置或设置为 auto 时为 `#`
+pub fn comment_char() -> char {
+    std::process::Command::new("git")
+        .args(["config", "--get", "core.commentChar"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|value| value != "auto")
+        .and_then(|value| value.chars().next())
+        .unwrap_or('#')
+}
+
+/// 当前所在 git 工作树的根目录，不在仓库中时返回 None
+pub fn repo_root() -> Option<PathBuf> {
+    let output = std::process::Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+/// 已暂存的文件，路径相对于仓库根目录，执行失败时返回空列表
+pub fn staged_files() -> Vec<String> {
+    let Ok(output) = std::process::Command::new("git")
+        .args(["diff", "--cached", "--name-only"])
+        .output()
+    else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// 当前仓库所有远程仓库的地址，origin 排在最前面
+pub fn remote_urls() -> Vec<String> {
+    let Ok(output) = std::process::Command::new("git")
+        .args(["config", "--get-regexp", r"^remote\..*\.url$"])
+        .output()
+    else {
+        return Vec::new();
+    };
+
+    let mut remotes: Vec<(String, String)> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(key, url)| (key.to_string(), url.trim().to_string()))
+        .collect();
+    remotes.sort_by_key(|(key, _)| key != "remote.origin.url");
+    remotes.into_iter().map(|(_, url)| url).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bilingual_message_keeps_sections() {
+        // 按默认模板生成的中文提交信息
+        let content = "feat: 添加用户认证模块\n\n1. 实现基于 JWT 的认证系统\n2. 添加用户登录和注册端点\n\nLog: 新增用户登录注册功能\n\nInfluence:\n1. 测试用户注册功能\n2. 验证登录功能\n\nSigned-off-by: A <a@b.c>\n";
+        let mut msg = CommitMessage::parse_with_comment_char(content, '#');
+        let sections = msg.take_sections();
+        // 发送给翻译服务的正文不包含 Log、Influence 段落
+        assert_eq!(msg.body.as_deref(), Some("1. 实现基于 JWT 的认证系统\n2. 添加用户登录和注册端点"));
+
+        let en_body = "1. Implement JWT-based authentication\n2. Add login and registration endpoints";
+        let message = bilingual_message(msg, sections, "feat: add user authentication module", Some(en_body)).format();
+        assert_eq!(message, "feat: add user authentication module\n\n\
+            1. Implement JWT-based authentication\n2. Add login and registration endpoints\n\n\
+            feat: 添加用户认证模块\n\n\
+            1. 实现基于 JWT 的认证系统\n2. 添加用户登录和注册端点\n\n\
+            Log: 新增用户登录注册功能\n\n\
+            Influence:\n1. 测试用户注册功能\n2. 验证登录功能\n\n\
+            Signed-off-by: A <a@b.c>");
+        assert_eq!(message.matches("Log:").count(), 1);
+
+        // Log 与尾部字段在同一段落时同样只保留一份，放在尾部字段之前
+        let mut msg = CommitMessage::parse_with_comment_char("fix: 修复崩溃\n\nLog: 修复崩溃\nFixes: #42\n", '#');
+        let sections = msg.take_sections();
+        assert_eq!(msg.body, None);
+        assert_eq!(bilingual_message(msg, sections, "fix: crash", None).format(),
+            "fix: crash\n\nfix: 修复崩溃\n\nLog: 修复崩溃\n\nFixes: #42");
+    }
+}
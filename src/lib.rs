@@ -1,11 +1,26 @@
 pub mod ai_service;
 pub mod auth;
 pub mod commit;
+pub mod commit_session;
+pub mod commit_split;
+pub mod commit_style;
+pub mod commit_template;
 pub mod config;
+pub mod config_migration;
+pub mod conventional;
 pub mod debug;
+pub mod diff_budget;
+pub mod diff_filter;
+pub mod doctor;
+pub mod fixup;
 pub mod git;
 pub mod github;
 pub mod gerrit;
 pub mod install;
+pub mod lint;
+pub mod profile;
+pub mod repo_config;
 pub mod review;
+pub mod reword;
+pub mod secret;
 pub mod terminal_format;
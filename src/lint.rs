@@ -0,0 +1,420 @@
+// 提交信息检查
+// 按规则检查最终的提交信息：标题长度和标点、type/scope 格式、标题后的空行、正文换行宽度、
+// 必需的尾部字段以及语言模式要求的中英文部分。标题标点、空行和正文换行可以自动修正；
+// commit-msg 钩子按配置只提示、拒绝提交或先修正再检查，`lint` 命令用于检查文件或已有的提交。
+
+use crate::commit::{self, CommitMessage};
+use crate::commit_template::LanguageMode;
+use crate::config::{Config, LintMode};
+use crate::conventional::{self, Title};
+use crate::git;
+use crate::terminal_format::Style;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// 标题结尾不允许出现的标点
+const TITLE_PUNCTUATION: &[char] = &['.', '。', ',', '，', ';', '；', ':', '：', '!', '！', '?', '？', '、'];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// 一条违反的规则
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Violation {
+    pub rule: &'static str,
+    pub severity: Severity,
+    /// 所在行号，从 1 开始
+    pub line: usize,
+    pub message: String,
+    /// 能否通过 [`fix`] 自动修正
+    pub fixable: bool,
+}
+
+impl Violation {
+    fn error(rule: &'static str, line: usize, message: String) -> Self {
+        Self { rule, severity: Severity::Error, line, message, fixable: false }
+    }
+
+    fn warning(rule: &'static str, line: usize, message: String) -> Self {
+        Self { rule, severity: Severity::Warning, line, message, fixable: false }
+    }
+
+    fn fixable(mut self) -> Self {
+        self.fixable = true;
+        self
+    }
+}
+
+/// 检查使用的参数，通常由配置生成
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub types: Vec<String>,
+    pub max_title_length: usize,
+    pub required_trailers: Vec<String>,
+    pub language: LanguageMode,
+    /// 注释符号，对应 `core.commentChar`
+    pub comment_char: char,
+}
+
+impl Options {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            types: conventional::allowed_types(config),
+            max_title_length: config.lint.max_title_length,
+            required_trailers: config.lint.required_trailers.clone(),
+            language: LanguageMode::determine(config.only_chinese, config.only_english),
+            comment_char: git::comment_char(),
+        }
+    }
+}
+
+/// 提交信息中的有效行及其行号，去掉以 `comment_char` 开头的注释、`--verbose` 附带的 diff 以及首尾的空行
+fn message_lines(content: &str, comment_char: char) -> Vec<(usize, &str)> {
+    let scissors = format!("{} {}", comment_char, commit::SCISSORS);
+    let mut lines: Vec<(usize, &str)> = content.lines()
+        .enumerate()
+        .take_while(|(_, line)| *line != scissors)
+        .filter(|(_, line)| !line.trim_start().starts_with(comment_char))
+        .map(|(i, line)| (i + 1, line.trim_end()))
+        .skip_while(|(_, line)| line.is_empty())
+        .collect();
+    while lines.last().is_some_and(|(_, line)| line.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+/// 合并、回退和 fixup 等提交的标题由 git 生成，不做检查
+fn is_exempt(title: &str) -> bool {
+    git::is_auto_generated_commit(title)
+        || ["fixup!", "squash!", "amend!"].iter().any(|prefix| title.starts_with(prefix))
+}
+
+/// 需要检查换行宽度的正文行，尾部字段和包含链接的行不检查
+fn is_wrappable(line: &str, marks: &[String]) -> bool {
+    line.chars().count() > git::MAX_LINE_LENGTH
+        && !line.contains("://")
+        && !marks.iter().any(|mark| mark.lines().any(|mark_line| mark_line == line))
+}
+
+/// 检查提交信息，返回违反的规则
+pub fn lint(content: &str, options: &Options) -> Vec<Violation> {
+    let lines = message_lines(content, options.comment_char);
+    let Some(&(title_line, title)) = lines.first() else {
+        return vec![Violation::error("title-empty", 1, "提交信息为空".to_string())];
+    };
+    if is_exempt(title) {
+        return Vec::new();
+    }
+
+    let mut violations = Vec::new();
+    let title_length = title.chars().count();
+    if title_length > options.max_title_length {
+        violations.push(Violation::error("title-length", title_line,
+            format!("标题长度为 {} 个字符，超过 {} 个字符的限制", title_length, options.max_title_length)));
+    }
+    if let Some(punctuation) = title.chars().last().filter(|c| TITLE_PUNCTUATION.contains(c)) {
+        violations.push(Violation::error("title-punctuation", title_line,
+            format!("标题不能以标点符号 {} 结尾", punctuation)).fixable());
+    }
+
+    match Title::parse(title) {
+        None => violations.push(Violation::error("title-format", title_line,
+            "标题应为 type: 描述 或 type(scope): 描述 格式".to_string())),
+        Some(parsed) => {
+            if !options.types.contains(&parsed.commit_type) {
+                violations.push(Violation::error("title-type", title_line,
+                    format!("提交类型 {} 不在允许的类型中（{}）", parsed.commit_type, options.types.join(", "))));
+            }
+            if parsed.scope.as_deref().is_some_and(|scope| scope.is_empty() || scope.contains(char::is_whitespace)) {
+                violations.push(Violation::error("title-scope", title_line,
+                    "scope 不能为空，也不能包含空白字符".to_string()));
+            }
+            if parsed.subject.is_empty() {
+                violations.push(Violation::error("title-format", title_line, "标题缺少描述".to_string()));
+            }
+        }
+    }
+
+    if let Some(&(line, _)) = lines.get(1).filter(|(_, line)| !line.is_empty()) {
+        violations.push(Violation::error("blank-line-after-title", line,
+            "标题和正文之间需要空一行".to_string()).fixable());
+    }
+
+    let joined = lines.iter().map(|(_, line)| *line).collect::<Vec<_>>().join("\n");
+    let message = CommitMessage::parse_with_comment_char(&joined, options.comment_char);
+    for &(line, text) in &lines[1..] {
+        if is_wrappable(text, &message.marks) {
+            violations.push(Violation::warning("body-line-length", line,
+                format!("行长度为 {} 个字符，超过 {} 个字符", text.chars().count(), git::MAX_LINE_LENGTH)).fixable());
+        }
+    }
+
+    let last_line = lines.last().map_or(title_line, |(line, _)| *line);
+    for trailer in &options.required_trailers {
+        let prefix = format!("{}:", trailer);
+        if !lines[1..].iter().any(|(_, line)| line.starts_with(&prefix)) {
+            violations.push(Violation::error("required-trailer", last_line, format!("缺少 {} 字段", prefix)));
+        }
+    }
+
+    let chinese_line = lines.iter().find(|(_, line)| git::contains_chinese(line)).map(|(line, _)| *line);
+    let language = match options.language {
+        LanguageMode::EnglishOnly => chinese_line.map(|line| (line, "提交信息应只使用英文")),
+        LanguageMode::ChineseOnly if !git::contains_chinese(title) => Some((title_line, "标题应使用中文")),
+        LanguageMode::Bilingual if git::contains_chinese(title) => Some((title_line, "中英双语提交信息应先写英文部分")),
+        LanguageMode::Bilingual if chinese_line.is_none() => Some((last_line, "中英双语提交信息缺少中文部分")),
+        _ => None,
+    };
+    if let Some((line, message)) = language {
+        violations.push(Violation::warning("language", line, message.to_string()));
+    }
+    violations
+}
+
+/// 修正能自动修正的问题：去掉标题结尾的标点、在标题后补空行、按宽度重新换行过长的正文行
+///
+/// 以 `comment_char` 开头的注释和 `--verbose` 附带的 diff 会被去掉，git 提交时同样会去掉这些内容。
+pub fn fix(content: &str, comment_char: char) -> String {
+    let lines = message_lines(content, comment_char);
+    let Some(&(_, title)) = lines.first() else {
+        return content.to_string();
+    };
+    if is_exempt(title) {
+        return content.to_string();
+    }
+
+    let joined = lines.iter().map(|(_, line)| *line).collect::<Vec<_>>().join("\n");
+    let marks = CommitMessage::parse_with_comment_char(&joined, comment_char).marks;
+    let mut result = vec![title.trim_end_matches(TITLE_PUNCTUATION).trim_end().to_string()];
+    if lines.get(1).is_some_and(|(_, line)| !line.is_empty()) {
+        result.push(String::new());
+    }
+    for &(_, line) in &lines[1..] {
+        if is_wrappable(line, &marks) {
+            result.push(git::wrap_text(line, git::MAX_LINE_LENGTH));
+        } else {
+            result.push(line.to_string());
+        }
+    }
+    result.join("\n") + "\n"
+}
+
+/// 打印检查结果，每条规则一行
+pub fn print_violations(violations: &[Violation]) {
+    for violation in violations {
+        let text = format!("  第 {} 行 [{}] {}", violation.line, violation.rule, violation.message);
+        match violation.severity {
+            Severity::Error => print!("{}", Style::red(&text)),
+            Severity::Warning => print!("{}", Style::yellow(&text)),
+        }
+    }
+}
+
+fn has_errors(violations: &[Violation]) -> bool {
+    violations.iter().any(|v| v.severity == Severity::Error)
+}
+
+/// commit-msg 钩子中按配置检查提交信息，存在错误且配置为拒绝或修正时返回错误以阻止提交
+pub fn check_commit_msg(path: &Path) -> Result<()> {
+    let config = Config::load_effective()?;
+    let mode = config.lint.mode;
+    if mode == LintMode::Off {
+        return Ok(());
+    }
+
+    let options = Options::from_config(&config);
+    let content = std::fs::read_to_string(path)?;
+    let mut violations = lint(&content, &options);
+    if mode == LintMode::Fix && violations.iter().any(|v| v.fixable) {
+        let fixed = fix(&content, options.comment_char);
+        std::fs::write(path, &fixed)?;
+        println!("{}", Style::green(&format!("已自动修正 {} 处提交信息格式问题",
+            violations.iter().filter(|v| v.fixable).count())));
+        violations = lint(&fixed, &options);
+    }
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    println!("{}", Style::title("提交信息检查结果:"));
+    print_violations(&violations);
+    if mode != LintMode::Warn && has_errors(&violations) {
+        return Err(anyhow::anyhow!("提交信息未通过检查，请修改后重新提交（可使用 git commit --no-verify 跳过检查）"));
+    }
+    Ok(())
+}
+
+/// 一条提交信息的检查结果
+#[derive(Debug, Serialize)]
+pub struct Report {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    pub title: String,
+    pub violations: Vec<Violation>,
+}
+
+fn git_output(args: &[&str]) -> Result<String> {
+    let output = Command::new("git").args(args).output().context("无法执行 git 命令")?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("git {} 执行失败: {}", args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// `target` 为文件路径时检查文件内容，否则作为提交或 `<base>..<head>` 范围检查已有的提交，默认检查 HEAD
+fn collect_reports(target: Option<&str>, options: &Options) -> Result<Vec<Report>> {
+    let target = target.unwrap_or("HEAD");
+    if Path::new(target).is_file() {
+        let content = std::fs::read_to_string(target)
+            .with_context(|| format!("无法读取文件: {}", target))?;
+        return Ok(vec![Report {
+            commit: None,
+            file: Some(PathBuf::from(target)),
+            title: message_lines(&content, options.comment_char).first().map(|(_, line)| line.to_string()).unwrap_or_default(),
+            violations: lint(&content, options),
+        }]);
+    }
+
+    let revisions = if target.contains("..") {
+        git_output(&["rev-list", "--no-merges", "--reverse", target])?
+    } else {
+        git_output(&["rev-list", "--no-walk", target])?
+    };
+    revisions.lines().map(|commit| {
+        let content = git_output(&["show", "-s", "--format=%B", commit])?;
+        Ok(Report {
+            commit: Some(commit.to_string()),
+            file: None,
+            title: content.lines().next().unwrap_or_default().to_string(),
+            violations: lint(&content, options),
+        })
+    }).collect()
+}
+
+/// `lint` 命令：检查提交信息文件、单个提交或提交范围，存在错误时返回错误
+pub fn run(target: Option<&str>, json: bool) -> Result<()> {
+    let config = Config::load_effective()?;
+    let reports = collect_reports(target, &Options::from_config(&config))?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        for report in &reports {
+            let name = match (&report.commit, &report.file) {
+                (Some(commit), _) => commit.chars().take(12).collect(),
+                (None, Some(file)) => file.display().to_string(),
+                (None, None) => String::new(),
+            };
+            if report.violations.is_empty() {
+                print!("{}", Style::green(&format!("✓ {} {}", name, report.title)));
+            } else {
+                print!("{}", Style::plain(&format!("✗ {} {}", name, report.title)));
+                print_violations(&report.violations);
+            }
+        }
+    }
+
+    let failed = reports.iter().filter(|report| has_errors(&report.violations)).count();
+    if failed > 0 {
+        return Err(anyhow::anyhow!("{} 条提交信息未通过检查", failed));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> Options {
+        Options {
+            types: conventional::DEFAULT_COMMIT_TYPES.iter().map(|t| t.to_string()).collect(),
+            max_title_length: 50,
+            required_trailers: Vec::new(),
+            language: LanguageMode::EnglishOnly,
+            comment_char: '#',
+        }
+    }
+
+    fn rules(violations: &[Violation]) -> Vec<&'static str> {
+        violations.iter().map(|v| v.rule).collect()
+    }
+
+    #[test]
+    fn test_lint_valid_message() {
+        let content = "feat(dock): add tray menu\n\nShow a menu when the tray icon is clicked.\n\nLog: add tray menu\n# Please enter the commit message\n";
+        assert!(lint(content, &options()).is_empty());
+        assert!(lint("Merge branch 'dev'", &options()).is_empty());
+        assert!(lint("fixup! feat: add tray menu", &options()).is_empty());
+    }
+
+    #[test]
+    fn test_lint_title_rules() {
+        let violations = lint(&format!("Feature: {}.", "x".repeat(50)), &options());
+        assert_eq!(rules(&violations), vec!["title-length", "title-punctuation", "title-type"]);
+        assert_eq!(violations[0].line, 1);
+
+        assert_eq!(rules(&lint("update readme", &options())), vec!["title-format"]);
+        assert_eq!(rules(&lint("fix(my scope): crash", &options())), vec!["title-scope"]);
+        assert_eq!(rules(&lint("# comment\nfix:", &options())), vec!["title-punctuation", "title-format"]);
+    }
+
+    #[test]
+    fn test_lint_body_and_trailers() {
+        let long_line = "word ".repeat(20);
+        let content = format!("fix: crash on exit\nbody starts here\n{}\nSee https://example.com/{}\n", long_line.trim(), "a".repeat(80));
+        let mut options = options();
+        options.required_trailers = vec!["Log".to_string()];
+        let violations = lint(&content, &options);
+        assert_eq!(rules(&violations), vec!["blank-line-after-title", "body-line-length", "required-trailer"]);
+        assert_eq!(violations[0].line, 2);
+        assert_eq!(violations[1].severity, Severity::Warning);
+        assert_eq!(violations[2].line, 4);
+    }
+
+    #[test]
+    fn test_lint_language() {
+        let mut options = options();
+        assert_eq!(rules(&lint("fix: 修复崩溃", &options)), vec!["language"]);
+
+        options.language = LanguageMode::Bilingual;
+        assert_eq!(rules(&lint("fix: crash on exit", &options)), vec!["language"]);
+        assert!(lint("fix: crash on exit\n\nfix: 修复退出时崩溃", &options).is_empty());
+
+        options.language = LanguageMode::ChineseOnly;
+        assert!(lint("fix: 修复退出时崩溃", &options).is_empty());
+    }
+
+    #[test]
+    fn test_fix() {
+        let long_line = "word ".repeat(20);
+        let content = format!("# comment\nfix: crash on exit。\nbody\n{}\n\nLog: fixed\n", long_line.trim());
+        let fixed = fix(&content, '#');
+        assert!(fixed.starts_with("fix: crash on exit\n\nbody\nword word"));
+        assert!(fixed.ends_with("\n\nLog: fixed\n"));
+        assert!(lint(&fixed, &options()).is_empty());
+    }
+
+    #[test]
+    fn test_comment_char() {
+        // core.commentChar=; 时 git 模板中的注释以 ; 开头，以 # 开头的行属于提交信息
+        let content = "fix: crash on exit\n\n#123 was caused by a dangling pointer\n; Please enter the commit message for your changes.\n; 修改的文件：\n;\tsrc/main.rs\n; ------------------------ >8 ------------------------\n+中文\n";
+        let mut options = options();
+        options.comment_char = ';';
+        assert!(lint(content, &options).is_empty());
+        assert_eq!(fix(content, ';'), "fix: crash on exit\n\n#123 was caused by a dangling pointer\n");
+
+        // 同样的内容按 # 作为注释符号检查时，; 开头的行会被当作提交信息
+        options.comment_char = '#';
+        assert_eq!(rules(&lint(content, &options)), vec!["language"]);
+    }
+}
@@ -1,20 +1,34 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use dialoguer::{Confirm, Input};
 use log::debug;
 use std::path::PathBuf;
-use crate::config::AIService;
 mod terminal_format;
 use terminal_format::Style;
 
 mod config;
+mod config_migration;
 mod git;
 mod github;
 mod gerrit;
 mod install;
+mod lint;
 mod commit;
+mod commit_session;
+mod commit_split;
+mod commit_style;
+mod commit_template;
+mod fixup;
+mod conventional;
 mod review;
+mod reword;
+mod profile;
+mod repo_config;
+mod secret;
 mod ai_service;
+mod diff_budget;
+mod diff_filter;
+mod doctor;
 
 #[derive(Parser)]
 #[command(name = "git-commit-helper")]
@@ -30,6 +44,10 @@ struct Cli {
     /// 禁用代码审查功能
     #[arg(long, global = true)]
     no_review: bool,
+
+    /// 使用指定的配置方案
+    #[arg(long, global = true, value_name = "NAME")]
+    profile: Option<String>,
 }
 
 #[derive(Subcommand, PartialEq)]
@@ -42,9 +60,20 @@ enum Commands {
         /// 设置默认是否只使用英文提交信息，true: 仅英文，false: 中英双语
         #[arg(long = "set-only-english", help = "设置是否默认只使用英文提交信息，true: 仅英文，false: 中英双语")]
         only_english: Option<bool>,
+        /// 设置是否默认从仓库提交历史中学习提交风格
+        #[arg(long = "set-learn-style", help = "设置是否默认从仓库提交历史中学习提交风格")]
+        learn_style: Option<bool>,
+        /// 设置 commit-msg 钩子中的提交信息检查方式
+        #[arg(long = "set-lint", value_name = "MODE", help = "设置 commit-msg 钩子中的提交信息检查方式：off、warn、reject、fix")]
+        lint: Option<config::LintMode>,
+        /// 设置是否为提交生成 Gerrit Change-Id
+        #[arg(long = "set-change-id", help = "设置是否为提交生成 Gerrit Change-Id，未设置时仅在远程仓库为 Gerrit 时生成")]
+        change_id: Option<bool>,
     },
     /// 显示当前配置信息
     Show,
+    /// 诊断配置、AI 服务、Git Hook、Gerrit 认证和终端环境
+    Doctor,
     /// 将工具安装到当前 git 仓库
     Install {
         /// 指定 git 仓库路径，默认为当前目录
@@ -101,6 +130,34 @@ enum Commands {
         /// 关联的GitHub issue或PMS链接
         #[arg(long, value_delimiter = ' ', num_args = 0..)]
         issues: Vec<String>,
+        /// 从仓库提交历史中选取示例，沿用仓库的提交风格
+        #[arg(long)]
+        learn_style: bool,
+        /// 一次生成多条候选提交信息供选择（2-5 条）
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(2..=5))]
+        candidates: Option<u8>,
+        /// 将暂存的改动按逻辑拆分为多个提交
+        #[arg(long, conflicts_with_all = ["amend", "fixup"])]
+        split: bool,
+        /// 修改上一次提交，根据上一次提交和暂存的改动重新生成提交信息
+        #[arg(long, conflicts_with = "fixup")]
+        amend: bool,
+        /// 创建 fixup 提交，未指定提交时根据暂存的改动建议目标提交
+        #[arg(long, value_name = "REV", num_args = 0..=1, default_missing_value = "")]
+        fixup: Option<String>,
+    },
+    /// 根据每个提交的改动重新生成一段范围内的提交信息
+    Reword {
+        /// 要改写的提交范围，格式为 <base>..<head>，head 省略时为当前分支
+        range: String,
+    },
+    /// 检查提交信息是否符合规范
+    Lint {
+        /// 提交信息文件、提交或 <base>..<head> 范围，默认为 HEAD
+        target: Option<String>,
+        /// 以 JSON 格式输出检查结果
+        #[arg(long)]
+        json: bool,
     },
     /// 管理 AI 代码审查功能
     #[command(name = "ai-review")]
@@ -117,17 +174,79 @@ enum Commands {
     },
 }
 
+/// 非交互式添加或修改 AI 服务时使用的参数
+#[derive(Args, PartialEq)]
+struct ServiceArgs {
+    /// API Key，也可以填写 env:/cmd:/keyring: 引用
+    #[arg(long, group = "api_key")]
+    key: Option<String>,
+    /// 从指定环境变量读取 API Key（保存为 env:变量名 引用）
+    #[arg(long, group = "api_key", value_name = "VAR")]
+    key_env: Option<String>,
+    /// API 地址
+    #[arg(long)]
+    endpoint: Option<String>,
+    /// 模型名称
+    #[arg(long)]
+    model: Option<String>,
+    /// 服务别名，可代替服务 ID 使用
+    #[arg(long)]
+    alias: Option<String>,
+    /// 认证方式：bearer、api-key、none
+    #[arg(long)]
+    auth: Option<config::AuthScheme>,
+    /// 附加的 HTTP 头，格式为 "Name: Value"，可重复指定
+    #[arg(long = "header", value_name = "HEADER")]
+    headers: Vec<String>,
+}
+
+impl ServiceArgs {
+    fn into_options(self) -> config::ServiceOptions {
+        config::ServiceOptions {
+            api_key: self.key.or(self.key_env.map(|var| format!("env:{}", var))),
+            endpoint: self.endpoint,
+            model: self.model,
+            alias: self.alias,
+            auth_scheme: self.auth,
+            headers: self.headers,
+        }
+    }
+}
+
 #[derive(Subcommand, PartialEq)]
 enum ServiceCommands {
-    /// 添加新的 AI 服务
-    Add,
-    /// 修改已有的 AI 服务配置
-    Edit,
+    /// 添加新的 AI 服务，指定 --service 时不进行交互
+    Add {
+        /// 服务类型：deepseek、openai、claude、copilot、gemini、grok、qwen、custom、ollama、llamacpp
+        #[arg(long)]
+        service: Option<config::AIService>,
+        #[command(flatten)]
+        options: ServiceArgs,
+        /// 设为默认服务
+        #[arg(long)]
+        default: bool,
+        /// 保存前不测试服务
+        #[arg(long)]
+        no_test: bool,
+    },
+    /// 修改已有的 AI 服务配置，同时指定服务和参数时不进行交互
+    Edit {
+        /// 服务 ID 或别名
+        target: Option<String>,
+        #[command(flatten)]
+        options: ServiceArgs,
+    },
     /// 删除 AI 服务
-    Remove,
+    Remove {
+        /// 服务 ID 或别名，不指定时交互选择
+        target: Option<String>,
+    },
     /// 设置默认 AI 服务
     #[command(name = "set-default")]
-    SetDefault,
+    SetDefault {
+        /// 服务 ID 或别名，不指定时交互选择
+        target: Option<String>,
+    },
     /// 设置网络请求超时时间
     #[command(name = "set-timeout")]
     SetTimeout {
@@ -135,6 +254,22 @@ enum ServiceCommands {
         #[arg(short, long)]
         seconds: u64,
     },
+    /// 设置网络请求失败时的重试策略
+    #[command(name = "set-retry")]
+    SetRetry {
+        /// 最多请求次数（包含第一次请求），设置为 1 表示不重试
+        #[arg(short, long)]
+        max_attempts: Option<u32>,
+        /// 第一次重试前的等待时间（单位：毫秒），之后每次翻倍
+        #[arg(short, long)]
+        backoff_ms: Option<u64>,
+        /// 单次等待时间上限（单位：毫秒）
+        #[arg(long)]
+        max_backoff_ms: Option<u64>,
+        /// 自动等待服务端 Retry-After 的上限（单位：秒），超过时询问是否等待
+        #[arg(long)]
+        max_retry_after_secs: Option<u64>,
+    },
     /// 列出所有 AI 服务
     List,
     /// 测试指定的 AI 服务
@@ -160,6 +295,11 @@ async fn main() -> Result<()> {
     debug!("正在启动 git-commit-helper...");
     let cli = Cli::parse();
 
+    // 通过环境变量传递，git commit 触发的 hook 子进程也会使用同一个配置方案
+    if let Some(profile) = &cli.profile {
+        std::env::set_var(profile::PROFILE_ENV, profile);
+    }
+
     // 检查当前命令是否需要 Gerrit 认证
     let needs_gerrit = match &cli.input {
         Some(input) if input.contains("/+/") => true,
@@ -167,10 +307,11 @@ async fn main() -> Result<()> {
     };
 
     // 加载配置文件
-    let _config = match config::Config::load() {
+    let _config = match config::Config::load_global() {
         Ok(mut config) => {
             // 如果是 Gerrit URL 且没有配置认证信息，提示用户配置
-            if needs_gerrit && config.gerrit.is_none() {
+            let has_gerrit = config::Config::load_effective().is_ok_and(|c| c.gerrit.is_some());
+            if needs_gerrit && !has_gerrit {
                 println!("检测到 Gerrit URL，但未配置 Gerrit 认证信息。");
                 if Confirm::new()
                     .with_prompt("是否现在配置 Gerrit 认证？")
@@ -183,8 +324,13 @@ async fn main() -> Result<()> {
             config
         }
         Err(e) => {
-            // 检查当前命令是否为 config，如果是则允许继续
-            if let Some(Commands::Config { .. }) = cli.command {
+            // 检查当前命令是否为 config 或通过参数添加服务，如果是则允许继续
+            if let Some(Commands::Config { .. } | Commands::Doctor) = cli.command {
+                config::Config::new()
+            } else if config::Config::config_path()?.exists() {
+                // 配置文件存在但无法加载（格式错误或版本过新），不能引导重新配置覆盖原文件
+                return Err(e);
+            } else if let Some(Commands::AI { command: ServiceCommands::Add { service: Some(_), .. } }) = cli.command {
                 config::Config::new()
             } else {
                 println!("{}", Style::red(&format!("错误: {}", e)));
@@ -202,9 +348,9 @@ async fn main() -> Result<()> {
     };
 
     match cli.command {
-        Some(Commands::Config { only_chinese, only_english }) => {
+        Some(Commands::Config { only_chinese, only_english, learn_style, lint, change_id }) => {
             if let Some(only_chinese) = only_chinese {
-                let mut config = config::Config::load().unwrap_or_else(|_| config::Config::new());
+                let mut config = config::Config::load_global_or_new()?;
                 config.only_chinese = only_chinese;
                 if only_chinese {
                     config.only_english = false; // 如果设置为仅中文，则清除仅英文标志
@@ -220,7 +366,7 @@ async fn main() -> Result<()> {
                 println!("{}", Style::green(&format!("已将默认提交信息语言设置为: {}", language_mode)));
                 Ok(())
             } else if let Some(only_english) = only_english {
-                let mut config = config::Config::load().unwrap_or_else(|_| config::Config::new());
+                let mut config = config::Config::load_global_or_new()?;
                 config.only_english = only_english;
                 if only_english {
                     config.only_chinese = false; // 如果设置为仅英文，则清除仅中文标志
@@ -235,85 +381,216 @@ async fn main() -> Result<()> {
                 };
                 println!("{}", Style::green(&format!("已将默认提交信息语言设置为: {}", language_mode)));
                 Ok(())
+            } else if let Some(learn_style) = learn_style {
+                let mut config = config::Config::load_global_or_new()?;
+                config.learn_style = learn_style;
+                config.save()?;
+                println!("{}", Style::green(&format!("已{}学习仓库提交风格", if learn_style { "启用" } else { "禁用" })));
+                Ok(())
+            } else if let Some(mode) = lint {
+                let mut config = config::Config::load_global_or_new()?;
+                config.lint.mode = mode;
+                config.save()?;
+                println!("{}", Style::green(&format!("已将提交信息检查方式设置为: {}", mode.label())));
+                Ok(())
+            } else if let Some(change_id) = change_id {
+                let mut config = config::Config::load_global_or_new()?;
+                config.change_id = Some(change_id);
+                config.save()?;
+                println!("{}", Style::green(&format!("已{}生成 Gerrit Change-Id", if change_id { "启用" } else { "禁用" })));
+                Ok(())
             } else {
                 config::Config::interactive_config().await?;
                 Ok(())
             }
         }
         Some(Commands::Show) => {
-            let config = config::Config::load()?;
+            let config = config::Config::load_effective()?;
             let config_path = config::Config::config_path()?;
             println!("{}", Style::title(&format!("配置文件路径: {}", config_path.display())));
+            if let Some(profile) = &config.origin.profile {
+                println!("{}", Style::title(&format!("当前配置方案: {}（{}）", profile.name, profile.reason)));
+            }
+            if let Some(repo_path) = &config.origin.repo_path {
+                println!("{}", Style::title(&format!("仓库配置路径: {}", repo_path.display())));
+            }
             println!("{}", Style::separator());
             println!("{}", Style::title("当前配置内容:"));
-            println!("{}", Style::plain(&format!("默认 AI 服务: {:?}", config.default_service)));
+            let origin = &config.origin;
+            let setting = |label: &str, value: String, keys: &[&str]| {
+                println!("{}", Style::plain(&format!("{}: {}  [{}]", label, value, origin.source_of(keys))));
+            };
+
+            setting("默认 AI 服务", config.default_service_name(), &["default_service_id"]);
+            setting("AI 代码审查", if config.ai_review { "已启用" } else { "已禁用" }.to_string(), &["ai_review"]);
+            let language_mode = if config.only_chinese {
+                "仅中文"
+            } else if config.only_english {
+                "仅英文"
+            } else {
+                "中英双语"
+            };
+            setting("提交信息语言", language_mode.to_string(), &["only_chinese", "only_english"]);
+            setting("请求超时", format!("{} 秒", config.timeout_seconds), &["timeout_seconds"]);
+            setting("最大 token", config.max_tokens.to_string(), &["max_tokens"]);
+            setting("失败重试", format!("最多请求 {} 次，初始等待 {} 毫秒，最长等待 {} 毫秒，Retry-After 最多自动等待 {} 秒",
+                config.retry.max_attempts, config.retry.initial_backoff_ms, config.retry.max_backoff_ms,
+                config.retry.max_retry_after_secs), &["retry"]);
+            let diff_ignore = if config.diff_ignore.is_empty() {
+                "(仅默认规则)".to_string()
+            } else {
+                config.diff_ignore.join(", ")
+            };
+            setting("Diff 过滤规则", diff_ignore, &["diff_ignore"]);
+            let review_prompt = config.review_prompt.as_deref()
+                .map(|path| config::Config::prompt_path(path).display().to_string())
+                .unwrap_or_else(|| "(默认)".to_string());
+            setting("审查提示词", review_prompt, &["review_prompt"]);
+            let commit_prompt = config.commit_prompt.as_deref()
+                .map(|path| config::Config::prompt_path(path).display().to_string())
+                .unwrap_or_else(|| "(默认)".to_string());
+            setting("提交信息模板", commit_prompt, &["commit_prompt"]);
+            setting("学习仓库提交风格", if config.learn_style { "已启用" } else { "已禁用" }.to_string(), &["learn_style"]);
+            setting("提交类型", conventional::allowed_types(&config).join(", "), &["commit_types"]);
+            let scopes = if config.scopes.is_empty() {
+                "(未配置)".to_string()
+            } else {
+                config.scopes.iter().map(|(pattern, scope)| format!("{} → {}", pattern, scope)).collect::<Vec<_>>().join(", ")
+            };
+            setting("Scope 映射", scopes, &["scopes"]);
+            let mut lint = format!("{}，标题最多 {} 个字符", config.lint.mode.label(), config.lint.max_title_length);
+            if !config.lint.required_trailers.is_empty() {
+                lint.push_str(&format!("，必需字段: {}", config.lint.required_trailers.join(", ")));
+            }
+            setting("提交信息检查", lint, &["lint"]);
+            let change_id = match config.change_id {
+                Some(true) => "已启用",
+                Some(false) => "已禁用",
+                None => "自动（远程仓库为 Gerrit 时生成）",
+            };
+            setting("Gerrit Change-Id", change_id.to_string(), &["change_id"]);
             println!("{}", Style::title("已配置的服务:"));
             for (i, service) in config.services.iter().enumerate() {
-                println!("{}", Style::plain(&format!("{}. {:?}", i + 1, service.service)));
-                println!("{}", Style::plain(&format!("   API Key: {}", service.api_key)));
+                println!("{}", Style::plain(&format!("{}. {}", i + 1, service.display_name())));
+                println!("{}", Style::plain(&format!("   API Key: {}", secret::redact(&service.api_key))));
                 if let Some(endpoint) = &service.api_endpoint {
                     println!("{}", Style::plain(&format!("   API Endpoint: {}", endpoint)));
                 }
                 if let Some(model) = &service.model {
                     println!("{}", Style::plain(&format!("   Model: {}", model)));
                 }
+                if service.service == config::AIService::Custom {
+                    println!("{}", Style::plain(&format!("   Auth: {:?}", service.auth_scheme)));
+                }
+                for (key, value) in &service.extra_headers {
+                    println!("{}", Style::plain(&format!("   Header: {}: {}", key, secret::redact(value))));
+                }
+            }
+            if !config.profiles.is_empty() {
+                println!("{}", Style::title("已配置的方案:"));
+                for (name, profile) in &config.profiles {
+                    let marker = if config.origin.profile.as_ref().is_some_and(|p| p.name == *name) { " (当前)" } else { "" };
+                    println!("{}", Style::plain(&format!("- {}{}", name, marker)));
+                    if !profile.remotes.is_empty() {
+                        println!("{}", Style::plain(&format!("   远程仓库: {}", profile.remotes.join(", "))));
+                    }
+                }
+            }
+            if let Some(gerrit) = &config.gerrit {
+                println!("{}", Style::title(&format!("Gerrit 认证:  [{}]", origin.source("gerrit"))));
+                if let Some(username) = &gerrit.username {
+                    println!("{}", Style::plain(&format!("   用户名: {}", username)));
+                }
+                if let Some(password) = &gerrit.password {
+                    println!("{}", Style::plain(&format!("   密码: {}", secret::redact(password))));
+                }
+                if let Some(token) = &gerrit.token {
+                    println!("{}", Style::plain(&format!("   Token: {}", secret::redact(token))));
+                }
             }
             Ok(())
         }
+        Some(Commands::Doctor) => doctor::run().await,
         Some(Commands::Install { path, force }) => {
             install::install_git_hook(path, force)?;
             Ok(())
         }
         Some(Commands::AI { command }) => {
-            let mut config = config::Config::load().unwrap_or_else(|_| config::Config::new());
+            let mut config = config::Config::load_global_or_new()?;
             match command {
-                ServiceCommands::Add => {
-                    let selected_service = {
-                        println!("\n请选择要添加的 AI 服务:");
-                        println!("1) DeepSeek");
-                        println!("2) OpenAI");
-                        println!("3) Claude");
-                        println!("4) Copilot");
-                        println!("5) Gemini");
-                        println!("6) Grok");
-                        println!("7) Qwen");
-
-                        let selection = Input::<String>::new()
-                            .with_prompt("请输入对应的数字")
-                            .report(true)
-                            .validate_with(|input: &String| -> Result<(), &str> {
-                                match input.parse::<usize>() {
-                                    Ok(n) if n >= 1 && n <= 7 => Ok(()),
-                                    _ => Err("请输入 1-7 之间的数字")
-                                }
-                            })
-                            .interact()?
-                            .parse::<usize>()?;
-
-                        match selection {
-                            1 => AIService::DeepSeek,
-                            2 => AIService::OpenAI,
-                            3 => AIService::Claude,
-                            4 => AIService::Copilot,
-                            5 => AIService::Gemini,
-                            6 => AIService::Grok,
-                            7 => AIService::Qwen,
-                            _ => unreachable!(),
+                ServiceCommands::Add { service, options, default, no_test } => {
+                    match service {
+                        Some(service) => config.add_service_with_options(service, options.into_options(), default, !no_test).await,
+                        None => {
+                            let selected_service = config::select_service_type()?;
+                            config.add_service(selected_service).await
                         }
-                    };
-                    config.add_service(selected_service).await
+                    }
+                }
+                ServiceCommands::Edit { target, options } => {
+                    let options = options.into_options();
+                    match target {
+                        Some(target) => {
+                            let index = config.find_service(&target)?;
+                            if options.is_empty() {
+                                config.edit_service_at(index).await
+                            } else {
+                                config.edit_service_with_options(index, options)
+                            }
+                        }
+                        None if options.is_empty() => config.edit_service().await,
+                        None => Err(anyhow::anyhow!("请指定要修改的服务 ID 或别名")),
+                    }
                 }
-                ServiceCommands::Edit => config.edit_service().await,
-                ServiceCommands::Remove => config.remove_service().await,
-                ServiceCommands::SetDefault => config.set_default_service().await,
+                ServiceCommands::Remove { target } => match target {
+                    Some(target) => {
+                        let index = config.find_service(&target)?;
+                        config.remove_service_at(index)?;
+                        println!("{}", Style::green(&format!("已删除 AI 服务: {}", target)));
+                        Ok(())
+                    }
+                    None => config.remove_service().await,
+                },
+                ServiceCommands::SetDefault { target } => match target {
+                    Some(target) => {
+                        let index = config.find_service(&target)?;
+                        config.set_default_service_at(index)?;
+                        println!("{}", Style::green(&format!("已将默认 AI 服务设置为: {}", config.services[index].display_name())));
+                        Ok(())
+                    }
+                    None => config.set_default_service().await,
+                },
                 ServiceCommands::SetTimeout { seconds } => {
                     config.timeout_seconds = seconds;
                     config.save()?;
                     println!("{}", Style::green(&format!("已将网络请求超时时间设置为 {} 秒", seconds)));
                     Ok(())
                 }
+                ServiceCommands::SetRetry { max_attempts, backoff_ms, max_backoff_ms, max_retry_after_secs } => {
+                    if max_attempts == Some(0) {
+                        return Err(anyhow::anyhow!("最多请求次数不能小于 1"));
+                    }
+                    if let Some(max_attempts) = max_attempts {
+                        config.retry.max_attempts = max_attempts;
+                    }
+                    if let Some(backoff_ms) = backoff_ms {
+                        config.retry.initial_backoff_ms = backoff_ms;
+                    }
+                    if let Some(max_backoff_ms) = max_backoff_ms {
+                        config.retry.max_backoff_ms = max_backoff_ms;
+                    }
+                    if let Some(max_retry_after_secs) = max_retry_after_secs {
+                        config.retry.max_retry_after_secs = max_retry_after_secs;
+                    }
+                    config.save()?;
+                    println!("{}", Style::green(&format!(
+                        "已将重试策略设置为：最多请求 {} 次，初始等待 {} 毫秒，最长等待 {} 毫秒，Retry-After 最多自动等待 {} 秒",
+                        config.retry.max_attempts, config.retry.initial_backoff_ms, config.retry.max_backoff_ms,
+                        config.retry.max_retry_after_secs)));
+                    Ok(())
+                }
                 ServiceCommands::List => {
-                    let config = config::Config::load()?;
+                    let config = config::Config::load_effective()?;
                     println!("{}", Style::title("已配置的 AI 服务列表:"));
                     if config.services.is_empty() {
                         println!("{}", Style::plain("  暂无配置的 AI 服务"));
@@ -322,7 +599,7 @@ async fn main() -> Result<()> {
 
                     for (i, service) in config.services.iter().enumerate() {
                         let default_marker = if config.is_default_service(service) { " (当前默认)" } else { "" };
-                        print!("{}", Style::plain(&format!("[{}] {:?}{}", i + 1, service.service, default_marker)));
+                        print!("{}", Style::plain(&format!("[{}] {}{}", i + 1, service.display_name(), default_marker)));
 
                         // 显示URL信息
                         match &service.api_endpoint {
@@ -334,6 +611,8 @@ async fn main() -> Result<()> {
                         if let Some(model) = &service.model {
                             println!("{}", Style::plain(&format!("    模型: {}", model)));
                         }
+                        println!("{}", Style::plain(&format!("    ID: {}", service.id)));
+                        println!("{}", Style::plain(&format!("    API Key: {}", secret::redact(&service.api_key))));
                     }
                     Ok(())
                 }
@@ -348,7 +627,7 @@ async fn main() -> Result<()> {
                     // 显示所有服务的详细信息
                     for (i, service) in config.services.iter().enumerate() {
                         let default_marker = if config.is_default_service(service) { " (当前默认)" } else { "" };
-                        print!("{}", Style::plain(&format!("[{}] {:?}{}", i + 1, service.service, default_marker)));
+                        print!("{}", Style::plain(&format!("[{}] {}{}", i + 1, service.display_name(), default_marker)));
 
                         // 显示URL信息
                         match &service.api_endpoint {
@@ -376,7 +655,7 @@ async fn main() -> Result<()> {
                         .parse::<usize>()?;
 
                     let service = &config.services[selection - 1];
-                    println!("{}", Style::title(&format!("正在测试 {:?} 服务...", service.service)));
+                    println!("{}", Style::title(&format!("正在测试 {} 服务...", service.display_name())));
 
                     let translator = ai_service::create_translator_for_service(service).await?;
                     let test_text = text.unwrap_or_else(|| "这是一个测试消息，用于验证翻译功能是否正常。".to_string());
@@ -432,7 +711,7 @@ async fn main() -> Result<()> {
             };
 
             let service = config.get_default_service()?;
-            println!("{}", Style::title(&format!("正在使用 {:?} 服务进行翻译...", service.service)));
+            println!("{}", Style::title(&format!("正在使用 {} 服务进行翻译...", service.display_name())));
 
             let translator = ai_service::create_translator_for_service(service).await?;
             match translator.translate(&content).await {
@@ -446,17 +725,39 @@ async fn main() -> Result<()> {
                 Err(e) => Err(e)
             }
         }
-        Some(Commands::Commit { r#type, message, all, no_translate, only_chinese, only_english, no_influence, no_log, issues }) => {
+        Some(Commands::Commit { r#type, message, all, no_translate, only_chinese, only_english, no_influence, no_log, issues, learn_style, candidates, split, amend, fixup }) => {
             let issues_str = if issues.is_empty() {
                 None
             } else {
                 Some(issues.join(" "))
             };
-            commit::generate_commit_message(r#type, message, all, cli.no_review, no_translate, only_chinese, only_english, no_influence, no_log, issues_str).await
+            commit::generate_commit_message(commit::CommitOptions {
+                commit_type: r#type,
+                message,
+                auto_add: all,
+                no_review: cli.no_review,
+                no_translate,
+                only_chinese,
+                only_english,
+                no_influence,
+                no_log,
+                issues: issues_str,
+                learn_style,
+                candidates: candidates.map(usize::from),
+                split,
+                amend,
+                fixup,
+            }).await
+        }
+        Some(Commands::Reword { range }) => {
+            reword::run(&range).await
+        }
+        Some(Commands::Lint { target, json }) => {
+            lint::run(target.as_deref(), json)
         }
         Some(Commands::AIReview { enable, disable, status }) => {
-            let mut config = config::Config::load()?;
             if status {
+                let config = config::Config::load()?;
                 let language_mode = if config.only_chinese {
                     "仅中文"
                 } else if config.only_english {
@@ -468,6 +769,7 @@ async fn main() -> Result<()> {
                 println!("{}", Style::plain(&format!("默认提交信息语言: {}", language_mode)));
                 return Ok(());
             }
+            let mut config = config::Config::load_global()?;
             if enable {
                 config.ai_review = true;
                 config.save()?;
@@ -0,0 +1,240 @@
+// 配置方案（profile）
+// 在全局配置的 profiles 中为不同场景（如公司 Gerrit 与开源 GitHub）各保存一组设置，
+// 通过 --profile 参数、GIT_COMMIT_HELPER_PROFILE 环境变量或远程仓库地址选择，叠加在全局配置之上。
+
+use crate::config::{Config, GerritConfig};
+use crate::git;
+use anyhow::Result;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// 指定配置方案的环境变量，`--profile` 参数也通过它传递给 git hook 等子进程
+pub const PROFILE_ENV: &str = "GIT_COMMIT_HELPER_PROFILE";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Profile {
+    /// 使用的 AI 服务，填写服务 ID 或别名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_service: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub only_chinese: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub only_english: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ai_review: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gerrit: Option<GerritConfig>,
+    /// 代码审查提示词文件，相对路径基于配置文件所在目录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review_prompt: Option<PathBuf>,
+    /// 提交信息模板文件，相对路径基于配置文件所在目录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_prompt: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub learn_style: Option<bool>,
+    /// 允许的提交类型，替换全局配置中的列表
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_types: Option<Vec<String>>,
+    /// 自动选择该方案的远程仓库地址，支持 `*` 通配符，例如 `github.com/linuxdeepin/*`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remotes: Vec<String>,
+}
+
+/// 当前生效的配置方案及其选择原因
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveProfile {
+    pub name: String,
+    pub reason: String,
+}
+
+/// 依次根据环境变量（含 `--profile` 参数）和远程仓库地址选择配置方案
+pub fn select(config: &Config) -> Result<Option<ActiveProfile>> {
+    if let Some(name) = std::env::var(PROFILE_ENV).ok().filter(|name| !name.is_empty()) {
+        if !config.profiles.contains_key(&name) {
+            return Err(anyhow::anyhow!("配置文件中不存在名为 {} 的配置方案，可用的方案: {}",
+                name, available(config)));
+        }
+        return Ok(Some(ActiveProfile { name, reason: format!("由 --profile 或 {} 指定", PROFILE_ENV) }));
+    }
+
+    if config.profiles.values().all(|profile| profile.remotes.is_empty()) {
+        return Ok(None);
+    }
+    Ok(select_by_remote(config, &git::remote_urls()))
+}
+
+fn select_by_remote(config: &Config, urls: &[String]) -> Option<ActiveProfile> {
+    for url in urls {
+        for (name, profile) in &config.profiles {
+            if let Some(pattern) = profile.remotes.iter().find(|pattern| remote_matches(pattern, url)) {
+                debug!("远程仓库 {} 匹配配置方案 {} 的规则 {}", url, name, pattern);
+                return Some(ActiveProfile { name: name.clone(), reason: format!("匹配远程仓库 {}", url) });
+            }
+        }
+    }
+    None
+}
+
+fn available(config: &Config) -> String {
+    if config.profiles.is_empty() {
+        "(无)".to_string()
+    } else {
+        config.profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+    }
+}
+
+/// 去掉协议和用户名，统一 `git@host:path` 与 `https://host/path` 两种写法
+fn normalize_remote(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/').trim_end_matches(".git");
+    let (rest, scp_like) = match url.split_once("://") {
+        Some((_, rest)) => (rest, false),
+        None => (url, true),
+    };
+    let rest = rest.split_once('@').map_or(rest, |(_, host_and_path)| host_and_path);
+    if scp_like {
+        rest.replacen(':', "/", 1)
+    } else {
+        rest.to_string()
+    }
+}
+
+/// 远程地址以规则开头时匹配，规则中的 `*` 匹配任意字符
+///
+/// 规则必须从主机名开始匹配，且只能在路径或端口的分隔处结束，
+/// 避免 `github.com/*` 匹配到 `mirror.example.com/github.com/x` 这类地址。
+fn remote_matches(pattern: &str, url: &str) -> bool {
+    let pattern = normalize_remote(pattern);
+    let regex = pattern.split('*').map(regex::escape).collect::<Vec<_>>().join(".*");
+    regex::Regex::new(&format!("^{}(?:[:/].*)?$", regex))
+        .is_ok_and(|regex| regex.is_match(&normalize_remote(url)))
+}
+
+impl Profile {
+    /// 将配置方案叠加到全局配置上，并记录被覆盖的配置项
+    pub fn apply(self, config: &mut Config, active: ActiveProfile) -> Result<()> {
+        if let Some(name) = &self.default_service {
+            let index = config.find_service(name)
+                .map_err(|e| e.context(format!("配置方案 {} 指定的 AI 服务无效", active.name)))?;
+            config.default_service_id = config.services[index].id.clone();
+            config.origin.mark_profile("default_service_id");
+        }
+
+        // 两个语言选项互斥，设置其中一个时同时覆盖另一个
+        if let Some(only_chinese) = self.only_chinese {
+            config.only_chinese = only_chinese;
+            if only_chinese {
+                config.only_english = false;
+                config.origin.mark_profile("only_english");
+            }
+            config.origin.mark_profile("only_chinese");
+        }
+        if let Some(only_english) = self.only_english {
+            config.only_english = only_english;
+            if only_english {
+                config.only_chinese = false;
+                config.origin.mark_profile("only_chinese");
+            }
+            config.origin.mark_profile("only_english");
+        }
+
+        if let Some(ai_review) = self.ai_review {
+            config.ai_review = ai_review;
+            config.origin.mark_profile("ai_review");
+        }
+        if let Some(gerrit) = self.gerrit {
+            config.gerrit = Some(gerrit);
+            config.origin.mark_profile("gerrit");
+        }
+        if let Some(review_prompt) = self.review_prompt {
+            config.review_prompt = Some(review_prompt);
+            config.origin.mark_profile("review_prompt");
+        }
+        if let Some(commit_prompt) = self.commit_prompt {
+            config.commit_prompt = Some(commit_prompt);
+            config.origin.mark_profile("commit_prompt");
+        }
+        if let Some(learn_style) = self.learn_style {
+            config.learn_style = learn_style;
+            config.origin.mark_profile("learn_style");
+        }
+        if let Some(commit_types) = self.commit_types {
+            config.commit_types = commit_types;
+            config.origin.mark_profile("commit_types");
+        }
+
+        config.origin.profile = Some(active);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AIService, AIServiceConfig, ConfigSource};
+
+    fn config() -> Config {
+        let mut config = Config::new();
+        for (id, name) in [("service_1", "Internal"), ("service_2", "Public")] {
+            let mut service = AIServiceConfig::new(AIService::OpenAI);
+            service.id = id.to_string();
+            service.name = Some(name.to_string());
+            config.services.push(service);
+        }
+        config.default_service_id = "service_1".to_string();
+        config.only_chinese = true;
+
+        config.profiles.insert("work".to_string(), Profile {
+            remotes: vec!["gerrit.example.com".to_string()],
+            ..Default::default()
+        });
+        config.profiles.insert("oss".to_string(), Profile {
+            default_service: Some("public".to_string()),
+            only_english: Some(true),
+            ai_review: Some(false),
+            remotes: vec!["https://github.com/linuxdeepin/*".to_string()],
+            ..Default::default()
+        });
+        config
+    }
+
+    #[test]
+    fn test_remote_matches() {
+        assert!(remote_matches("github.com/linuxdeepin/*", "git@github.com:linuxdeepin/dde-shell.git"));
+        assert!(remote_matches("https://github.com/linuxdeepin/*", "ssh://git@github.com/linuxdeepin/dtk"));
+        assert!(remote_matches("gerrit.example.com", "ssh://me@gerrit.example.com:29418/project"));
+        assert!(!remote_matches("github.com/linuxdeepin/*", "https://github.com/zccrs/git-commit-helper"));
+        // 规则只匹配地址的开头，并在路径或端口的分隔处结束
+        assert!(!remote_matches("github.com/*", "https://git.corp.example/mirrors/github.com/x"));
+        assert!(!remote_matches("gerrit.example.com", "https://gerrit.example.com.evil.org/project"));
+        assert!(!remote_matches("github.com/linuxdeepin", "https://github.com/linuxdeepin-fork/dtk"));
+    }
+
+    #[test]
+    fn test_select_by_remote() {
+        let config = config();
+        let urls = ["https://github.com/zccrs/fork".to_string(), "git@github.com:linuxdeepin/dtk.git".to_string()];
+        let active = select_by_remote(&config, &urls).unwrap();
+        assert_eq!(active.name, "oss");
+        assert!(active.reason.contains("linuxdeepin/dtk"));
+
+        assert!(select_by_remote(&config, &["https://gitlab.com/a/b".to_string()]).is_none());
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut config = config();
+        let profile = config.profiles["oss"].clone();
+        let active = ActiveProfile { name: "oss".to_string(), reason: String::new() };
+        profile.apply(&mut config, active.clone()).unwrap();
+
+        assert_eq!(config.default_service_id, "service_2");
+        assert!(config.only_english);
+        assert!(!config.only_chinese);
+        assert!(!config.ai_review);
+        assert_eq!(config.origin.source("only_chinese"), ConfigSource::Profile);
+        assert_eq!(config.origin.source("timeout_seconds"), ConfigSource::Default);
+        assert_eq!(config.origin.profile, Some(active));
+        assert!(config.save().is_err());
+    }
+}
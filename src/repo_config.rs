@@ -0,0 +1,282 @@
+// 仓库级配置
+// 仓库根目录下的 .git-commit-helper.toml 会覆盖全局配置中的同名设置，
+// 便于不同仓库使用不同的提交语言、审查提示词和 AI 服务。该文件通常会提交到版本库，因此不允许保存密钥。
+
+use crate::config::{Config, LintConfig};
+use crate::git;
+use anyhow::{Context, Result};
+use log::warn;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+/// 仓库配置文件名，放在工作树根目录
+pub const REPO_CONFIG_FILE: &str = ".git-commit-helper.toml";
+
+/// 不允许出现在仓库配置中的字段
+const SECRET_KEYS: &[&str] = &["api_key", "services", "gerrit", "password", "token"];
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RepoConfig {
+    /// 使用的 AI 服务，填写全局配置中的服务 ID 或名称
+    pub default_service: Option<String>,
+    pub only_chinese: Option<bool>,
+    pub only_english: Option<bool>,
+    pub ai_review: Option<bool>,
+    pub timeout_seconds: Option<u64>,
+    pub max_tokens: Option<u64>,
+    /// 代码审查提示词文件，相对路径基于仓库根目录
+    pub review_prompt: Option<PathBuf>,
+    /// 提交信息模板文件，相对路径基于仓库根目录
+    pub commit_prompt: Option<PathBuf>,
+    /// 是否从本仓库的提交历史中选取风格示例
+    pub learn_style: Option<bool>,
+    /// 允许的提交类型，替换全局配置中的列表
+    pub commit_types: Option<Vec<String>>,
+    /// 文件路径规则到 scope 的映射，与全局配置合并，同一规则以仓库配置为准
+    #[serde(default)]
+    pub scopes: BTreeMap<String, String>,
+    /// 提交信息检查规则，整体替换全局配置
+    pub lint: Option<LintConfig>,
+    /// 是否为提交生成 Gerrit Change-Id
+    pub change_id: Option<bool>,
+    /// 追加在全局规则之后的 diff 过滤规则
+    #[serde(default)]
+    pub diff_ignore: Vec<String>,
+}
+
+/// 查找当前仓库的配置文件
+pub fn find() -> Option<PathBuf> {
+    git::repo_root()
+        .map(|root| root.join(REPO_CONFIG_FILE))
+        .filter(|path| path.exists())
+}
+
+/// 递归查找 TOML 中的敏感字段，返回第一个找到的字段路径
+fn find_secret_key(value: &toml::Value, prefix: &str) -> Option<String> {
+    let table = value.as_table()?;
+    for (key, value) in table {
+        let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        if SECRET_KEYS.contains(&key.as_str()) {
+            return Some(path);
+        }
+        if let Some(found) = find_secret_key(value, &path) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// 解析仓库配置中的提示词文件路径，只允许指向仓库内的文件
+///
+/// 仓库配置随代码一起分发，绝对路径、`..` 或指向仓库外的符号链接会让工具读取本机的任意文件并发送给 AI 服务，
+/// 这类路径会被忽略并给出警告。
+fn prompt_path(root: &Path, prompt: &Path) -> Option<PathBuf> {
+    let joined = root.join(prompt);
+    let escapes = prompt.components().any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+        || match (root.canonicalize(), joined.canonicalize()) {
+            (Ok(root), Ok(canonical)) => !canonical.starts_with(root),
+            // 文件不存在时读取提示词会报错，这里只检查路径本身
+            _ => false,
+        };
+    if escapes {
+        warn!("仓库配置中的提示词文件不在仓库目录内，已忽略: {}", prompt.display());
+        return None;
+    }
+    Some(joined)
+}
+
+impl RepoConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("无法读取仓库配置: {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("仓库配置格式错误: {}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let value: toml::Value = toml::from_str(content)?;
+        if let Some(key) = find_secret_key(&value, "") {
+            return Err(anyhow::anyhow!(
+                "仓库配置中不允许包含 {}，API Key 等敏感信息请保存在全局配置中", key));
+        }
+        Ok(value.try_into()?)
+    }
+
+    /// 将仓库配置叠加到全局配置上，并记录被覆盖的配置项
+    pub fn apply(self, config: &mut Config, path: &Path) -> Result<()> {
+        if let Some(name) = &self.default_service {
+            let service = config.services.iter()
+                .find(|s| s.id == *name || s.display_name().eq_ignore_ascii_case(name))
+                .ok_or_else(|| anyhow::anyhow!("仓库配置指定的 AI 服务不存在: {}", name))?;
+            config.default_service_id = service.id.clone();
+            config.origin.mark_repo("default_service_id");
+        }
+
+        // 两个语言选项互斥，设置其中一个时同时覆盖另一个
+        if let Some(only_chinese) = self.only_chinese {
+            config.only_chinese = only_chinese;
+            if only_chinese {
+                config.only_english = false;
+                config.origin.mark_repo("only_english");
+            }
+            config.origin.mark_repo("only_chinese");
+        }
+        if let Some(only_english) = self.only_english {
+            config.only_english = only_english;
+            if only_english {
+                config.only_chinese = false;
+                config.origin.mark_repo("only_chinese");
+            }
+            config.origin.mark_repo("only_english");
+        }
+
+        if let Some(ai_review) = self.ai_review {
+            config.ai_review = ai_review;
+            config.origin.mark_repo("ai_review");
+        }
+        if let Some(timeout_seconds) = self.timeout_seconds {
+            config.timeout_seconds = timeout_seconds;
+            config.origin.mark_repo("timeout_seconds");
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            config.max_tokens = max_tokens;
+            config.origin.mark_repo("max_tokens");
+        }
+        let root = path.parent().unwrap_or(Path::new("."));
+        if let Some(review_prompt) = self.review_prompt.and_then(|prompt| prompt_path(root, &prompt)) {
+            config.review_prompt = Some(review_prompt);
+            config.origin.mark_repo("review_prompt");
+        }
+        if let Some(commit_prompt) = self.commit_prompt.and_then(|prompt| prompt_path(root, &prompt)) {
+            config.commit_prompt = Some(commit_prompt);
+            config.origin.mark_repo("commit_prompt");
+        }
+        if let Some(learn_style) = self.learn_style {
+            config.learn_style = learn_style;
+            config.origin.mark_repo("learn_style");
+        }
+        if let Some(commit_types) = self.commit_types {
+            config.commit_types = commit_types;
+            config.origin.mark_repo("commit_types");
+        }
+        if !self.scopes.is_empty() {
+            config.scopes.extend(self.scopes);
+            config.origin.mark_repo("scopes");
+        }
+        if let Some(lint) = self.lint {
+            config.lint = lint;
+            config.origin.mark_repo("lint");
+        }
+        if let Some(change_id) = self.change_id {
+            config.change_id = Some(change_id);
+            config.origin.mark_repo("change_id");
+        }
+        if !self.diff_ignore.is_empty() {
+            config.diff_ignore.extend(self.diff_ignore);
+            config.origin.mark_repo("diff_ignore");
+        }
+
+        config.origin.repo_path = Some(path.to_path_buf());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AIService, AIServiceConfig, ConfigSource};
+
+    fn global_config() -> Config {
+        let mut config = Config::new();
+        let mut service = AIServiceConfig::new(AIService::Custom);
+        service.id = "service_1".to_string();
+        service.name = Some("Internal Gateway".to_string());
+        config.services.push(service);
+        config.diff_ignore.push("*.snap".to_string());
+        config
+    }
+
+    #[test]
+    fn test_rejects_secrets() {
+        let err = RepoConfig::parse("api_key = \"sk-123\"").unwrap_err();
+        assert!(err.to_string().contains("api_key"));
+
+        let err = RepoConfig::parse("[[services]]\nservice = \"OpenAI\"\n").unwrap_err();
+        assert!(err.to_string().contains("services"));
+
+        let err = RepoConfig::parse("[gerrit]\nusername = \"me\"\n").unwrap_err();
+        assert!(err.to_string().contains("gerrit"));
+    }
+
+    #[test]
+    fn test_rejects_unknown_fields() {
+        assert!(RepoConfig::parse("only_chineese = true").is_err());
+    }
+
+    #[test]
+    fn test_apply_overrides() {
+        let repo = RepoConfig::parse(r#"
+default_service = "internal gateway"
+only_english = true
+max_tokens = 4096
+review_prompt = "docs/review.md"
+commit_prompt = ".github/commit_prompt.txt"
+commit_types = ["feat", "fix", "perf"]
+change_id = true
+diff_ignore = ["proto/gen/"]
+
+[scopes]
+"panels/dock/" = "dock"
+"#).unwrap();
+
+        let mut config = global_config();
+        config.only_chinese = true;
+        repo.apply(&mut config, Path::new("/repo/.git-commit-helper.toml")).unwrap();
+
+        assert_eq!(config.default_service_id, "service_1");
+        assert!(config.only_english);
+        assert!(!config.only_chinese);
+        assert_eq!(config.max_tokens, 4096);
+        assert_eq!(config.review_prompt, Some(PathBuf::from("/repo/docs/review.md")));
+        assert_eq!(config.commit_prompt, Some(PathBuf::from("/repo/.github/commit_prompt.txt")));
+        assert_eq!(config.diff_ignore, vec!["*.snap", "proto/gen/"]);
+        assert_eq!(config.commit_types, vec!["feat", "fix", "perf"]);
+        assert_eq!(config.change_id, Some(true));
+        assert_eq!(config.scopes.get("panels/dock/").map(String::as_str), Some("dock"));
+
+        let origin = &config.origin;
+        assert_eq!(origin.repo_path, Some(PathBuf::from("/repo/.git-commit-helper.toml")));
+        assert_eq!(origin.source("only_chinese"), ConfigSource::Repo);
+        assert_eq!(origin.source("max_tokens"), ConfigSource::Repo);
+        assert_eq!(origin.source("timeout_seconds"), ConfigSource::Default);
+        assert!(config.save().is_err());
+    }
+
+    #[test]
+    fn test_prompt_outside_repo() {
+        let repo = RepoConfig::parse("review_prompt = \"/etc/passwd\"\ncommit_prompt = \"../../x\"").unwrap();
+        let mut config = global_config();
+        repo.apply(&mut config, Path::new("/repo/.git-commit-helper.toml")).unwrap();
+        assert_eq!(config.review_prompt, None);
+        assert_eq!(config.commit_prompt, None);
+        assert_eq!(config.origin.source("review_prompt"), ConfigSource::Default);
+
+        // 指向仓库外的符号链接同样会被忽略
+        let dir = std::env::temp_dir().join(format!("gch-repo-config-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        std::fs::write(dir.join("docs/review.md"), "review").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("/etc/passwd", dir.join("docs/link.md")).unwrap();
+        assert_eq!(prompt_path(&dir, Path::new("docs/review.md")), Some(dir.join("docs/review.md")));
+        #[cfg(unix)]
+        assert_eq!(prompt_path(&dir, Path::new("docs/link.md")), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_apply_unknown_service() {
+        let repo = RepoConfig::parse("default_service = \"missing\"").unwrap();
+        assert!(repo.apply(&mut global_config(), Path::new(REPO_CONFIG_FILE)).is_err());
+    }
+}
@@ -2,6 +2,8 @@ use anyhow::Result;
 use std::process::Command;
 use crate::config::Config;
 use crate::ai_service;
+use crate::diff_budget;
+use crate::diff_filter;
 use crate::github;
 use crate::gerrit;
 use log::{debug, info};
@@ -78,11 +80,8 @@ pub async fn review_remote_changes(config: &Config, url: &str) -> Result<String>
     }
 
     // 代码审查
-    let translator = ai_service::create_translator(config).await?;
-    info!("正在使用 {:?} 服务进行代码审查...", config.default_service);
-
-    let system_prompt = get_review_prompt();
-    let review_result = translator.chat(&system_prompt, &diff).await?;
+    info!("正在使用 {} 服务进行代码审查...", config.default_service_name());
+    let review_result = review_diff(config, &diff).await?;
     review.push_str(&review_result);
 
     // 终端格式化输出
@@ -144,23 +143,39 @@ pub async fn review_changes(config: &Config, no_review: bool) -> Result<Option<S
     }
 
     // 使用配置的 AI 服务进行代码审查
-    let translator = ai_service::create_translator(config).await?;
-    info!("正在使用 {:?} 服务进行代码审查...", config.default_service);
-
-    let system_prompt = get_review_prompt();
-    let review = translator.chat(&system_prompt, &diff).await?;
+    info!("正在使用 {} 服务进行代码审查...", config.default_service_name());
+    let review = review_diff(config, &diff).await?;
 
     Ok(Some(review))
 }
 
+/// 使用默认 AI 服务审查 diff，改动过大时先压缩为摘要，并在审查结果开头说明
+async fn review_diff(config: &Config, diff: &str) -> Result<String> {
+    let service = config.get_default_service()?;
+    let translator = ai_service::create_translator_for_service(service).await?;
+    let system_prompt = get_review_prompt(config);
+
+    let prepared = diff_budget::prepare_diff(
+        translator.as_ref(), service, config.max_tokens, &system_prompt, diff).await?;
+    let review = ai_service::chat_with_live_output(translator.as_ref(), &system_prompt, &prepared.content).await?;
+
+    if prepared.condensed {
+        return Ok(format!("注意：改动过大，以下审查基于分块生成的改动摘要，可能遗漏细节。\n\n{}", review));
+    }
+    Ok(review)
+}
+
 // 构建代码审查提示语
-fn get_review_prompt() -> String {
-    // 获取配置文件路径
-    let prompt_path = crate::config::Config::config_path()
-        .expect("无法获取配置目录")
-        .parent()
-        .expect("无法获取父目录")
-        .join("review_prompt.txt");
+fn get_review_prompt(config: &Config) -> String {
+    // 优先使用配置中指定的提示词文件，否则使用配置目录下的 review_prompt.txt
+    let prompt_path = match &config.review_prompt {
+        Some(path) => crate::config::Config::prompt_path(path),
+        None => crate::config::Config::config_path()
+            .expect("无法获取配置目录")
+            .parent()
+            .expect("无法获取父目录")
+            .join("review_prompt.txt"),
+    };
 
     // 如果文件存在则读取，否则使用默认提示语
     if prompt_path.exists() {
@@ -170,6 +185,9 @@ fn get_review_prompt() -> String {
             DEFAULT_REVIEW_PROMPT.to_string()
         })
     } else {
+        if config.review_prompt.is_some() {
+            log::warn!("审查提示词文件不存在: {}，使用默认提示词", prompt_path.display());
+        }
         DEFAULT_REVIEW_PROMPT.to_string()
     }
 }
@@ -215,7 +233,7 @@ fn get_staged_changes() -> Result<String> {
         return Err(anyhow::anyhow!("获取暂存区改动失败"));
     }
 
-    Ok(String::from_utf8(output.stdout)?)
+    Ok(diff_filter::filter_diff(&String::from_utf8(output.stdout)?))
 }
 
 pub fn should_skip_review(message: &str) -> bool {
@@ -235,17 +253,13 @@ pub async fn review_local_commit(config: &Config, commit_id: &str) -> Result<Str
     }
 
     // 代码审查
-    info!("正在使用 {:?} 服务进行代码审查...", config.default_service);
-    let mut review = String::new();
-    let translator = ai_service::create_translator(config).await?;
-    let system_prompt = get_review_prompt();
-    let review_result = translator.chat(&system_prompt, &diff).await?;
-    review.push_str(&review_result);
+    info!("正在使用 {} 服务进行代码审查...", config.default_service_name());
+    let review = review_diff(config, &diff).await?;
 
     Ok(review)
 }
 
-fn get_commit_diff(commit_id: &str) -> Result<String> {
+pub(crate) fn get_commit_diff(commit_id: &str) -> Result<String> {
     let output = Command::new("git")
         .args(&["show", "--pretty=format:", commit_id])
         .output()?;
@@ -0,0 +1,316 @@
+// 批量改写提交信息
+// 为 `<base>..<head>` 范围内的每个提交根据各自的 diff 重新生成提交信息，以新旧对照表的形式确认后，
+// 使用 commit-tree 按原有的树和作者信息重建提交并更新分支，不需要交互式 rebase。
+// 原有的 Change-Id 等尾部字段会保留，原分支备份在 refs/commit-helper/reword/ 下，合并、还原等提交保持不变。
+
+use crate::ai_service;
+use crate::commit::{self, MessageContext, PreparedSession};
+use crate::commit_session;
+use crate::commit_template::{LanguageMode, Sections};
+use crate::config;
+use crate::diff_filter;
+use crate::gerrit;
+use crate::review;
+use crate::terminal_format::Style;
+use anyhow::{Context, Result};
+use dialoguer::console::{pad_str, Alignment, Term};
+use dialoguer::Select;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 备份引用的前缀
+const BACKUP_REF_PREFIX: &str = "refs/commit-helper/reword";
+
+/// 确认改写前可选的操作
+const MENU_ITEMS: [&str; 5] = [
+    "确认改写",
+    "查看完整的提交信息",
+    "在编辑器中修改",
+    "保留原提交信息",
+    "取消",
+];
+
+/// 范围内的一个提交
+struct Entry {
+    commit: String,
+    old: String,
+    /// 新的提交信息，None 表示保持原提交信息
+    new: Option<String>,
+}
+
+fn git(args: &[&str]) -> Result<String> {
+    git_with(args, &[], None)
+}
+
+/// 执行 git 命令，`envs` 为额外的环境变量，`input` 作为标准输入
+fn git_with(args: &[&str], envs: &[(&str, &str)], input: Option<&str>) -> Result<String> {
+    let mut child = Command::new("git")
+        .args(args)
+        .envs(envs.iter().copied())
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("无法执行 git 命令")?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("git {} 执行失败: {}", args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+}
+
+/// 解析 `<base>..<head>`，head 省略时为 HEAD，返回 base 和要改写的分支
+fn parse_range(range: &str) -> Result<(String, String)> {
+    let (base, head) = range.split_once("..")
+        .filter(|(base, head)| !base.is_empty() && !head.starts_with('.'))
+        .ok_or_else(|| anyhow::anyhow!("无效的提交范围: {}，请使用 <base>..<head> 格式", range))?;
+    Ok((base.to_string(), if head.is_empty() { "HEAD".to_string() } else { head.to_string() }))
+}
+
+/// head 对应的分支引用，只能改写分支
+fn branch_ref(head: &str) -> Result<String> {
+    let name = git(&["rev-parse", "--symbolic-full-name", head]).unwrap_or_default();
+    if name.starts_with("refs/heads/") {
+        Ok(name)
+    } else {
+        Err(anyhow::anyhow!("{} 不是本地分支，只能改写分支上的提交", head))
+    }
+}
+
+fn short(commit: &str) -> &str {
+    &commit[..commit.len().min(7)]
+}
+
+fn title(message: &str) -> &str {
+    message.lines().next().unwrap_or_default()
+}
+
+/// 对照表的一行，新旧标题各占一半宽度，过长时截断
+fn table_row(commit: &str, old: &str, new: &str, width: usize) -> String {
+    let column = width.saturating_sub(13).max(20) / 2;
+    format!("{} │ {} │ {}",
+        pad_str(commit, 7, Alignment::Left, None),
+        pad_str(old, column, Alignment::Left, Some("…")),
+        pad_str(new, column, Alignment::Left, Some("…")))
+}
+
+fn print_table(entries: &[Entry]) {
+    let width = Term::stdout().size_checked().map_or(120, |(_, columns)| columns as usize);
+    println!();
+    print!("{}", Style::title(&table_row("提交", "原标题", "新标题", width)));
+    for entry in entries {
+        let row = match &entry.new {
+            Some(new) => table_row(short(&entry.commit), title(&entry.old), title(new), width),
+            None => table_row(short(&entry.commit), title(&entry.old), "（保持不变）", width),
+        };
+        print!("{}", Style::plain(&row));
+    }
+}
+
+fn select_entry(prompt: &str, entries: &[Entry]) -> Result<usize> {
+    let items: Vec<String> = entries.iter()
+        .map(|entry| format!("{} {}", short(&entry.commit), title(&entry.old)))
+        .collect();
+    Ok(Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt(prompt)
+        .items(&items)
+        .default(0)
+        .interact()?)
+}
+
+/// 展示新旧对照表，用户可以查看、修改或保留原提交信息，确认后返回 true
+fn confirm(entries: &mut [Entry]) -> Result<bool> {
+    loop {
+        print_table(entries);
+        if entries.iter().all(|entry| entry.new.is_none()) {
+            println!("{}", Style::yellow("没有需要改写的提交"));
+            return Ok(false);
+        }
+
+        match Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+            .with_prompt("是否按新的提交信息改写？")
+            .items(&MENU_ITEMS)
+            .default(0)
+            .interact()?
+        {
+            0 => return Ok(true),
+            1 => {
+                let entry = &entries[select_entry("请选择要查看的提交", entries)?];
+                println!("\n{}", Style::title("原提交信息:"));
+                println!("{}", entry.old);
+                println!("\n{}", Style::title("新提交信息:"));
+                println!("{}", entry.new.as_deref().unwrap_or("（保持不变）"));
+            }
+            2 => {
+                let index = select_entry("请选择要修改的提交", entries)?;
+                let entry = &mut entries[index];
+                match commit_session::edit_in_editor(entry.new.as_deref().unwrap_or(&entry.old))? {
+                    Some(edited) if edited.trim() != entry.old.trim() => entry.new = Some(edited),
+                    Some(_) => entry.new = None,
+                    None => println!("{}", Style::yellow("提交信息为空，保留修改前的内容")),
+                }
+            }
+            3 => {
+                let index = select_entry("请选择保留原提交信息的提交", entries)?;
+                entries[index].new = None;
+            }
+            _ => return Ok(false),
+        }
+    }
+}
+
+/// 按顺序重建提交，保留原有的树和作者信息，返回新的分支末端
+fn rewrite(base: &str, entries: &[Entry]) -> Result<String> {
+    let mut parent = base.to_string();
+    let mut changed = false;
+    for entry in entries {
+        // 第一个需要改写的提交之前的提交保持原样
+        if !changed && entry.new.is_none() {
+            parent = entry.commit.clone();
+            continue;
+        }
+        changed = true;
+
+        let author = git(&["show", "-s", "--date=raw", "--format=%an%x00%ae%x00%ad", &entry.commit])?;
+        let author: Vec<&str> = author.split('\0').collect();
+        let [name, email, date] = author[..] else {
+            return Err(anyhow::anyhow!("无法读取提交 {} 的作者信息", short(&entry.commit)));
+        };
+        let tree = format!("{}^{{tree}}", entry.commit);
+        let message = format!("{}\n", entry.new.as_deref().unwrap_or(&entry.old).trim_end());
+        parent = git_with(
+            &["commit-tree", &tree, "-p", &parent, "-F", "-"],
+            &[("GIT_AUTHOR_NAME", name), ("GIT_AUTHOR_EMAIL", email), ("GIT_AUTHOR_DATE", date)],
+            Some(&message),
+        )?;
+    }
+    Ok(parent)
+}
+
+/// 把改写前的分支备份到 `refs/commit-helper/reword/<分支>/<时间戳>`，同一秒内多次改写时追加序号
+///
+/// 创建引用时要求其不存在（旧值为全零），不会覆盖已有的备份。
+fn backup(branch: &str, old_head: &str) -> Result<String> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let prefix = format!("{}/{}/{}", BACKUP_REF_PREFIX, branch.trim_start_matches("refs/heads/"), now);
+    let zero = "0".repeat(old_head.len());
+    let mut name = prefix.clone();
+    let mut attempt = 0;
+    while git(&["rev-parse", "--verify", "--quiet", &name]).is_ok() {
+        attempt += 1;
+        name = format!("{}-{}", prefix, attempt);
+    }
+    git(&["update-ref", &name, old_head, &zero])?;
+    Ok(name)
+}
+
+/// 改写 `<base>..<head>` 范围内提交的提交信息
+pub async fn run(range: &str) -> Result<()> {
+    let (base, head) = parse_range(range)?;
+    let branch = branch_ref(&head)?;
+    let base = git(&["rev-parse", "--verify", &format!("{}^{{commit}}", base)])
+        .map_err(|_| anyhow::anyhow!("无效的提交: {}", base))?;
+    let old_head = git(&["rev-parse", "--verify", &branch])?;
+    if git(&["merge-base", "--is-ancestor", &base, &old_head]).is_err() {
+        return Err(anyhow::anyhow!("{} 不是 {} 的祖先提交", short(&base), head));
+    }
+    let range = format!("{}..{}", base, old_head);
+    if !git(&["rev-list", "--merges", &range])?.is_empty() {
+        return Err(anyhow::anyhow!("范围内包含合并提交，不支持改写"));
+    }
+    let commits: Vec<String> = git(&["rev-list", "--reverse", &range])?.lines().map(str::to_string).collect();
+    if commits.is_empty() {
+        return Err(anyhow::anyhow!("范围 {} 内没有提交", range));
+    }
+
+    let config = config::Config::load()?;
+    let service = config.get_default_service()?;
+    let translator = ai_service::create_translator_for_service(service).await?;
+    let context = MessageContext {
+        config: &config,
+        translator: translator.as_ref(),
+        mode: LanguageMode::determine(config.only_chinese, config.only_english),
+        sections: Sections::default(),
+        commit_type: None,
+        reference: None,
+        review: None,
+        learn_style: config.learn_style,
+        candidates: None,
+        amend: false,
+        trailers: Vec::new(),
+    };
+
+    let mut entries = Vec::new();
+    for (i, commit) in commits.iter().enumerate() {
+        let old = git(&["show", "-s", "--format=%B", commit])?;
+        let label = format!("[{}/{}] {} {}", i + 1, commits.len(), short(commit), title(&old));
+        let diff = diff_filter::filter_diff(&review::get_commit_diff(commit)?);
+        if review::should_skip_review(title(&old)) || diff.trim().is_empty() {
+            println!("{}", Style::plain(&format!("{}：跳过", label)));
+            entries.push(Entry { commit: commit.clone(), old, new: None });
+            continue;
+        }
+
+        println!("{}", Style::plain(&format!("{}：正在生成提交信息...", label)));
+        let files: Vec<String> = git(&["show", "--name-only", "--format=", commit])?
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect();
+        let generated = match commit::prepare_session(&context, &diff, &files, None).await {
+            Ok(PreparedSession { mut session, scope, .. }) => session.generate(None).await
+                .map(|message| commit::finalize_message(&message, None, scope.as_deref(), None, &commit::kept_trailers(&old)))
+                .and_then(|message| gerrit::ensure_change_id(&config, &message)),
+            Err(e) => Err(e),
+        };
+        let new = match generated {
+            Ok(new) => Some(new),
+            Err(e) => {
+                println!("{}", Style::red(&format!("生成失败，保留原提交信息: {}", e)));
+                None
+            }
+        };
+        entries.push(Entry { commit: commit.clone(), old, new });
+    }
+
+    if !confirm(&mut entries)? {
+        println!("已取消改写");
+        return Ok(());
+    }
+
+    let new_head = rewrite(&base, &entries)?;
+    let backup = backup(&branch, &old_head)?;
+    // 分支在此期间被修改时更新失败，不会覆盖其他改动
+    git(&["update-ref", "-m", &format!("commit-helper: reword {}", range), &branch, &new_head, &old_head])?;
+
+    let count = entries.iter().filter(|entry| entry.new.is_some()).count();
+    println!("{}", Style::green(&format!("已改写 {} 个提交的提交信息", count)));
+    println!("原分支已备份到 {}，可以使用 git update-ref {} {} 恢复", backup, branch, backup);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("origin/master..HEAD").unwrap(), ("origin/master".to_string(), "HEAD".to_string()));
+        assert_eq!(parse_range("main..").unwrap(), ("main".to_string(), "HEAD".to_string()));
+        assert!(parse_range("HEAD~3").is_err());
+        assert!(parse_range("..HEAD").is_err());
+        assert!(parse_range("main...dev").is_err());
+    }
+
+    #[test]
+    fn test_table_row() {
+        let row = table_row("abc1234", "wip", "feat(dock): 新增托盘插件加载", 53);
+        assert_eq!(row, "abc1234 │ wip                  │ feat(dock): 新增托…");
+        assert_eq!(table_row("提交", "原标题", "新标题", 33), "提交    │ 原标题     │ 新标题    ");
+    }
+}
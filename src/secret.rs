@@ -0,0 +1,137 @@
+// 密钥引用解析
+// 配置文件中的 API Key、Gerrit 密码等字段除了直接填写明文，还可以填写引用，加载配置时再解析为实际值：
+//   env:OPENAI_API_KEY           读取环境变量
+//   cmd:pass show openai         执行命令并使用其标准输出
+//   keyring:git-commit-helper/openai  从系统密钥环读取（Linux 使用 secret-tool，macOS 使用 security）
+
+use anyhow::{Context, Result};
+use log::debug;
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::Mutex;
+
+/// 同一进程中会多次加载配置，缓存解析结果，避免重复执行命令或弹出密钥环授权
+static CACHE: Mutex<Option<HashMap<String, String>>> = Mutex::new(None);
+
+/// 判断字段值是否为密钥引用
+pub fn is_reference(value: &str) -> bool {
+    ["env:", "cmd:", "keyring:"].iter().any(|prefix| value.starts_with(prefix))
+}
+
+/// 解析密钥引用，非引用的值原样返回
+pub fn resolve(value: &str) -> Result<String> {
+    if !is_reference(value) {
+        return Ok(value.to_string());
+    }
+
+    if let Some(cached) = CACHE.lock().unwrap().as_ref().and_then(|cache| cache.get(value)) {
+        return Ok(cached.clone());
+    }
+
+    debug!("解析密钥引用: {}", value);
+    let resolved = if let Some(name) = value.strip_prefix("env:") {
+        std::env::var(name.trim()).with_context(|| format!("环境变量 {} 未设置", name.trim()))?
+    } else if let Some(command) = value.strip_prefix("cmd:") {
+        run_command(command.trim())?
+    } else if let Some(entry) = value.strip_prefix("keyring:") {
+        read_keyring(entry.trim())?
+    } else {
+        unreachable!()
+    };
+
+    if resolved.is_empty() {
+        return Err(anyhow::anyhow!("密钥引用 {} 解析结果为空", value));
+    }
+    CACHE.lock().unwrap().get_or_insert_with(HashMap::new).insert(value.to_string(), resolved.clone());
+    Ok(resolved)
+}
+
+/// 用于展示的脱敏值：引用原样显示，明文只保留首尾少量字符
+pub fn redact(value: &str) -> String {
+    if value.is_empty() {
+        return "(未设置)".to_string();
+    }
+    if is_reference(value) {
+        return value.to_string();
+    }
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() <= 12 {
+        return "****".to_string();
+    }
+    let head: String = chars[..3].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}****{}", head, tail)
+}
+
+fn run_command(command: &str) -> Result<String> {
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let output = Command::new(shell)
+        .args([flag, command])
+        .output()
+        .with_context(|| format!("执行命令失败: {}", command))?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("命令 {} 执行失败: {}",
+            command, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    // 与 pass 等工具保持一致，只取第一行
+    Ok(String::from_utf8_lossy(&output.stdout).lines().next().unwrap_or_default().trim().to_string())
+}
+
+/// 读取系统密钥环，引用格式为 `keyring:<service>/<account>`，省略 account 时使用当前用户名
+fn read_keyring(entry: &str) -> Result<String> {
+    let (service, account) = match entry.split_once('/') {
+        Some((service, account)) => (service.to_string(), account.to_string()),
+        None => (entry.to_string(), std::env::var("USER").unwrap_or_default()),
+    };
+    if service.is_empty() {
+        return Err(anyhow::anyhow!("无效的密钥环引用: keyring:{}", entry));
+    }
+
+    let output = if cfg!(target_os = "macos") {
+        Command::new("security")
+            .args(["find-generic-password", "-s", &service, "-a", &account, "-w"])
+            .output()
+            .context("无法执行 security 命令")?
+    } else {
+        Command::new("secret-tool")
+            .args(["lookup", "service", &service, "account", &account])
+            .output()
+            .context("无法执行 secret-tool 命令，请安装 libsecret-tools")?
+    };
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("密钥环中未找到 service={} account={} 的条目", service, account));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim_end_matches(['\r', '\n']).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_plain_and_env() {
+        assert_eq!(resolve("sk-plain").unwrap(), "sk-plain");
+
+        std::env::set_var("GIT_COMMIT_HELPER_TEST_SECRET", "sk-from-env");
+        assert_eq!(resolve("env:GIT_COMMIT_HELPER_TEST_SECRET").unwrap(), "sk-from-env");
+        assert!(resolve("env:GIT_COMMIT_HELPER_TEST_MISSING").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_command() {
+        assert_eq!(resolve("cmd:printf 'sk-from-cmd\\nsecond line'").unwrap(), "sk-from-cmd");
+        assert!(resolve("cmd:exit 1").is_err());
+        assert!(resolve("cmd:true").is_err());
+    }
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(redact(""), "(未设置)");
+        assert_eq!(redact("short"), "****");
+        assert_eq!(redact("sk-1234567890abcdef"), "sk-****cdef");
+        assert_eq!(redact("env:OPENAI_API_KEY"), "env:OPENAI_API_KEY");
+    }
+}
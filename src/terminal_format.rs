@@ -1,6 +1,7 @@
 // 终端彩色输出工具模块
 // 用于统一管理ANSI颜色和结构化输出
 
+use dialoguer::console::{measure_text_width, Term};
 use std::io::{self, Write};
 
 pub struct Style;
@@ -66,3 +67,78 @@ pub fn print_progress(msg: &str, percent: Option<u8>) {
     print!("{}", text);
     io::stdout().flush().ok();
 }
+
+/// 流式输出工具：实时显示 AI 返回的内容，结束后可整体擦除
+///
+/// 仅在标准输出为终端时启用，否则所有操作均为空操作。光标无法移动到屏幕顶端之上，
+/// 内容即将超出终端高度时会擦除已输出的内容，改为显示一行等待提示，避免结束后无法完整擦除。
+pub struct StreamPrinter {
+    text: String,
+    enabled: bool,
+    /// 内容过长，已停止实时显示
+    paused: bool,
+}
+
+impl StreamPrinter {
+    pub fn new() -> Self {
+        Self {
+            text: String::new(),
+            enabled: Term::stdout().is_term(),
+            paused: false,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn push(&mut self, token: &str) {
+        if !self.enabled || self.paused || token.is_empty() {
+            return;
+        }
+        // 第一段内容到达时清除进度提示所在的行
+        if self.text.is_empty() {
+            print!("\r\x1b[K");
+        }
+        let (height, width) = Term::stdout().size();
+        if rows(&format!("{}{}", self.text, token), width as usize) >= height as usize {
+            self.clear();
+            self.paused = true;
+            self.text = "内容较长，生成完成后显示...".to_string();
+            print!("{}{}{}", Style::GRAY, self.text, Style::RESET);
+        } else {
+            self.text.push_str(token);
+            print!("{}{}{}", Style::GRAY, token, Style::RESET);
+        }
+        io::stdout().flush().ok();
+    }
+
+    /// 擦除已输出的全部内容，光标回到第一行行首
+    pub fn clear(&mut self) {
+        if !self.enabled || self.text.is_empty() {
+            return;
+        }
+        let rows = rows(&self.text, Term::stdout().size().1 as usize);
+        print!("\r\x1b[2K");
+        for _ in 1..rows {
+            print!("\x1b[1A\x1b[2K");
+        }
+        io::stdout().flush().ok();
+        self.text.clear();
+        self.paused = false;
+    }
+}
+
+/// 文本在指定宽度的终端中占用的行数
+fn rows(text: &str, width: usize) -> usize {
+    let width = width.max(1);
+    text.split('\n')
+        .map(|line| measure_text_width(line).div_ceil(width).max(1))
+        .sum()
+}
+
+impl Default for StreamPrinter {
+    fn default() -> Self {
+        Self::new()
+    }
+}